@@ -0,0 +1,57 @@
+//! Exposes the version of the `typst` crate that is linked into the binary,
+//! as `TYPST_VERSION`.
+//!
+//! The exact version is read from `Cargo.lock`. When there is no lock file,
+//! the version required in `Cargo.toml` is used instead.
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock))
+        .or_else(|| {
+            let manifest = std::fs::read_to_string("Cargo.toml").ok()?;
+            required_version(&manifest)
+        })
+        .expect(
+            "the version of typst should be in Cargo.lock, \
+            or be a plain version requirement in Cargo.toml",
+        );
+    println!("cargo:rustc-env=TYPST_VERSION={version}");
+}
+
+/// Finds the version of `typst` in a lock file.
+fn locked_version(lock: &str) -> Option<String> {
+    let mut lines = lock.lines();
+    lines.find(|line| *line == "name = \"typst\"")?;
+    let version = lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')?;
+    Some(version.to_owned())
+}
+
+/// Finds the version requirement of the `typst` dependency in a manifest,
+/// written either as `typst = "x.y.z"` or as
+/// `typst = { version = "x.y.z", ... }`.
+fn required_version(manifest: &str) -> Option<String> {
+    let requirement = manifest.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "typst").then_some(value.trim())
+    })?;
+    let requirement = match requirement.strip_prefix('{') {
+        Some(table) => table
+            .split(',')
+            .filter_map(|field| field.split_once('='))
+            .find(|(key, _)| key.trim() == "version")?
+            .1
+            .trim(),
+        None => requirement,
+    };
+    let version = requirement.strip_prefix('"')?.strip_suffix('"')?;
+    // Requirements like `^0.12` or `=0.12.0` name the version after an
+    // operator.
+    Some(version.trim_start_matches(['^', '=', '~', ' ']).to_owned())
+}
@@ -8,26 +8,69 @@ use typst::{
 
 use crate::world::SystemWorld;
 
+use self::files::FileInventory;
+
+pub mod archive;
 pub mod authors;
+mod changelog;
 mod compile;
+mod config;
 mod diagnostics;
+pub mod explanations;
 mod file_size;
-mod imports;
+pub mod files;
+mod fonts;
+mod global_rules;
+pub mod imports;
 mod kebab_case;
+mod line_endings;
 mod manifest;
+mod paths;
+mod placeholders;
+mod plugins;
+
+pub use diagnostics::{fatal_error, Diagnostics, Fix, Suggestion, Timings};
 
-pub use diagnostics::Diagnostics;
+/// The version of the Typst compiler used by the checks.
+pub const TYPST_VERSION: &str = env!("TYPST_VERSION");
 
 pub async fn all_checks(
     package_spec: Option<&PackageSpec>,
     package_dir: PathBuf,
     check_authors: bool,
+    font_paths: &[PathBuf],
+    fail_fast: bool,
+    jobs: usize,
 ) -> eyre::Result<(SystemWorld, Diagnostics)> {
     let mut diags = Diagnostics::default();
+    if fail_fast {
+        diags.set_fail_fast();
+    }
+    config::apply(&mut diags, &package_dir);
 
-    let worlds = manifest::check(&package_dir, &mut diags, package_spec).await?;
+    // The files of the package are only listed once, for all the checks.
+    let inventory = FileInventory::for_package(&package_dir)?;
+    diags.lap("file listing");
+
+    let mut worlds = manifest::check(
+        &package_dir,
+        &mut diags,
+        package_spec,
+        font_paths,
+        &inventory,
+    )
+    .await?;
+    if diags.should_stop() {
+        return Ok((worlds.package, diags));
+    }
     compile::check(&mut diags, &worlds.package);
-    if let Some(template_world) = worlds.template {
+    diags.lap("compilation");
+    if diags.should_stop() {
+        return Ok((worlds.package, diags));
+    }
+    let is_template = worlds.template.is_some();
+    if let Some(mut template_world) = worlds.template {
+        template_world.reset();
         let mut template_diags = Diagnostics::default();
         compile::check(&mut template_diags, &template_world);
         let template_dir = template_world
@@ -35,20 +78,163 @@ pub async fn all_checks(
             .strip_prefix(worlds.package.root())
             .expect("Template should be in a subfolder of the package");
         diags.extend(template_diags, template_dir);
+        diags.lap("template compilation");
+        if diags.should_stop() {
+            return Ok((worlds.package, diags));
+        }
     }
+    worlds.package.reset();
     kebab_case::check(&mut diags, &worlds.package);
+    if !is_template {
+        global_rules::check(&mut diags, &worlds.package);
+    }
+    diags.lap("style");
+    if diags.should_stop() {
+        return Ok((worlds.package, diags));
+    }
 
-    let res = imports::check(&mut diags, package_spec, &package_dir, &worlds.package);
+    let res = imports::check(
+        &mut diags,
+        package_spec,
+        &package_dir,
+        &inventory,
+        &worlds.package,
+        jobs,
+    );
     diags.maybe_emit(res);
+    diags.lap("imports");
+    if diags.should_stop() {
+        return Ok((worlds.package, diags));
+    }
 
     if let Some(spec) = package_spec.filter(|_| check_authors) {
         authors::check(&mut diags, spec);
+        diags.lap("authors");
+    }
+
+    if let Some(spec) = package_spec {
+        changelog::check(&mut diags, spec, &package_dir);
+        diags.lap("changelog");
     }
 
     Ok((worlds.package, diags))
 }
 
+/// Number of threads to use when checking the files of a package, or
+/// packages at the same time.
+///
+/// It can be set with the `JOBS` environment variable, and defaults to the
+/// number of available cores.
+pub fn jobs() -> usize {
+    std::env::var("JOBS")
+        .ok()
+        .and_then(|jobs| jobs.parse().ok())
+        .filter(|jobs| *jobs > 0)
+        .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
+        .unwrap_or(1)
+}
+
 /// Create a label for a span.
 fn label(world: &SystemWorld, span: Span) -> Option<Label<FileId>> {
     Some(Label::primary(span.id()?, world.range(span)?))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// Copies a fixture to a temporary directory, without some of its files.
+    fn copy_fixture(name: &str, skip: &[&str]) -> PathBuf {
+        fn copy_dir(from: &Path, to: &Path, skip: &[&str]) {
+            std::fs::create_dir_all(to).unwrap();
+            for entry in std::fs::read_dir(from).unwrap() {
+                let entry = entry.unwrap();
+                let name = entry.file_name();
+                if skip.iter().any(|skipped| name == *skipped) {
+                    continue;
+                }
+                if entry.file_type().unwrap().is_dir() {
+                    copy_dir(&entry.path(), &to.join(&name), skip);
+                } else {
+                    std::fs::copy(entry.path(), to.join(&name)).unwrap();
+                }
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        copy_dir(&fixture(name), &dir, skip);
+        dir
+    }
+
+    async fn codes(package_dir: PathBuf) -> Vec<String> {
+        crate::http::set_offline();
+        let (_, diags) = all_checks(None, package_dir, false, &[], false, 1)
+            .await
+            .unwrap();
+        diags.stats().by_code.keys().cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn disabled_codes_are_not_reported() {
+        let codes = codes(fixture("disabled-codes")).await;
+        assert!(!codes.iter().any(|code| code == "import/relative"));
+        assert!(!codes.iter().any(|code| code == "kebab-case/parameter"));
+        assert!(codes.iter().any(|code| code == "kebab-case/value"));
+    }
+
+    #[tokio::test]
+    async fn codes_are_reported_without_configuration() {
+        let dir = copy_fixture("disabled-codes", &["typst-check.toml"]);
+        let codes = codes(dir.clone()).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(codes.iter().any(|code| code == "import/relative"));
+        assert!(codes.iter().any(|code| code == "kebab-case/parameter"));
+    }
+
+    #[tokio::test]
+    async fn templates_cant_read_the_files_of_their_package() {
+        let codes = codes(fixture("template-asset")).await;
+        assert!(codes
+            .iter()
+            .any(|code| code == "template/asset-outside-template"));
+        assert!(!codes
+            .iter()
+            .any(|code| code == "compile/path-outside-package"));
+    }
+
+    #[tokio::test]
+    async fn packages_are_walked_once() {
+        let walks = files::WALKS.with(|walks| walks.get());
+        codes(fixture("template-asset")).await;
+        assert_eq!(files::WALKS.with(|walks| walks.get()) - walks, 1);
+    }
+
+    #[tokio::test]
+    async fn fonts_are_only_searched_for_compilation() {
+        crate::http::set_offline();
+        let mut diags = Diagnostics::default();
+        let dir = fixture("disabled-codes");
+        let inventory = FileInventory::for_package(&dir).unwrap();
+        let worlds = manifest::check(&dir, &mut diags, None, &[], &inventory)
+            .await
+            .unwrap();
+        assert!(!worlds.package.searched_fonts());
+        assert!(!worlds.template.is_some_and(|t| t.searched_fonts()));
+
+        compile::check(&mut diags, &worlds.package);
+        assert!(worlds.package.searched_fonts());
+    }
+}
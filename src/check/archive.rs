@@ -0,0 +1,97 @@
+//! Checking the archive of a package, as it would be published, instead of
+//! its directory.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use eyre::Context;
+
+/// Whether a path looks like an archive that can be checked.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz") || name.ends_with(".zip")
+}
+
+/// The contents of an archive, extracted to a temporary directory.
+///
+/// The directory is deleted when this value is dropped.
+pub struct ExtractedArchive {
+    dir: PathBuf,
+}
+
+impl ExtractedArchive {
+    /// Extracts a `.tar.gz` archive.
+    ///
+    /// Entries that are not regular files or directories, or that would be
+    /// extracted outside of the temporary directory, are rejected.
+    pub fn extract(archive: &Path) -> eyre::Result<Self> {
+        if archive.extension().is_some_and(|ext| ext == "zip") {
+            eyre::bail!(
+                "Only .tar.gz archives are supported, packages are published in this format."
+            );
+        }
+
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).context("Failed to create a temporary directory")?;
+        // From now on, the directory is removed even if the extraction fails.
+        let extracted = ExtractedArchive { dir };
+
+        let file = std::fs::File::open(archive)
+            .with_context(|| format!("Failed to open {}", archive.display()))?;
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        for entry in tar.entries().context("Failed to read the archive")? {
+            let mut entry = entry.context("Failed to read the archive")?;
+            let path = entry
+                .path()
+                .context("Invalid path in the archive")?
+                .into_owned();
+            if !path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                eyre::bail!(
+                    "The archive contains a path that would be outside of the package: {}",
+                    path.display()
+                );
+            }
+            let kind = entry.header().entry_type();
+            if !kind.is_file() && !kind.is_dir() {
+                eyre::bail!(
+                    "The archive contains something else than files and directories: {}",
+                    path.display()
+                );
+            }
+            entry
+                .unpack_in(&extracted.dir)
+                .with_context(|| format!("Failed to extract {}", path.display()))?;
+        }
+
+        Ok(extracted)
+    }
+
+    /// The directory of the package: the root of the archive, or its only
+    /// directory if the files are in a subdirectory.
+    pub fn package_dir(&self) -> PathBuf {
+        if self.dir.join("typst.toml").exists() {
+            return self.dir.clone();
+        }
+        let mut entries = std::fs::read_dir(&self.dir).into_iter().flatten().flatten();
+        match (entries.next(), entries.next()) {
+            (Some(entry), None) if entry.path().join("typst.toml").exists() => entry.path(),
+            _ => self.dir.clone(),
+        }
+    }
+}
+
+impl Drop for ExtractedArchive {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
@@ -11,6 +11,7 @@ pub fn check(diags: &mut Diagnostics, spec: &PackageSpec) -> Option<()> {
 
         diags.emit(
                 Diagnostic::warning()
+                    .with_code("authors/changed")
                     .with_labels(vec![Label::primary(manifest, 0..0)])
                     .with_message(
                         "The authors of this version are not the same as those of the previous one (according to Git)."
@@ -21,8 +22,9 @@ pub fn check(diags: &mut Diagnostics, spec: &PackageSpec) -> Option<()> {
     Some(())
 }
 
-pub fn commit_for_previous_version(spec: &PackageSpec) -> Option<String> {
-    let last_manifest = spec.previous_version()?.directory().join("typst.toml");
+/// The last commit that changed the manifest of a package.
+pub fn commit_for_version(spec: &PackageSpec) -> Option<String> {
+    let last_manifest = spec.directory().join("typst.toml");
 
     let repo = git::repo_dir();
     let repo = git::GitRepo::open(&repo);
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use typst::syntax::{package::PackageSpec, FileId, VirtualPath};
+
+use crate::package::PackageExt;
+
+use super::Diagnostics;
+
+/// Warn when an update doesn't say what changed, either in a changelog or in
+/// a section of the README.
+///
+/// This is a convention more than a rule, so this is never an error.
+pub fn check(diags: &mut Diagnostics, spec: &PackageSpec, package_dir: &Path) -> Option<()> {
+    spec.previous_version()?;
+
+    let version = spec.version.to_string();
+    let mut documented = false;
+    for entry in std::fs::read_dir(package_dir).ok()?.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_uppercase();
+        let is_changelog = file_name.starts_with("CHANGELOG") || file_name.starts_with("CHANGES");
+        let is_readme = file_name.starts_with("README");
+        if !is_changelog && !is_readme {
+            continue;
+        }
+
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        documented |= if is_changelog {
+            mentions_version(&contents, &version)
+        } else {
+            contents
+                .lines()
+                .filter(|line| line.trim_start().starts_with('#'))
+                .any(|heading| mentions_version(heading, &version))
+        };
+    }
+
+    if documented {
+        return Some(());
+    }
+
+    let manifest_path = package_dir.join("typst.toml");
+    let manifest_contents = std::fs::read_to_string(manifest_path).ok()?;
+    let manifest = toml_edit::ImDocument::parse(&manifest_contents).ok()?;
+    let span = manifest
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.span())
+        .unwrap_or_default();
+
+    diags.emit(
+        Diagnostic::warning()
+            .with_code("docs/no-changelog")
+            .with_labels(vec![Label::primary(
+                FileId::new(None, VirtualPath::new("typst.toml")),
+                span,
+            )])
+            .with_message(format!(
+                "We could not find what changed in version {version}. \
+                Consider adding a CHANGELOG.md file, or a section for this version \
+                in your README, so that users know what to expect when updating."
+            )),
+    );
+
+    Some(())
+}
+
+/// Whether a text contains a version number, possibly prefixed with `v`, and
+/// not as part of a longer version number.
+fn mentions_version(text: &str, version: &str) -> bool {
+    text.match_indices(version).any(|(start, _)| {
+        let end = start + version.len();
+        let is_version_char = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit() || c == '.');
+        // A dot after the version can also end a sentence.
+        let mut after = text[end..].chars();
+        let continues = match after.next() {
+            Some('.') => after.next().is_some_and(|c| c.is_ascii_digit()),
+            c => c.is_some_and(|c| c.is_ascii_digit()),
+        };
+        !is_version_char(text[..start].chars().next_back()) && !continues
+    })
+}
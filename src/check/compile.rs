@@ -22,6 +22,18 @@ pub fn check(diags: &mut Diagnostics, world: &SystemWorld) -> Option<Document> {
     }
 }
 
+/// Code of the diagnostic for files that could not be read because they are
+/// outside of the package.
+const PATH_OUTSIDE_PACKAGE: &str = "compile/path-outside-package";
+
+/// Code of the diagnostic for files that templates read from outside of their
+/// directory.
+const ASSET_OUTSIDE_TEMPLATE: &str = "template/asset-outside-template";
+
+/// Codes of the other diagnostics of the compiler.
+const COMPILER_ERROR: &str = "compile/error";
+const COMPILER_WARNING: &str = "compile/warning";
+
 fn convert_diagnostics<'a>(
     world: &'a SystemWorld,
     iter: impl IntoIterator<Item = SourceDiagnostic> + 'a,
@@ -29,6 +41,55 @@ fn convert_diagnostics<'a>(
     iter.into_iter()
         .filter(|diagnostic| !diagnostic.message.starts_with("unknown font family:"))
         .map(|diagnostic| {
+            // The world recorded which files it refused to read, which gives
+            // more context than the error of the compiler.
+            let outside_root = world.files_outside_root();
+            let requested_paths = || {
+                outside_root
+                    .iter()
+                    .map(|id| {
+                        format!(
+                            "requested path: {}",
+                            id.vpath().as_rootless_path().display()
+                        )
+                    })
+                    .collect()
+            };
+            let access_denied =
+                diagnostic.message.contains("access denied") && !outside_root.is_empty();
+
+            // Symbolic links leaving the template are reported by the world
+            // with its own message.
+            let outside_template = diagnostic
+                .message
+                .contains("is outside of the template directory");
+            if world.is_template() && (access_denied || outside_template) {
+                return Diagnostic::error()
+                    .with_code(ASSET_OUTSIDE_TEMPLATE)
+                    .with_message(
+                        "This file is outside of the template directory. \
+                        It will not be copied by `typst init`, \
+                        so it won't exist in projects created from this template.",
+                    )
+                    .with_labels(label(world, diagnostic.span).into_iter().collect())
+                    .with_notes(if outside_template {
+                        vec![diagnostic.message.to_string()]
+                    } else {
+                        requested_paths()
+                    });
+            }
+
+            if access_denied {
+                return Diagnostic::error()
+                    .with_code(PATH_OUTSIDE_PACKAGE)
+                    .with_message(
+                        "This file is outside of the package. \
+                        Published packages can only read files inside their own directory.",
+                    )
+                    .with_labels(label(world, diagnostic.span).into_iter().collect())
+                    .with_notes(requested_paths());
+            }
+
             let severity = if diagnostic.severity == Severity::Error {
                 "error"
             } else {
@@ -36,8 +97,8 @@ fn convert_diagnostics<'a>(
             };
 
             match diagnostic.severity {
-                Severity::Error => Diagnostic::error(),
-                Severity::Warning => Diagnostic::warning(),
+                Severity::Error => Diagnostic::error().with_code(COMPILER_ERROR),
+                Severity::Warning => Diagnostic::warning().with_code(COMPILER_WARNING),
             }
             .with_message(format!(
                 "The following {} was reported by the Typst compiler: {}",
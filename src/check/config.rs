@@ -0,0 +1,150 @@
+//! Configuration of the checks by the authors of a package.
+
+use std::path::Path;
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use typst::syntax::{FileId, VirtualPath};
+
+use super::{explanations, Diagnostics};
+
+/// Name of the file that can hold the configuration, next to the manifest.
+const CONFIG_FILE: &str = "typst-check.toml";
+
+/// Code of the diagnostic for configurations that can't be used.
+const INVALID_CONFIG: &str = "config/invalid";
+
+/// Disables the diagnostics listed in the configuration of a package.
+///
+/// The configuration is read from `typst-check.toml` if it exists, or from
+/// the `[tool.package-check]` section of the manifest otherwise. Its
+/// `disabled` field is a list of diagnostic codes.
+pub fn apply(diags: &mut Diagnostics, package_dir: &Path) -> Option<()> {
+    let (file_name, contents) = match std::fs::read_to_string(package_dir.join(CONFIG_FILE)) {
+        Ok(contents) => (CONFIG_FILE, contents),
+        Err(_) => (
+            "typst.toml",
+            std::fs::read_to_string(package_dir.join("typst.toml")).ok()?,
+        ),
+    };
+    let file_id = FileId::new(None, VirtualPath::new(file_name));
+    let invalid = |message: String, span: Option<std::ops::Range<usize>>| {
+        Diagnostic::warning()
+            .with_code(INVALID_CONFIG)
+            .with_message(message)
+            .with_labels(vec![Label::primary(file_id, span.unwrap_or_default())])
+            .with_notes(vec!["No diagnostic was disabled.".to_owned()])
+    };
+
+    let document = match toml_edit::ImDocument::parse(&contents) {
+        Ok(document) => document,
+        Err(error) => {
+            // Errors in the manifest are already reported by its own checks.
+            if file_name == CONFIG_FILE {
+                diags.emit(invalid(
+                    format!("This configuration can't be parsed: {}", error.message()),
+                    error.span(),
+                ));
+            }
+            return None;
+        }
+    };
+    let config = if file_name == CONFIG_FILE {
+        document.as_item()
+    } else {
+        document.get("tool")?.get("package-check")?
+    };
+    let disabled_field = config.get("disabled")?;
+    let Some(disabled) = disabled_field.as_array() else {
+        diags.emit(invalid(
+            "`disabled` should be a list of diagnostic codes.".to_owned(),
+            disabled_field.span(),
+        ));
+        return None;
+    };
+
+    let mut codes = Vec::new();
+    for entry in disabled {
+        let Some(code) = entry.as_str() else {
+            continue;
+        };
+        if explanations::find(code).is_none() {
+            let mut notes = Vec::new();
+            let close_matches = explanations::close_matches(code);
+            if !close_matches.is_empty() {
+                notes.push(format!("Did you mean {}?", close_matches.join(", ")));
+            }
+            diags.emit(
+                Diagnostic::warning()
+                    .with_code("config/unknown-code")
+                    .with_message(format!("Unknown diagnostic code: `{code}`."))
+                    .with_labels(vec![Label::primary(
+                        file_id,
+                        entry.span().unwrap_or_default(),
+                    )])
+                    .with_notes(notes),
+            );
+        }
+        codes.push(code.to_owned());
+    }
+    diags.disable(codes);
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_config(name: &str, config: &str) -> Diagnostics {
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-config-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CONFIG_FILE), config).unwrap();
+        let mut diags = Diagnostics::default();
+        apply(&mut diags, &dir);
+        std::fs::remove_dir_all(dir).unwrap();
+        diags
+    }
+
+    fn warning_codes(diags: &Diagnostics) -> Vec<&str> {
+        diags
+            .warnings()
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn known_codes_are_disabled() {
+        let mut diags = apply_config(
+            "known",
+            r#"disabled = ["import/relative", "kebab-case/parameter"]"#,
+        );
+        assert!(warning_codes(&diags).is_empty());
+        diags.emit(Diagnostic::warning().with_code("import/relative"));
+        diags.emit(Diagnostic::warning().with_code("kebab-case/value"));
+        assert_eq!(warning_codes(&diags), ["kebab-case/value"]);
+    }
+
+    #[test]
+    fn unknown_codes_are_reported() {
+        let diags = apply_config("unknown", r#"disabled = ["import/relatve"]"#);
+        assert_eq!(warning_codes(&diags), ["config/unknown-code"]);
+        assert_eq!(
+            diags.warnings()[0].notes,
+            ["Did you mean import/relative, import/outdated?"]
+        );
+    }
+
+    #[test]
+    fn invalid_configurations_are_reported() {
+        let diags = apply_config("unparsable", r#"disabled = ["import/relative""#);
+        assert_eq!(warning_codes(&diags), ["config/invalid"]);
+
+        let diags = apply_config("not-a-list", r#"disabled = "import/relative""#);
+        assert_eq!(warning_codes(&diags), ["config/invalid"]);
+    }
+}
@@ -1,12 +1,133 @@
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use codespan_reporting::diagnostic::{Diagnostic, Label, LabelStyle, Severity};
 use typst::syntax::{FileId, VirtualPath};
 
 #[derive(Default, Debug)]
 pub struct Diagnostics {
     warnings: Vec<Diagnostic<FileId>>,
     errors: Vec<Diagnostic<FileId>>,
+    /// Informational findings, that are neither errors nor warnings.
+    notes: Vec<Diagnostic<FileId>>,
+    suggestions: Vec<Suggestion>,
+    fixes: Vec<Fix>,
+    stats: DiagnosticsStats,
+    timings: Timings,
+    /// Codes of the diagnostics that should not be reported.
+    disabled: Vec<String>,
+    /// Whether the checks should stop at the first error.
+    fail_fast: bool,
+    /// Whether some checks were skipped because of `fail_fast`.
+    stopped_early: bool,
+}
+
+/// Counts of diagnostics, kept up to date as they are emitted.
+#[derive(Default, Debug, Clone)]
+pub struct DiagnosticsStats {
+    pub errors: usize,
+    pub warnings: usize,
+    pub notes: usize,
+    /// Diagnostics without a code are counted with an empty one.
+    pub by_code: BTreeMap<String, usize>,
+    /// Counts by file, using the file of the primary label of each
+    /// diagnostic. Diagnostics without labels are not counted here.
+    pub by_file: HashMap<FileId, usize>,
+}
+
+impl DiagnosticsStats {
+    pub fn total(&self) -> usize {
+        self.errors + self.warnings + self.notes
+    }
+
+    fn record(&mut self, d: &Diagnostic<FileId>) {
+        match d.severity {
+            Severity::Note | Severity::Help => self.notes += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Error | Severity::Bug => self.errors += 1,
+        }
+        *self
+            .by_code
+            .entry(d.code.clone().unwrap_or_default())
+            .or_default() += 1;
+        if let Some(label) = d.labels.first() {
+            *self.by_file.entry(label.file_id).or_default() += 1;
+        }
+    }
+}
+
+/// How long each phase of the checks took.
+///
+/// Phases are timed one after the other: each of them is considered to start
+/// when the previous one ended.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+    last: Instant,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            phases: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+}
+
+impl Timings {
+    /// Records the end of a phase.
+    pub fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+
+    /// The phases, from the slowest to the fastest.
+    pub fn sorted(&self) -> Vec<(&'static str, Duration)> {
+        let mut phases = self.phases.clone();
+        phases.sort_by(|(_, a), (_, b)| b.cmp(a));
+        phases
+    }
+
+    /// A collapsed Markdown table of the phases, for GitHub summaries.
+    pub fn to_markdown(&self) -> String {
+        let mut table = "<details><summary>Timings</summary>\n\n\
+            | Phase | Duration |\n|---|---|\n"
+            .to_owned();
+        for (phase, duration) in self.sorted() {
+            table += &format!("| {} | {:.3}s |\n", phase, duration.as_secs_f64());
+        }
+        table += "\n</details>\n";
+        table
+    }
+}
+
+/// A replacement that fixes a diagnostic.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub file_id: FileId,
+    pub range: Range<usize>,
+    pub replacement: String,
+    /// The message of the diagnostic that this fixes.
+    pub message: String,
+}
+
+/// A change to the files of a package that fixes a diagnostic, and that is
+/// safe to apply automatically.
+///
+/// Fixes that only replace some text are [`Suggestion`]s.
+#[derive(Debug, Clone)]
+pub enum Fix {
+    /// Renames a file. Paths are relative to the package root.
+    Rename { from: PathBuf, to: PathBuf },
+    /// Adds a path, relative to the package root, to the `exclude` field of
+    /// the manifest.
+    Exclude(PathBuf),
 }
 
 impl Diagnostics {
@@ -17,12 +138,47 @@ impl Diagnostics {
     }
 
     pub fn emit(&mut self, d: Diagnostic<FileId>) {
+        if self.is_disabled(&d) {
+            tracing::debug!("Dropping disabled diagnostic: {:?}", &d);
+            return;
+        }
         tracing::debug!("Emitting: {:?}", &d);
-        if d.severity == Severity::Warning {
-            self.warnings.push(d)
-        } else {
-            self.errors.push(d)
+        self.stats.record(&d);
+        match d.severity {
+            Severity::Note | Severity::Help => self.notes.push(d),
+            Severity::Warning => self.warnings.push(d),
+            Severity::Error | Severity::Bug => self.errors.push(d),
+        }
+    }
+
+    /// Emits a diagnostic, that can be fixed by replacing `range` in
+    /// `file_id` with `replacement`.
+    pub fn emit_with_suggestion(
+        &mut self,
+        d: Diagnostic<FileId>,
+        file_id: FileId,
+        range: Range<usize>,
+        replacement: String,
+    ) {
+        if self.is_disabled(&d) {
+            return;
         }
+        self.suggestions.push(Suggestion {
+            file_id,
+            range,
+            replacement,
+            message: d.message.clone(),
+        });
+        self.emit(d)
+    }
+
+    /// Emits a diagnostic, that can be fixed with `fix`.
+    pub fn emit_with_fix(&mut self, d: Diagnostic<FileId>, fix: Fix) {
+        if self.is_disabled(&d) {
+            return;
+        }
+        self.fixes.push(fix);
+        self.emit(d)
     }
 
     pub fn emit_many(&mut self, ds: impl Iterator<Item = Diagnostic<FileId>>) {
@@ -31,23 +187,58 @@ impl Diagnostics {
         }
     }
 
+    /// Adds the diagnostics of `other`, in which paths are relative to
+    /// `dir_prefix`.
+    ///
+    /// Whether an id is remapped only depends on where the file comes from,
+    /// not on its path: ids of files in packages are kept as is, all the
+    /// other ones are relative to the root of the world that `other` was
+    /// collected for. Fake ids stay fake, and distinct.
     pub fn extend(&mut self, mut other: Self, dir_prefix: &Path) {
-        let fix_labels = |diag: &mut Diagnostic<FileId>| {
+        let mut remapped_fakes = HashMap::new();
+        let mut fix_file_id = |file_id: &mut FileId| {
+            if file_id.package().is_some() {
+                return;
+            }
+
+            let vpath = VirtualPath::new(dir_prefix.join(file_id.vpath().as_rootless_path()));
+            *file_id = if is_fake(*file_id) {
+                *remapped_fakes
+                    .entry(*file_id)
+                    .or_insert_with(|| FileId::new_fake(vpath))
+            } else {
+                FileId::new(None, vpath)
+            };
+        };
+        let mut fix_labels = |diag: &mut Diagnostic<FileId>| {
             for label in diag.labels.iter_mut() {
-                if label.file_id.package().is_none() {
-                    label.file_id = FileId::new(
-                        None,
-                        VirtualPath::new(dir_prefix.join(label.file_id.vpath().as_rootless_path())),
-                    )
-                }
+                fix_file_id(&mut label.file_id);
             }
         };
 
-        other.errors.iter_mut().for_each(fix_labels);
-        self.errors.extend(other.errors);
+        for mut diag in other
+            .errors
+            .into_iter()
+            .chain(other.warnings)
+            .chain(other.notes)
+        {
+            fix_labels(&mut diag);
+            self.emit(diag);
+        }
+
+        for suggestion in other.suggestions.iter_mut() {
+            fix_file_id(&mut suggestion.file_id);
+        }
+        self.suggestions.extend(other.suggestions);
 
-        other.warnings.iter_mut().for_each(fix_labels);
-        self.warnings.extend(other.warnings);
+        self.fixes
+            .extend(other.fixes.into_iter().map(|fix| match fix {
+                Fix::Rename { from, to } => Fix::Rename {
+                    from: dir_prefix.join(from),
+                    to: dir_prefix.join(to),
+                },
+                Fix::Exclude(path) => Fix::Exclude(dir_prefix.join(path)),
+            }));
     }
 
     pub fn errors(&self) -> &[Diagnostic<FileId>] {
@@ -57,4 +248,232 @@ impl Diagnostics {
     pub fn warnings(&self) -> &[Diagnostic<FileId>] {
         &self.warnings
     }
+
+    pub fn stats(&self) -> &DiagnosticsStats {
+        &self.stats
+    }
+
+    /// Changes the severity of the diagnostics with the given codes, or drops
+    /// them if the new severity is `None`. Later entries take precedence.
+    pub fn override_severities(&mut self, severities: &[(String, Option<Severity>)]) {
+        if severities.is_empty() {
+            return;
+        }
+
+        let all = std::mem::take(&mut self.errors)
+            .into_iter()
+            .chain(std::mem::take(&mut self.warnings))
+            .chain(std::mem::take(&mut self.notes))
+            .collect::<Vec<_>>();
+        self.stats = DiagnosticsStats::default();
+        for mut d in all {
+            let new_severity = severities
+                .iter()
+                .rev()
+                .find(|(code, _)| d.code.as_ref() == Some(code));
+            match new_severity {
+                Some((_, None)) => continue,
+                Some((_, Some(severity))) => d.severity = *severity,
+                None => {}
+            }
+            self.emit(d);
+        }
+    }
+
+    /// Drops the diagnostics about files of the package that are not in
+    /// `files`, which are relative to the package root.
+    ///
+    /// Diagnostics that are not about a specific file, or about a file of
+    /// another package, are kept.
+    pub fn retain_files(&mut self, files: &[PathBuf]) {
+        let all = std::mem::take(&mut self.errors)
+            .into_iter()
+            .chain(std::mem::take(&mut self.warnings))
+            .chain(std::mem::take(&mut self.notes))
+            .collect::<Vec<_>>();
+        self.stats = DiagnosticsStats::default();
+        for d in all {
+            let primary = d
+                .labels
+                .iter()
+                .find(|label| label.style == LabelStyle::Primary);
+            let keep = match primary {
+                Some(label) if label.file_id.package().is_none() => files
+                    .iter()
+                    .any(|file| file == label.file_id.vpath().as_rootless_path()),
+                _ => true,
+            };
+            if keep {
+                self.emit(d);
+            }
+        }
+    }
+
+    /// Adds the long explanation of their code to the diagnostics, as a
+    /// note.
+    pub fn add_explanations(&mut self) {
+        for d in self
+            .errors
+            .iter_mut()
+            .chain(&mut self.warnings)
+            .chain(&mut self.notes)
+        {
+            if let Some(explanation) = d.code.as_deref().and_then(super::explanations::find) {
+                d.notes.push(explanation.description.trim_end().to_owned());
+            }
+        }
+    }
+
+    /// Makes [`Diagnostics::should_stop`] return `true` once an error was
+    /// emitted.
+    pub fn set_fail_fast(&mut self) {
+        self.fail_fast = true;
+    }
+
+    /// Whether the remaining checks should be skipped, because an error was
+    /// found and the checks should stop at the first one.
+    pub fn should_stop(&mut self) -> bool {
+        let stop = self.fail_fast && !self.errors.is_empty();
+        self.stopped_early |= stop;
+        stop
+    }
+
+    /// Whether some checks were skipped, and other issues may exist.
+    pub fn stopped_early(&self) -> bool {
+        self.stopped_early
+    }
+
+    /// Stops reporting the diagnostics with these codes.
+    pub fn disable(&mut self, codes: impl IntoIterator<Item = String>) {
+        self.disabled.extend(codes);
+    }
+
+    fn is_disabled(&self, d: &Diagnostic<FileId>) -> bool {
+        d.code
+            .as_ref()
+            .is_some_and(|code| self.disabled.contains(code))
+    }
+
+    /// Records the end of a phase of the checks, see [`Timings`].
+    pub fn lap(&mut self, phase: &'static str) {
+        self.timings.lap(phase)
+    }
+
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Informational findings, that don't affect the result of the checks.
+    pub fn notes(&self) -> &[Diagnostic<FileId>] {
+        &self.notes
+    }
+
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+}
+
+/// Whether a file id was created with [`FileId::new_fake`], in which case it
+/// is not the id that is interned for its path.
+fn is_fake(id: FileId) -> bool {
+    FileId::new(id.package().cloned(), id.vpath().clone()) != id
+}
+
+/// Turns an error that prevented the checks from completing into a
+/// diagnostic.
+///
+/// It is attached to the manifest of the package, as most of these errors
+/// come from it, and as it is always there. The causes of the error are
+/// listed as notes.
+pub fn fatal_error(error: &eyre::Report) -> Diagnostic<FileId> {
+    Diagnostic::error()
+        .with_message(format!("The checks could not be completed: {}", error))
+        .with_labels(vec![Label::primary(
+            FileId::new(None, VirtualPath::new("typst.toml")),
+            0..0,
+        )])
+        .with_notes(
+            error
+                .chain()
+                .skip(1)
+                .map(|cause| format!("caused by: {}", cause))
+                .collect(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::package::PackageSpec;
+
+    use super::*;
+
+    fn warning_in(file_id: FileId) -> Diagnostic<FileId> {
+        Diagnostic::warning()
+            .with_message("test")
+            .with_labels(vec![Label::primary(file_id, 0..0)])
+    }
+
+    fn extended(file_ids: &[FileId]) -> Vec<FileId> {
+        let mut template_diags = Diagnostics::default();
+        for file_id in file_ids {
+            template_diags.emit(warning_in(*file_id));
+        }
+        let mut diags = Diagnostics::default();
+        diags.extend(template_diags, Path::new("template"));
+        diags
+            .warnings()
+            .iter()
+            .map(|d| d.labels[0].file_id)
+            .collect()
+    }
+
+    #[test]
+    fn extend_prefixes_files_of_the_world() {
+        let id = FileId::new(None, VirtualPath::new("main.typ"));
+        let [remapped] = extended(&[id])[..] else {
+            panic!("expected one diagnostic");
+        };
+        assert_eq!(
+            remapped.vpath().as_rootless_path(),
+            Path::new("template/main.typ")
+        );
+        assert!(!is_fake(remapped));
+    }
+
+    #[test]
+    fn extend_prefixes_paths_that_look_already_prefixed() {
+        let id = FileId::new(None, VirtualPath::new("template/x.typ"));
+        let [remapped] = extended(&[id])[..] else {
+            panic!("expected one diagnostic");
+        };
+        assert_eq!(
+            remapped.vpath().as_rootless_path(),
+            Path::new("template/template/x.typ")
+        );
+    }
+
+    #[test]
+    fn extend_keeps_package_files() {
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let id = FileId::new(Some(spec), VirtualPath::new("lib.typ"));
+        assert_eq!(extended(&[id]), vec![id]);
+    }
+
+    #[test]
+    fn extend_keeps_fake_ids_fake_and_distinct() {
+        let first = FileId::new_fake(VirtualPath::new("README.md"));
+        let second = FileId::new_fake(VirtualPath::new("README.md"));
+        let remapped = extended(&[first, second, first]);
+        assert_eq!(remapped.len(), 3);
+        assert!(remapped.iter().all(|id| is_fake(*id)));
+        assert!(remapped
+            .iter()
+            .all(|id| id.vpath().as_rootless_path() == Path::new("template/README.md")));
+        assert_ne!(remapped[0], remapped[1]);
+        assert_eq!(remapped[0], remapped[2]);
+    }
 }
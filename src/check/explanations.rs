@@ -0,0 +1,1276 @@
+//! The diagnostic codes that the checks use, with long-form explanations for
+//! the `explain` and `list-checks` commands.
+//!
+//! Every diagnostic that a check emits has a code, and every code is listed
+//! here, as this table is also used to validate the codes given by users.
+//! Only errors that prevent the checks from running, and notes about the run
+//! itself, don't have a code.
+
+use codespan_reporting::diagnostic::Severity;
+
+pub struct Explanation {
+    pub code: &'static str,
+    /// The severity of the diagnostics with this code, unless it is changed
+    /// by the user.
+    pub severity: Severity,
+    /// A one-line description of the rule.
+    pub summary: &'static str,
+    /// Why the rule exists, and how to fix the issue, in Markdown.
+    pub description: &'static str,
+    /// A page of the documentation of `typst/packages` or of these checks
+    /// with more details, if there is one.
+    pub doc_url: Option<&'static str>,
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "compile/path-outside-package",
+        severity: Severity::Error,
+        summary: "A file outside of the package is read",
+        description: "\
+Packages can only read files that are inside of their own directory. Once
+published, the package is stored on its own, and files that are next to it on
+your computer won't be available to users.
+
+This usually happens with paths that go up too many times:
+
+```typ
+// Fails, if this file is at the root of the package.
+#read(\"../data.csv\")
+```
+
+```typ
+// Works, `data.csv` is part of the package.
+#read(\"data.csv\")
+```
+
+To fix it, move the file into the package, and update the path.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "compile/error",
+        severity: Severity::Error,
+        summary: "The Typst compiler reported an error",
+        description: "\
+The entrypoint of the package, or of its template, doesn't compile. The
+message of the compiler is included in the diagnostic.
+
+Users will get the same error as soon as they import the package, or create a
+project from the template, so it needs to be fixed before publishing.
+
+To reproduce it, compile the file with the version of Typst that the checks
+use (see `typst-version`).
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "compile/warning",
+        severity: Severity::Warning,
+        summary: "The Typst compiler reported a warning",
+        description: "\
+The Typst compiler emitted a warning while compiling the entrypoint of the
+package or of its template. The message of the compiler is included in the
+diagnostic.
+
+Users will see this warning in their own documents, so it is better to fix
+it, for instance by removing unused code or by using the replacement of a
+deprecated function.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "paths/absolute",
+        severity: Severity::Warning,
+        summary: "An absolute path is given to a function that loads a file",
+        description: "\
+In Typst, paths starting with `/` are relative to the root of the project or
+the package, not to the root of the file system. Paths like `/home/me/logo.svg`
+or `C:\\Users\\me\\logo.svg` are most likely paths on the computer of the author,
+that won't exist for users of the package.
+
+```typ
+// Warns: this file only exists on your computer.
+#image(\"/home/me/logo.svg\")
+```
+
+```typ
+// Works: this file is part of the package.
+#image(\"/assets/logo.svg\")
+```
+
+To fix it, copy the file into the package, and use a path relative to the
+package root.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "style/line-endings",
+        severity: Severity::Warning,
+        summary: "A text file doesn't use LF line endings, or doesn't end with a new line",
+        description: "\
+Files of the package should use Unix (`\\n`) line endings, and end with a new
+line. Windows (`\\r\\n`) line endings, especially when they are mixed with
+Unix ones, make diffs hard to review, as every line seems changed.
+
+To fix it, convert the file to LF line endings, for instance with the
+`dos2unix` tool or the settings of your editor. A `.gitattributes` file can
+prevent it from happening again.
+
+A missing new line at the end of a file is only reported as a note.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "docs/no-changelog",
+        severity: Severity::Warning,
+        summary: "An update doesn't document what changed",
+        description: "\
+When publishing a new version of a package, users need to know what changed
+to decide whether to upgrade, and what to adapt in their documents.
+
+The checks look for a changelog file (`CHANGELOG.md`, `CHANGES.md`, etc.)
+mentioning the new version, or a heading of the README mentioning it:
+
+```md
+## 0.2.0
+
+- Added the `compact` option.
+```
+
+To fix it, add an entry for this version in the changelog, or a section in
+the README.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "import/relative",
+        severity: Severity::Warning,
+        summary: "The entrypoint is imported with a relative path",
+        description: "\
+Files that are meant to be copied out of the package, like the ones of a
+template or of examples, should import the package by its specification.
+Once copied, the relative path doesn't point to the package anymore.
+
+```typ
+// Warns: this path won't exist in projects created from the template.
+#import \"../lib.typ\": *
+```
+
+```typ
+// Works: the package is downloaded if needed.
+#import \"@preview/my-package:0.1.0\": *
+```
+
+To fix it, use the package specification, with the version being published.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "import/outdated",
+        severity: Severity::Warning,
+        summary: "The package imports an older version of itself",
+        description: "\
+A file of the package imports the package itself, but with another version
+than the one being published. This usually happens when the version in the
+manifest was updated, but not the imports in the template or the examples.
+
+```typ
+// Warns, when publishing version 0.2.0.
+#import \"@preview/my-package:0.1.0\": *
+```
+
+To fix it, update the version in the import.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "kebab-case/value",
+        severity: Severity::Warning,
+        summary: "A public value doesn't have a kebab-case name",
+        description: "\
+Names of the functions and variables of Typst use kebab-case, and packages
+should follow the same convention for the values they export, so that
+documents using several packages stay consistent.
+
+```typ
+// Warns.
+#let makeTitle(body) = body
+```
+
+```typ
+// Works.
+#let make-title(body) = body
+```
+
+Constants can use SCREAMING_SNAKE_CASE, and names starting with `_` are
+considered private.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "kebab-case/parameter",
+        severity: Severity::Warning,
+        summary: "A parameter of a public function doesn't have a kebab-case name",
+        description: "\
+Users pass named arguments by their name, which should use kebab-case like
+the parameters of the functions of Typst.
+
+```typ
+// Warns.
+#let template(paperSize: \"a4\", body) = body
+```
+
+```typ
+// Works.
+#let template(paper-size: \"a4\", body) = body
+```
+
+Renaming a parameter is a breaking change: it is better to do it before the
+first version is published.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "style/global-rule",
+        severity: Severity::Warning,
+        summary: "A set or show rule of the package has no effect for its users",
+        description: "\
+Set and show rules at the top level of a file only apply to the rest of this
+file. When a document imports the package, they don't change anything in the
+document.
+
+```typ
+// Warns: this doesn't change the font of the document.
+#set text(font: \"Libertinus Serif\")
+```
+
+```typ
+// Works: users apply it with `#show: template`.
+#let template(body) = {
+  set text(font: \"Libertinus Serif\")
+  body
+}
+```
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/fonts",
+        severity: Severity::Warning,
+        summary: "A font that is not embedded in Typst is used",
+        description: "\
+Only the fonts embedded in Typst, and the fonts installed by each user, are
+available when compiling a document. Fonts can't be distributed with a
+package, so a font that isn't embedded will most likely be missing for most
+users, and Typst will silently fall back to another one.
+
+To fix it, use one of the embedded fonts suggested by the diagnostic, or
+document that the font needs to be installed and provide a fallback:
+
+```typ
+#set text(font: (\"Fira Sans\", \"Libertinus Serif\"))
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/resources.md"),
+    },
+    Explanation {
+        code: "files/large",
+        severity: Severity::Warning,
+        summary: "A file of the package is large",
+        description: "\
+Every user of the package downloads all of its files that are not excluded.
+Large files make the package slower to download, and take space in the
+package repository forever.
+
+If the file is not needed to use the package (documentation, examples,
+tests), add it to the `exclude` field of the manifest:
+
+```toml
+[package]
+exclude = [\"docs/manual.pdf\"]
+```
+
+Really large files should not be added to the repository at all, even
+excluded.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/resources.md"),
+    },
+    Explanation {
+        code: "files/wasm-opt",
+        severity: Severity::Warning,
+        summary: "A WebAssembly plugin could be smaller",
+        description: "\
+Running `wasm-opt -Os` on this plugin makes it noticeably smaller, which
+makes the package faster to download and to load.
+
+To fix it, optimize the plugin as part of its build:
+
+```sh
+wasm-opt -Os plugin.wasm -o plugin.wasm
+```
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/example",
+        severity: Severity::Warning,
+        summary: "An example file is part of the package",
+        description: "\
+Examples are useful in the repository of the package, but users don't need
+them to use it. If the file is not imported by the package, add it to the
+`exclude` field of the manifest:
+
+```toml
+[package]
+exclude = [\"examples/*\"]
+```
+
+Files of the template are never reported.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/test",
+        severity: Severity::Warning,
+        summary: "A test file is part of the package",
+        description: "\
+Tests are useful in the repository of the package, but users don't need them
+to use it. If the file is not imported by the package, add it to the
+`exclude` field of the manifest:
+
+```toml
+[package]
+exclude = [\"tests/*\"]
+```
+
+Files of the template are never reported.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/docs",
+        severity: Severity::Warning,
+        summary: "A documentation directory is part of the package",
+        description: "\
+The directory doesn't seem to be used by the entrypoint of the package, and
+its name suggests that it contains documentation. Users don't need it to use
+the package, and the README is already shown on Typst Universe.
+
+To fix it, add the directory to the `exclude` field of the manifest:
+
+```toml
+[package]
+exclude = [\"docs/*\"]
+```
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/pdf",
+        severity: Severity::Warning,
+        summary: "A PDF file is part of the package",
+        description: "\
+Packages can't include PDF files in documents, so a PDF file is generally a
+manual or a compiled example, that users don't need to use the package.
+
+To fix it, add the file to the `exclude` field of the manifest.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "files/licence",
+        severity: Severity::Error,
+        summary: "The license file is named LICENCE",
+        description: "\
+Tools looking for the license of the package, including Typst Universe,
+expect a file named `LICENSE`, with an optional extension.
+
+```
+LICENCE.md  <- fails
+LICENSE.md  <- works
+```
+
+To fix it, rename the file, or run `check --fix`.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "files/all-caps",
+        severity: Severity::Error,
+        summary: "The README or LICENSE file is not named in all caps",
+        description: "\
+All packages use `README` and `LICENSE` in capital letters, which some tools
+rely on, as file names are case-sensitive on most systems.
+
+```
+Readme.md  <- fails
+README.md  <- works
+```
+
+To fix it, rename the file, or run `check --fix`.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "exclude/license",
+        severity: Severity::Warning,
+        summary: "The LICENSE file is excluded",
+        description: "\
+The license has to be distributed with the package, for users to know under
+which terms they can use it.
+
+To fix it, remove the pattern that matches `LICENSE` from the `exclude` field
+of the manifest.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "exclude/readme",
+        severity: Severity::Warning,
+        summary: "The README file is excluded",
+        description: "\
+The README is the documentation of the package, it should be distributed
+with it.
+
+To fix it, remove the pattern that matches `README.md` from the `exclude`
+field of the manifest.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "exclude/template",
+        severity: Severity::Error,
+        summary: "A file of the template is excluded",
+        description: "\
+`typst init` copies the template directory from the published package:
+excluded files won't be part of new projects, and the template is likely
+to break without them.
+
+```toml
+[package]
+exclude = [\"template/logo.png\"]  # fails
+
+[template]
+path = \"template\"
+```
+
+To fix it, remove the pattern that matches this file from the `exclude`
+field, or delete the file if the template doesn't need it.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "plugins/excluded",
+        severity: Severity::Error,
+        summary: "A WebAssembly plugin is excluded from the package",
+        description: "\
+The package loads this plugin, but it is excluded, so it won't be available
+to users and the package won't compile for them.
+
+```toml
+[package]
+exclude = [\"*.wasm\"]  # fails, if a plugin is loaded
+```
+
+To fix it, remove the pattern that matches the plugin from the `exclude`
+field of the manifest.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "plugins/case",
+        severity: Severity::Error,
+        summary: "A WebAssembly plugin is loaded with the wrong case",
+        description: "\
+The path given to `plugin` only matches the name of the file if case is
+ignored. It may work on your computer, but file names are case-sensitive on
+most systems, where the package won't compile.
+
+```typ
+// Fails, if the file is named `Plugin.wasm`.
+#let p = plugin(\"plugin.wasm\")
+```
+
+To fix it, use the exact name of the file.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "plugins/missing",
+        severity: Severity::Error,
+        summary: "A WebAssembly plugin that doesn't exist is loaded",
+        description: "\
+The package calls `plugin` with the path of a file that is not part of the
+package.
+
+```typ
+// Fails, if there is no `plugin.wasm` next to this file.
+#let p = plugin(\"plugin.wasm\")
+```
+
+To fix it, add the plugin to the package, or fix its path. Plugins that are
+built from source need to be built before publishing.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "plugins/unused",
+        severity: Severity::Warning,
+        summary: "A WebAssembly file is never loaded",
+        description: "\
+The package includes a WebAssembly file that isn't loaded with `plugin`. It
+makes the package larger for no reason.
+
+To fix it, delete the file, or add it to the `exclude` field of the manifest.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "template/placeholder",
+        severity: Severity::Warning,
+        summary: "A file of the template contains placeholder text",
+        description: "\
+Placeholders like \"Lorem ipsum\" or \"TODO\" end up in every document that
+is created from the template. They are fine for the content that users will
+replace, but are often left over in places that users don't expect to edit.
+
+If the text is meant to be replaced by users, this warning can be ignored.
+Placeholders in comments are not reported.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "template/asset-outside-template",
+        severity: Severity::Error,
+        summary: "A file outside of the template directory is read by the template",
+        description: "\
+`typst init` only copies the template directory into the new project, so the
+template can't use the other files of the package, like images or data that
+are next to it. It can still import the package itself.
+
+```typ
+// Fails, if this file is at the root of the template directory.
+#image(\"../assets/logo.png\")
+```
+
+To fix it, move the file into the template directory, and update the path.
+Symbolic links pointing outside of the template directory are reported too.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "template/compiled-document",
+        severity: Severity::Error,
+        summary: "The template contains a compiled document",
+        description: "\
+The template directory contains the output of one of its Typst files. It
+would be copied into every new project, where it would be outdated as soon
+as users edit the document.
+
+```
+template/main.typ
+template/main.pdf  <- fails
+```
+
+To fix it, delete the compiled file. The thumbnail of the template, if it is
+generated from it, should be outside of the template directory.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "authors/changed",
+        severity: Severity::Warning,
+        summary: "The authors of this version differ from the ones of the previous version",
+        description: "\
+According to Git, none of the authors of the previous version of this
+package changed the manifest of this version. This is reported so that
+reviewers can make sure that the package is updated by its maintainers.
+
+If the maintainers of the package changed, mention it in the pull request.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "config/unknown-code",
+        severity: Severity::Warning,
+        summary: "The configuration disables a code that doesn't exist",
+        description: "\
+The `disabled` field of `typst-check.toml`, or of the `[tool.package-check]`
+section of the manifest, contains a code that the checks never report. It is
+probably a typo, and the diagnostic it was meant to disable is still
+reported.
+
+```toml
+disabled = [\"import/relatve\"]  # warns
+```
+
+```toml
+disabled = [\"import/relative\"]  # works
+```
+
+Run `list-checks` to see all the codes.
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "config/invalid",
+        severity: Severity::Warning,
+        summary: "The configuration of the checks can't be used",
+        description: "\
+`typst-check.toml` is not valid TOML, or its `disabled` field is not a list.
+The whole configuration is ignored, so no diagnostic is disabled.
+
+```toml
+disabled = \"import/relative\"    # warns
+disabled = [\"import/relative\"]  # works
+```
+",
+        doc_url: None,
+    },
+    Explanation {
+        code: "manifest/package/missing",
+        severity: Severity::Error,
+        summary: "The manifest has no [package] section",
+        description: "\
+All the metadata of the package is in the `[package]` section of
+`typst.toml`:
+
+```toml
+[package]
+name = \"my-package\"
+version = \"0.1.0\"
+entrypoint = \"lib.typ\"
+```
+
+See the README of the packages repository for the full format of the
+manifest.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/name/missing",
+        severity: Severity::Error,
+        summary: "The manifest doesn't have a name",
+        description: "\
+The `name` field is required, it is what users write to import the package.
+
+```toml
+[package]
+name = \"my-package\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/name/invalid",
+        severity: Severity::Error,
+        summary: "The name of the package is not a string",
+        description: "\
+The `name` field must be a string.
+
+```toml
+name = my-package    # fails
+name = \"my-package\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/name/kebab-case",
+        severity: Severity::Error,
+        summary: "The name of the package is not in kebab-case",
+        description: "\
+Package names only use lowercase letters, digits and dashes.
+
+```toml
+name = \"MyPackage\"   # fails
+name = \"my-package\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/name/typst",
+        severity: Severity::Warning,
+        summary: "The name of the package contains \"typst\"",
+        description: "\
+All packages are for Typst, so this word doesn't help to describe what the
+package does, and makes the name longer to type in each import.
+
+```toml
+name = \"typst-diagrams\"  # warns
+name = \"diagrams\"        # works
+```
+
+If the name is a pun or a reference that needs it, this warning can be
+ignored.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/name/unexpected",
+        severity: Severity::Error,
+        summary: "The name of the package doesn't match its directory",
+        description: "\
+Packages are stored in `packages/{namespace}/{name}/{version}`, and the name
+in the manifest must be the same as the one of the directory.
+
+To publish a new package under another name, create a new directory instead
+of renaming the package in the manifest.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/version/missing",
+        severity: Severity::Error,
+        summary: "The manifest doesn't have a version",
+        description: "\
+The `version` field is required, it is what users write to import this
+version of the package.
+
+```toml
+[package]
+version = \"0.1.0\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/version/invalid",
+        severity: Severity::Error,
+        summary: "The version of the package is not a valid semantic version",
+        description: "\
+The `version` field must be a string with three numbers: `MAJOR.MINOR.PATCH`.
+
+```toml
+version = \"1.0\"    # fails
+version = \"1.0.0\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/version/unexpected",
+        severity: Severity::Error,
+        summary: "The version of the package doesn't match its directory",
+        description: "\
+Packages are stored in `packages/{namespace}/{name}/{version}`, and the
+version in the manifest must be the same as the one of the directory.
+
+To publish a new version, create a new directory instead of changing the
+version in the manifest of an existing one.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/compiler/invalid",
+        severity: Severity::Error,
+        summary: "The minimum compiler version is not a valid semantic version",
+        description: "\
+The `compiler` field must be a string with three numbers.
+
+```toml
+compiler = \"0.12\"    # fails
+compiler = \"0.12.0\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/compiler/newer",
+        severity: Severity::Warning,
+        summary: "The package requires a newer Typst than the checks",
+        description: "\
+The `compiler` field requires a version of Typst that is newer than the one
+used by the checks. Compilation errors may come from features that this
+version doesn't have yet, and may not happen with the right version.
+
+Run `typst-version` to see the version used by the checks.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/license/missing",
+        severity: Severity::Error,
+        summary: "The manifest doesn't have a license",
+        description: "\
+Packages published on Typst Universe must have an open source license, given
+as an SPDX expression in the `license` field.
+
+```toml
+[package]
+license = \"MIT\"
+```
+
+The text of the license should be in a `LICENSE` file next to the manifest.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "manifest/license/invalid",
+        severity: Severity::Error,
+        summary: "The license is not a valid SPDX expression",
+        description: "\
+The `license` field must be a string with an SPDX 2 expression.
+
+```toml
+license = \"MIT License\"     # fails
+license = \"MIT OR Apache-2.0\"  # works
+```
+
+See https://spdx.org/licenses/ for the identifiers of the licenses.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "manifest/license/not-osi",
+        severity: Severity::Error,
+        summary: "The license is not approved by the OSI",
+        description: "\
+Packages published on Typst Universe must use licenses approved by the Open
+Source Initiative.
+
+```toml
+license = \"CC-BY-4.0\"  # fails
+license = \"MIT-0\"      # works
+```
+
+Content that is not code, like images, can be distributed under other
+licenses as long as the package itself uses an approved one.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "manifest/license/referencer",
+        severity: Severity::Error,
+        summary: "The license refers to a custom license",
+        description: "\
+Licenses like `LicenseRef-...` refer to a custom license, that can't be
+checked automatically.
+
+```toml
+license = \"LicenseRef-Proprietary\"  # fails
+license = \"MIT\"                     # works
+```
+
+To fix it, use the SPDX identifier of an OSI approved license.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/licensing.md"),
+    },
+    Explanation {
+        code: "manifest/description/missing",
+        severity: Severity::Error,
+        summary: "The manifest doesn't have a description",
+        description: "\
+Packages published on Typst Universe must have a short description, that is
+shown in search results.
+
+```toml
+[package]
+description = \"Draw diagrams with arrows.\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/description/invalid",
+        severity: Severity::Error,
+        summary: "The description is not a string",
+        description: "\
+The `description` field must be a string.
+
+```toml
+description = [\"Diagrams\"]            # fails
+description = \"Draw diagrams with arrows.\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/authors/missing",
+        severity: Severity::Error,
+        summary: "The manifest doesn't list the authors",
+        description: "\
+Packages published on Typst Universe must list their authors.
+
+```toml
+[package]
+authors = [\"Jane Doe <@jane>\"]
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/authors/invalid",
+        severity: Severity::Error,
+        summary: "The authors are not an array of strings",
+        description: "\
+The `authors` field must be an array of strings, even if there is only one
+author.
+
+```toml
+authors = \"Jane Doe\"    # fails
+authors = [\"Jane Doe\"]  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/keywords/redundant",
+        severity: Severity::Warning,
+        summary: "A keyword repeats the name, a category or a discipline",
+        description: "\
+The name, categories and disciplines of the package are already used when
+searching for packages, repeating them as keywords doesn't help users find
+the package.
+
+```toml
+name = \"diagrams\"
+categories = [\"visualization\"]
+keywords = [\"diagrams\", \"visualization\", \"arrows\"]  # warns twice
+```
+
+To fix it, remove the redundant keywords.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/url/invalid",
+        severity: Severity::Error,
+        summary: "A link of the manifest is not a valid URL",
+        description: "\
+The `repository` and `homepage` fields must be full links, starting with
+`https://`.
+
+```toml
+repository = \"github.com/me/my-package\"          # fails
+repository = \"https://github.com/me/my-package\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/url/unreachable",
+        severity: Severity::Error,
+        summary: "A link of the manifest could not be fetched",
+        description: "\
+The `repository` or `homepage` link returned an error. It may be a typo, or a
+private repository.
+
+To fix it, check that the link works when you are not logged in. Offline,
+links are not fetched and this error is never reported.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/repository/unverified",
+        severity: Severity::Warning,
+        summary: "The repository of the package doesn't seem to contain it",
+        description: "\
+The `repository` field of the manifest should link to the source code of the
+package. The checks look for a `typst.toml` file with the same package name
+in this repository, and couldn't find one.
+
+This can be a typo in the URL, a private repository, or the link to another
+package of the same author.
+
+To fix it, double-check the link. If the repository is correct but organized
+in an unusual way, this warning can be ignored.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/repository/offline",
+        severity: Severity::Note,
+        summary: "The links of the manifest were not fetched",
+        description: "\
+The checks ran offline, so the `repository` and `homepage` links were only
+checked for their syntax, and the repository was not compared to the
+package.
+
+Run the checks online to get these checks too.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/homepage/same-as-repository",
+        severity: Severity::Error,
+        summary: "The homepage is the same link as the repository",
+        description: "\
+The `homepage` field is meant for a dedicated website. When it is the same as
+the repository, Typst Universe shows the same link twice.
+
+```toml
+repository = \"https://github.com/me/my-package\"
+homepage = \"https://github.com/me/my-package\"  # fails
+```
+
+To fix it, remove the `homepage` field.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/entrypoint/in-template",
+        severity: Severity::Error,
+        summary: "The entrypoint of the package is in the template directory",
+        description: "\
+The entrypoint is what users get when importing the package, and the
+template directory is what is copied by `typst init`. The library should be
+outside of the template, and the template should import it by its package
+specification.
+
+```toml
+[package]
+entrypoint = \"template/main.typ\"  # fails
+
+[template]
+path = \"template\"
+entrypoint = \"main.typ\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/template/entrypoint-outside",
+        severity: Severity::Warning,
+        summary: "The entrypoint of the template is outside of the template directory",
+        description: "\
+Only the template directory is copied by `typst init`, so an entrypoint
+outside of it will be missing in new projects.
+
+```toml
+[template]
+path = \"template\"
+entrypoint = \"../main.typ\"  # warns
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/thumbnail/not-template",
+        severity: Severity::Warning,
+        summary: "A package that is not a template has a thumbnail",
+        description: "\
+Only templates have a thumbnail, and it is configured in the `[template]`
+section. In the `[package]` section, the field has no effect.
+
+```toml
+[template]
+path = \"template\"
+entrypoint = \"main.typ\"
+thumbnail = \"thumbnail.png\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/thumbnail/missing",
+        severity: Severity::Error,
+        summary: "A template doesn't have a thumbnail",
+        description: "\
+Templates are shown on Typst Universe with a thumbnail, that is required.
+It should be a PNG or WebP image, at least 1080px on its longer edge.
+
+```toml
+[template]
+thumbnail = \"thumbnail.png\"
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/thumbnail/not-found",
+        severity: Severity::Error,
+        summary: "The thumbnail of the template doesn't exist",
+        description: "\
+The `thumbnail` field of the `[template]` section points to a file that is
+not in the package. The path is relative to the root of the package, not to
+the template directory.
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+    Explanation {
+        code: "manifest/thumbnail/format",
+        severity: Severity::Error,
+        summary: "The thumbnail is not a PNG or WebP image",
+        description: "\
+Thumbnails must be PNG or WebP images.
+
+```toml
+thumbnail = \"thumbnail.svg\"  # fails
+thumbnail = \"thumbnail.png\"  # works
+```
+",
+        doc_url: Some("https://github.com/typst/packages/blob/main/docs/manifest.md"),
+    },
+];
+
+/// Finds the explanation of a code.
+pub fn find(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code == code)
+}
+
+/// The documentation of a code, if there is some.
+pub fn doc_url(code: &str) -> Option<&'static str> {
+    find(code)?.doc_url
+}
+
+/// Codes that look like `code`, to help with typos.
+pub fn close_matches(code: &str) -> Vec<&'static str> {
+    let category = code.split('/').next().unwrap_or(code);
+    EXPLANATIONS
+        .iter()
+        .map(|explanation| explanation.code)
+        .filter(|known| {
+            known.starts_with(category)
+                || known.contains(code)
+                || code.contains(known)
+                || edit_distance(code, known) <= 3
+        })
+        .collect()
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// The string literals of the checks that look like diagnostic codes.
+    fn codes_in_sources() -> Vec<(String, String)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/check");
+        let mut codes = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .file_name()
+                .is_some_and(|name| name == "explanations.rs")
+            {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            // Tests use unknown codes on purpose.
+            let source = source.split("#[cfg(test)]").next().unwrap();
+            // Splitting at quotes gives the contents of the literals, and the
+            // code between them, that never looks like a code.
+            for piece in source.split('"') {
+                let looks_like_code = piece.contains('/')
+                    && !piece.starts_with('/')
+                    && !piece.ends_with('/')
+                    && piece
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c == '-' || c == '/');
+                if looks_like_code {
+                    codes.push((path.display().to_string(), piece.to_string()));
+                }
+            }
+        }
+        codes
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        for (i, explanation) in EXPLANATIONS.iter().enumerate() {
+            assert!(
+                EXPLANATIONS[i + 1..]
+                    .iter()
+                    .all(|other| other.code != explanation.code),
+                "{} is registered twice",
+                explanation.code
+            );
+        }
+    }
+
+    #[test]
+    fn every_emitted_code_is_registered() {
+        for (file, code) in codes_in_sources() {
+            assert!(
+                find(&code).is_some(),
+                "{code}, used in {file}, is not registered"
+            );
+        }
+    }
+
+    #[test]
+    fn every_registered_code_is_emitted() {
+        let emitted = codes_in_sources();
+        for explanation in EXPLANATIONS {
+            assert!(
+                emitted.iter().any(|(_, code)| code == explanation.code),
+                "{} is never emitted",
+                explanation.code
+            );
+        }
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        for explanation in EXPLANATIONS {
+            assert!(!explanation.summary.is_empty(), "{}", explanation.code);
+            assert!(!explanation.summary.ends_with('.'), "{}", explanation.code);
+            assert!(
+                explanation.description.ends_with('\n'),
+                "{}",
+                explanation.code
+            );
+
+            // Errors block the publication of the package: they should say
+            // why, and how to fix them.
+            let min_lines = if explanation.severity == Severity::Error {
+                3
+            } else {
+                1
+            };
+            assert!(
+                explanation.description.lines().count() >= min_lines,
+                "{} needs a longer explanation",
+                explanation.code
+            );
+        }
+    }
+
+    #[test]
+    fn documentation_urls_are_valid() {
+        for explanation in EXPLANATIONS {
+            let Some(url) = explanation.doc_url else {
+                continue;
+            };
+            assert!(
+                url.starts_with("https://github.com/typst/packages/")
+                    || url.starts_with("https://github.com/typst/package-check/"),
+                "{} links to {url}",
+                explanation.code
+            );
+            assert!(!url.contains(char::is_whitespace), "{}", explanation.code);
+        }
+        assert!(find("files/fonts").unwrap().doc_url.is_some());
+    }
+
+    #[test]
+    fn close_matches_suggest_typos() {
+        assert_eq!(
+            close_matches("import/relatve"),
+            ["import/relative", "import/outdated"]
+        );
+        assert!(close_matches("paths/absolute").contains(&"paths/absolute"));
+        assert!(close_matches("nothing-like-it").is_empty());
+    }
+}
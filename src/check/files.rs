@@ -0,0 +1,357 @@
+use std::{
+    cell::Cell,
+    fs::Metadata,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use eyre::Context;
+use ignore::overrides::{Override, OverrideBuilder};
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+
+thread_local! {
+    /// How many times a package directory was walked on this thread, to make
+    /// sure that the checks only do it once.
+    pub static WALKS: Cell<usize> = const { Cell::new(0) };
+    /// How many entries these walks visited, to make sure that they don't
+    /// go through excluded directories.
+    pub static VISITS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The files and directories of a package, listed once and shared by all the
+/// checks that need to look at them.
+pub struct FileInventory {
+    entries: Vec<FileEntry>,
+}
+
+pub struct FileEntry {
+    /// Path of the entry, relative to the package root.
+    pub path: PathBuf,
+    pub metadata: Metadata,
+    /// Whether this entry is matched by the `exclude` globs of the manifest.
+    pub excluded: bool,
+    /// Whether this entry is in the template directory (or is the template
+    /// directory itself).
+    pub in_template: bool,
+}
+
+impl FileInventory {
+    /// Walks `package_dir`, skipping hidden and ignored files like the rest
+    /// of the checks do.
+    ///
+    /// Excluded files are kept, with their `excluded` flag set. The contents
+    /// of excluded directories are not listed though, only the directories
+    /// themselves, as they can be huge (`node_modules`, sources of assets,
+    /// etc.).
+    pub fn new(package_dir: &Path, exclude: &Override, template_dir: Option<&Path>) -> Self {
+        // Paths from the manifest may start with `./`, but the ones we get
+        // from the walker don't.
+        let template_dir = template_dir.map(|dir| {
+            dir.components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .collect::<PathBuf>()
+        });
+        let template_dir = template_dir.as_deref();
+
+        WALKS.with(|walks| walks.set(walks.get() + 1));
+        // Excluded directories are not walked into, and not even read: they
+        // are recorded when the walker finds them, and listed with the other
+        // entries.
+        let pruned = Arc::new(Mutex::new(Vec::new()));
+        let walker = {
+            let package_dir = package_dir.to_owned();
+            let exclude = exclude.clone();
+            let pruned = pruned.clone();
+            ignore::WalkBuilder::new(&package_dir)
+                .filter_entry(move |entry| {
+                    VISITS.with(|visits| visits.set(visits.get() + 1));
+                    let Ok(path) = entry.path().strip_prefix(&package_dir) else {
+                        return true;
+                    };
+                    let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                    if is_dir
+                        && !path.as_os_str().is_empty()
+                        && exclude.matched(path, true).is_ignore()
+                    {
+                        pruned.lock().push(entry.path().to_owned());
+                        return false;
+                    }
+                    true
+                })
+                .build()
+        };
+
+        // Pruned directories are recorded when the walker looks for its next
+        // entry: they are taken before it, to keep the order of the walk.
+        let take_pruned = || {
+            pruned
+                .lock()
+                .drain(..)
+                .filter_map(|dir| Some((std::fs::metadata(&dir).ok()?, dir)))
+                .collect::<Vec<_>>()
+        };
+        let mut found = Vec::new();
+        for entry in walker.flatten() {
+            found.extend(take_pruned());
+            if let Ok(metadata) = entry.metadata() {
+                found.push((metadata, entry.into_path()));
+            }
+        }
+        found.extend(take_pruned());
+
+        let mut entries: Vec<FileEntry> = Vec::new();
+        for (metadata, path) in found {
+            let Ok(path) = path.strip_prefix(package_dir) else {
+                continue;
+            };
+            if path.as_os_str().is_empty() {
+                // The package root itself.
+                continue;
+            }
+
+            // Exclusion globs are relative to the package root, so there is
+            // no need to canonicalize paths to match them. Parent directories
+            // can't be excluded, as we don't walk into them.
+            let excluded = exclude.matched(path, metadata.is_dir()).is_ignore();
+
+            entries.push(FileEntry {
+                path: path.to_owned(),
+                in_template: template_dir.is_some_and(|dir| path.starts_with(dir)),
+                metadata,
+                excluded,
+            });
+        }
+        debug!(
+            "Found {} entries in {}",
+            entries.len(),
+            package_dir.display()
+        );
+
+        Self { entries }
+    }
+
+    /// Lists the files of the package in `package_dir`, with the exclusions
+    /// of its manifest.
+    pub fn for_package(package_dir: &Path) -> eyre::Result<Self> {
+        let manifest_contents = std::fs::read_to_string(package_dir.join("typst.toml"))
+            .context("Failed to read manifest contents.")?;
+        let manifest = toml_edit::ImDocument::parse(&manifest_contents)
+            .context("Failed to parse manifest contents")?;
+        let (exclude, _) = read_exclude(package_dir, &manifest)?;
+        Ok(Self::new(
+            package_dir,
+            &exclude,
+            template_root(&manifest).as_deref(),
+        ))
+    }
+
+    /// All the entries, including directories and excluded files.
+    pub fn entries(&self) -> impl Iterator<Item = &FileEntry> {
+        self.entries.iter()
+    }
+
+    /// Finds the entry for a path, relative to the package root.
+    pub fn get(&self, path: &Path) -> Option<&FileEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Entries that are not excluded, and that are not directories.
+    pub fn included_files(&self) -> impl Iterator<Item = &FileEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.excluded && !entry.metadata.is_dir())
+    }
+}
+
+/// Reads the `exclude` globs of a manifest.
+///
+/// Also returns the span of the `exclude` field.
+pub fn read_exclude(
+    package_dir: &Path,
+    manifest: &toml_edit::ImDocument<&String>,
+) -> eyre::Result<(Override, Range<usize>)> {
+    let exclude = exclude_array(manifest);
+
+    let mut exclude_globs = OverrideBuilder::new(
+        package_dir
+            .canonicalize()
+            .context("Failed to canonicalize package directory")?,
+    );
+    for exclusion in exclude.into_iter().flatten() {
+        let Some(exclusion) = exclusion.as_str() else {
+            continue;
+        };
+
+        if exclusion.starts_with('!') {
+            warn!("globs with '!' are not supported");
+            continue;
+        }
+
+        let exclusion = exclusion.trim_start_matches("./");
+        exclude_globs.add(&format!("!{exclusion}")).ok();
+    }
+    Ok((
+        exclude_globs.build().context("Invalid exclude globs")?,
+        exclude.and_then(|exclude| exclude.span()).unwrap_or(0..0),
+    ))
+}
+
+/// A single glob of the `exclude` field of a manifest.
+pub struct ExcludePattern {
+    /// The glob, as written in the manifest.
+    pub glob: String,
+    /// The line of the manifest where this glob is, starting at 1.
+    pub line: usize,
+    matcher: Override,
+}
+
+impl ExcludePattern {
+    /// Whether this glob excludes a path, relative to the package root.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.matcher.matched(path, is_dir).is_ignore()
+    }
+}
+
+/// Reads the `exclude` globs of a manifest one by one, to be able to tell
+/// which of them excludes a given file.
+///
+/// Globs that are not supported or invalid are skipped, like in
+/// [`read_exclude`].
+pub fn read_exclude_patterns(
+    package_dir: &Path,
+    manifest: &toml_edit::ImDocument<&String>,
+) -> eyre::Result<Vec<ExcludePattern>> {
+    let root = package_dir
+        .canonicalize()
+        .context("Failed to canonicalize package directory")?;
+
+    let mut patterns = Vec::new();
+    for exclusion in exclude_array(manifest).into_iter().flatten() {
+        let Some(glob) = exclusion.as_str() else {
+            continue;
+        };
+        if glob.starts_with('!') {
+            continue;
+        }
+
+        let mut builder = OverrideBuilder::new(&root);
+        if builder
+            .add(&format!("!{}", glob.trim_start_matches("./")))
+            .is_err()
+        {
+            continue;
+        }
+        let Ok(matcher) = builder.build() else {
+            continue;
+        };
+
+        let offset = exclusion.span().map(|span| span.start).unwrap_or(0);
+        patterns.push(ExcludePattern {
+            glob: glob.to_owned(),
+            line: manifest.raw()[..offset].matches('\n').count() + 1,
+            matcher,
+        });
+    }
+
+    Ok(patterns)
+}
+
+fn exclude_array<'a>(manifest: &'a toml_edit::ImDocument<&String>) -> Option<&'a toml_edit::Array> {
+    manifest
+        .get("package")
+        .and_then(|package| package.get("exclude"))
+        .and_then(|item| item.as_array())
+}
+
+/// The path of the template directory, relative to the package root.
+pub fn template_root(manifest: &toml_edit::ImDocument<&String>) -> Option<PathBuf> {
+    Some(PathBuf::from(
+        manifest
+            .get("template")
+            .and_then(|t| t.get("path"))?
+            .as_str()?,
+    ))
+}
+
+/// Formats a file size for humans.
+pub fn format_size(size: u64) -> String {
+    if size >= 1024 * 1024 {
+        format!("{:.1}MB", size as f64 / 1024.0 / 1024.0)
+    } else {
+        format!("{}kB", size.div_ceil(1024))
+    }
+}
+
+/// Whether the contents of a file are not text, and can't be displayed.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// A short description of the type of a binary file, based on its
+/// extension.
+pub fn binary_kind(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("ttf" | "otf" | "ttc" | "otc" | "woff" | "woff2") => "font",
+        Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "ico") => "image",
+        Some("wasm") => "WebAssembly module",
+        Some("pdf") => "PDF document",
+        Some("zip" | "gz" | "tar" | "xz" | "7z") => "archive",
+        _ => "binary file",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many entries are visited to list the files of a package.
+    fn visits(package_dir: &Path) -> (usize, FileInventory) {
+        let before = VISITS.with(|visits| visits.get());
+        let inventory = FileInventory::for_package(package_dir).unwrap();
+        (VISITS.with(|visits| visits.get()) - before, inventory)
+    }
+
+    #[test]
+    fn excluded_directories_are_not_walked_into() {
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-deep-exclusion-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("typst.toml"),
+            "[package]\nname = \"deep\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n\
+            exclude = [\"node_modules\"]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib.typ"), "").unwrap();
+        std::fs::create_dir(dir.join("node_modules")).unwrap();
+        let (shallow, _) = visits(&dir);
+
+        let mut level = dir.join("node_modules");
+        for depth in 0..20 {
+            for i in 0..20 {
+                std::fs::write(level.join(format!("file-{i}.js")), "").unwrap();
+            }
+            level = level.join(format!("level-{depth}"));
+            std::fs::create_dir(&level).unwrap();
+        }
+        let (deep, inventory) = visits(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(deep, shallow);
+        let node_modules = inventory.get(Path::new("node_modules")).unwrap();
+        assert!(node_modules.excluded);
+        assert!(!inventory
+            .entries()
+            .any(|entry| entry.path.starts_with("node_modules/level-0")));
+    }
+}
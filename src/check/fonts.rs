@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use codespan_reporting::diagnostic::Diagnostic;
+use typst::{
+    syntax::{
+        ast::{self, AstNode},
+        FileId, SyntaxNode, VirtualPath,
+    },
+    text::{Font, FontBook},
+};
+
+use crate::world::SystemWorld;
+
+use super::{files::FileInventory, label, Diagnostics};
+
+/// Warns about fonts that are used in the sources of the package but that are
+/// not embedded in Typst, as they will likely be missing for most users.
+///
+/// Only string literals given as `font` arguments are checked.
+pub fn check(diags: &mut Diagnostics, world: &SystemWorld, inventory: &FileInventory) {
+    let book = embedded_fonts();
+
+    let sources = inventory
+        .included_files()
+        .filter(|entry| entry.path.extension().is_some_and(|ext| ext == "typ"));
+    for entry in sources {
+        let fid = FileId::new(None, VirtualPath::new(&entry.path));
+        let Ok(source) = world.lookup(fid) else {
+            continue;
+        };
+
+        let mut families = Vec::new();
+        find_font_families(source.root(), &mut families);
+
+        let mut reported = HashSet::new();
+        for family in families {
+            let name = family.get().to_lowercase();
+            if book.contains_family(&name) || !reported.insert(name.clone()) {
+                continue;
+            }
+
+            let mut diag = Diagnostic::warning()
+                .with_code("files/fonts")
+                .with_labels(label(world, family.span()).into_iter().collect())
+                .with_message(format!(
+                    "The {} font is not embedded in Typst, \
+                    it will probably be missing for most users.",
+                    family.get()
+                ));
+            let similar = similar_families(&book, &name);
+            if !similar.is_empty() {
+                diag = diag.with_notes(vec![format!(
+                    "Similar fonts that are always available: {}",
+                    similar.join(", ")
+                )]);
+            }
+            diags.emit(diag);
+        }
+    }
+}
+
+/// Builds a font book with only the fonts that are embedded in Typst.
+fn embedded_fonts() -> FontBook {
+    let mut book = FontBook::new();
+    for data in typst_assets::fonts() {
+        let buffer = typst::foundations::Bytes::from_static(data);
+        for font in Font::iter(buffer) {
+            book.push(font.info().clone());
+        }
+    }
+    book
+}
+
+/// Collects the string literals given as font families, with the `font`
+/// argument of any function.
+fn find_font_families<'a>(node: &'a SyntaxNode, families: &mut Vec<ast::Str<'a>>) {
+    if let Some(named) = node.cast::<ast::Named>() {
+        if named.name().as_str() == "font" {
+            match named.expr() {
+                ast::Expr::Str(family) => families.push(family),
+                ast::Expr::Array(array) => {
+                    families.extend(array.items().filter_map(|item| match item {
+                        ast::ArrayItem::Pos(ast::Expr::Str(family)) => Some(family),
+                        _ => None,
+                    }))
+                }
+                // Computed font names can't be checked.
+                _ => {}
+            }
+        }
+    }
+
+    for child in node.children() {
+        find_font_families(child, families);
+    }
+}
+
+/// Embedded font families that share their first word with `family`.
+fn similar_families(book: &FontBook, family: &str) -> Vec<String> {
+    let Some(first_word) = family.split_whitespace().next() else {
+        return Vec::new();
+    };
+
+    book.families()
+        .map(|(name, _)| name)
+        .filter(|name| {
+            name.split_whitespace()
+                .next()
+                .is_some_and(|word| word.eq_ignore_ascii_case(first_word))
+        })
+        .take(3)
+        .map(ToOwned::to_owned)
+        .collect()
+}
@@ -0,0 +1,62 @@
+use codespan_reporting::diagnostic::Diagnostic;
+use typst::{
+    syntax::ast::{self, AstNode},
+    World,
+};
+
+use crate::world::SystemWorld;
+
+use super::{label, Diagnostics};
+
+/// Elements for which set and show rules would generally be used to
+/// configure a whole document.
+const DOCUMENT_ELEMENTS: &[&str] = &["page", "document", "text"];
+
+/// Warn about set and show rules at the top level of the entrypoint of
+/// packages that are not templates.
+///
+/// Only definitions are imported from a package, so these rules have no
+/// effect on the documents that use it. Authors generally expect them to
+/// apply, and should rather provide a function that users can apply with a
+/// show rule.
+pub fn check(diags: &mut Diagnostics, world: &SystemWorld) -> Option<()> {
+    let main = world.source(world.main()).ok()?;
+
+    for child in main.root().children() {
+        let (span, element) = if let Some(set) = child.cast::<ast::SetRule>() {
+            let ast::Expr::Ident(target) = set.target() else {
+                continue;
+            };
+            (set.span(), Some(target.get().clone()))
+        } else if let Some(show) = child.cast::<ast::ShowRule>() {
+            match show.selector() {
+                Some(ast::Expr::Ident(selector)) => (show.span(), Some(selector.get().clone())),
+                Some(_) => continue,
+                // `show: ...` applies to everything that comes after it.
+                None => (show.span(), None),
+            }
+        } else {
+            continue;
+        };
+
+        if element
+            .as_ref()
+            .is_some_and(|element| !DOCUMENT_ELEMENTS.contains(&element.as_str()))
+        {
+            continue;
+        }
+
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("style/global-rule")
+                .with_labels(label(world, span).into_iter().collect())
+                .with_message(
+                    "This rule has no effect on the documents that import this package. \
+                    If it is meant to configure the document of your users, \
+                    put it in a function that they can apply with a `show` rule.",
+                ),
+        );
+    }
+
+    Some(())
+}
@@ -16,97 +16,170 @@ use typst::{
 
 use crate::world::SystemWorld;
 
-use super::Diagnostics;
+use super::{files::FileInventory, Diagnostics};
 
 pub fn check(
     diags: &mut Diagnostics,
     package_spec: Option<&PackageSpec>,
     package_dir: &Path,
+    inventory: &FileInventory,
     world: &SystemWorld,
+    jobs: usize,
 ) -> eyre::Result<()> {
-    check_dir(diags, package_spec, package_dir, world)
-}
+    let sources = find_sources(package_dir, inventory);
 
-pub fn check_dir(
-    diags: &mut Diagnostics,
-    package_spec: Option<&PackageSpec>,
-    dir: &Path,
-    world: &SystemWorld,
-) -> eyre::Result<()> {
-    let root_path = world.root();
-    let main_path = root_path
+    let main_path = world
+        .root()
         .join(world.main().vpath().as_rootless_path())
         .canonicalize()
         .ok();
 
-    for ch in std::fs::read_dir(dir).context("Can't read directory")? {
-        let Ok(ch) = ch else {
+    // Each file is analyzed on its own, so they are split between a few
+    // threads. Each of them collects its diagnostics separately, and they are
+    // merged in the order of the files to keep the output deterministic.
+    let chunk_size = sources.len().div_ceil(jobs.max(1)).max(1);
+    let results = std::thread::scope(|scope| {
+        let threads = sources
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let main_path = main_path.as_deref();
+                scope.spawn(move || {
+                    let mut diags = Diagnostics::default();
+                    for path in chunk {
+                        check_source(&mut diags, package_spec, path, main_path, world)?;
+                    }
+                    eyre::Ok(diags)
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().expect("Import check panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    for res in results {
+        diags.extend(res?, Path::new(""));
+    }
+
+    Ok(())
+}
+
+/// Lists the packages imported by the Typst files of a package.
+///
+/// Only direct imports are listed, sorted and without duplicates.
+pub fn package_imports(package_dir: &Path, world: &SystemWorld) -> eyre::Result<Vec<PackageSpec>> {
+    let inventory = FileInventory::for_package(package_dir)?;
+    let sources = find_sources(package_dir, &inventory);
+
+    let mut packages = Vec::new();
+    for path in sources {
+        let Ok(relative_path) = path.strip_prefix(package_dir) else {
             continue;
         };
-        let Ok(meta) = ch.metadata() else {
+        let Ok(source) = world.lookup(FileId::new(None, VirtualPath::new(relative_path))) else {
             continue;
         };
-
-        let path = dir.join(ch.file_name());
-        if meta.is_dir() {
-            return check_dir(diags, package_spec, &path, world);
+        let imports = source
+            .root()
+            .children()
+            .filter_map(|ch| ch.cast::<ModuleImport>());
+        for import in imports {
+            let ast::Expr::Str(source_str) = import.source() else {
+                continue;
+            };
+            if let Ok(spec) = PackageSpec::from_str(source_str.get().as_str()) {
+                packages.push(spec);
+            }
         }
-        if path.extension().and_then(|ext| ext.to_str()) == Some("typ") {
-            let fid = FileId::new(
-                None,
-                VirtualPath::new(
-                    path.strip_prefix(root_path)
-                        // Not actually true
-                        .context(
-                            "Prefix striping failed even though `path` is built from `root_dir`",
-                        )?,
-                ),
-            );
-            let source = world.lookup(fid).context("Can't read source file")?;
-            let imports = source
-                .root()
-                .children()
-                .filter_map(|ch| ch.cast::<ModuleImport>());
-            for import in imports {
-                let ast::Expr::Str(source_str) = import.source() else {
-                    continue;
-                };
-                let import_path = path
-                    .parent()
-                    .unwrap_or(&PathBuf::new())
-                    .join(source_str.get().as_str())
-                    .canonicalize()
-                    .ok();
-                if main_path == import_path {
-                    diags
-                        .emit(Diagnostic::warning()
-                            .with_labels(vec![Label::primary(
-                                fid,
-                                world.range(import.span()).unwrap_or_default(),
-                            )])
-                            .with_message(
-                                "This import should use the package specification, not a relative path."
-                            )
-                        )
+    }
+    packages.sort_by_key(|spec| spec.to_string());
+    packages.dedup();
+
+    Ok(packages)
+}
+
+/// Lists the Typst files of a package, excluded or not.
+fn find_sources(package_dir: &Path, inventory: &FileInventory) -> Vec<PathBuf> {
+    inventory
+        .entries()
+        .filter(|entry| {
+            !entry.metadata.is_dir()
+                && entry.path.extension().and_then(|ext| ext.to_str()) == Some("typ")
+        })
+        .map(|entry| package_dir.join(&entry.path))
+        .collect()
+}
+
+fn check_source(
+    diags: &mut Diagnostics,
+    package_spec: Option<&PackageSpec>,
+    path: &Path,
+    main_path: Option<&Path>,
+    world: &SystemWorld,
+) -> eyre::Result<()> {
+    let fid = FileId::new(
+        None,
+        VirtualPath::new(
+            path.strip_prefix(world.root())
+                // Not actually true
+                .context("Prefix striping failed even though `path` is built from `root_dir`")?,
+        ),
+    );
+    let source = world.lookup(fid).context("Can't read source file")?;
+    let imports = source
+        .root()
+        .children()
+        .filter_map(|ch| ch.cast::<ModuleImport>());
+    for import in imports {
+        let ast::Expr::Str(source_str) = import.source() else {
+            continue;
+        };
+        let import_path = path
+            .parent()
+            .unwrap_or(&PathBuf::new())
+            .join(source_str.get().as_str())
+            .canonicalize()
+            .ok();
+        if main_path == import_path.as_deref() {
+            let diag = Diagnostic::warning()
+                .with_code("import/relative")
+                .with_labels(vec![Label::primary(
+                    fid,
+                    world.range(import.span()).unwrap_or_default(),
+                )])
+                .with_message(
+                    "This import should use the package specification, not a relative path.",
+                );
+            match (package_spec, world.range(source_str.span())) {
+                (Some(package_spec), Some(range)) => {
+                    diags.emit_with_suggestion(diag, fid, range, format!("\"{}\"", package_spec))
                 }
+                _ => diags.emit(diag),
+            }
+        }
 
-                if let Some(package_spec) = package_spec {
-                    if let Ok(import_spec) = PackageSpec::from_str(source_str.get().as_str()) {
-                        if package_spec.namespace == import_spec.namespace
-                            && package_spec.name == import_spec.name
-                            && package_spec.version != import_spec.version
-                        {
-                            diags.emit(
-                                Diagnostic::warning()
-                                    .with_labels(vec![Label::primary(
-                                        fid,
-                                        world.range(import.span()).unwrap_or_default(),
-                                    )])
-                                    .with_message(
-                                        "This import seems to use an older version of the package.",
-                                    ),
-                            )
-                        }
+        if let Some(package_spec) = package_spec {
+            if let Ok(import_spec) = PackageSpec::from_str(source_str.get().as_str()) {
+                if package_spec.namespace == import_spec.namespace
+                    && package_spec.name == import_spec.name
+                    && package_spec.version != import_spec.version
+                {
+                    let diag = Diagnostic::warning()
+                        .with_code("import/outdated")
+                        .with_labels(vec![Label::primary(
+                            fid,
+                            world.range(import.span()).unwrap_or_default(),
+                        )])
+                        .with_message("This import seems to use an older version of the package.");
+                    match world.range(source_str.span()) {
+                        Some(range) => diags.emit_with_suggestion(
+                            diag,
+                            fid,
+                            range,
+                            format!("\"{}\"", package_spec),
+                        ),
+                        None => diags.emit(diag),
                     }
                 }
             }
@@ -115,3 +188,59 @@ pub fn check_dir(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lists the Typst files of a directory by reading it recursively, like
+    /// the import checks did before using the inventory of the package.
+    fn read_sources(dir: &Path, sources: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                read_sources(&path, sources);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("typ") {
+                sources.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn sources_are_found_in_the_inventory() {
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-many-sources-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("typst.toml"),
+            "[package]\nname = \"many\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n\
+            exclude = [\"*.draft.typ\"]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib.typ"), "").unwrap();
+        for i in 0..300 {
+            let chapter = dir.join(format!("chapters/{}", i / 30));
+            std::fs::create_dir_all(&chapter).unwrap();
+            std::fs::write(chapter.join(format!("part-{i}.typ")), "").unwrap();
+            std::fs::write(chapter.join(format!("figure-{i}.svg")), "").unwrap();
+        }
+        // Excluded files are still checked.
+        for i in 0..10 {
+            std::fs::write(dir.join(format!("notes-{i}.draft.typ")), "").unwrap();
+        }
+
+        let inventory = FileInventory::for_package(&dir).unwrap();
+        let mut sources = find_sources(&dir, &inventory);
+        sources.sort();
+        let mut expected = Vec::new();
+        read_sources(&dir, &mut expected);
+        expected.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(sources.len(), 311);
+        assert_eq!(sources, expected);
+    }
+}
@@ -87,7 +87,7 @@ fn check_source(
                         .to_owned(),
                 labels: label(world, name_ident.span()).into_iter().collect(),
                 notes: Vec::new(),
-                code: None,
+                code: Some("kebab-case/value".to_owned()),
             })
         }
 
@@ -111,7 +111,7 @@ fn check_source(
                             .to_owned(),
                         labels: label(world, span).into_iter().collect(),
                         notes: Vec::new(),
-                        code: None,
+                        code: Some("kebab-case/parameter".to_owned()),
                     })
                 }
             }
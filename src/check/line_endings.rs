@@ -0,0 +1,61 @@
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use typst::{
+    syntax::{FileId, VirtualPath},
+    World,
+};
+
+use crate::world::SystemWorld;
+
+use super::{files::FileInventory, Diagnostics};
+
+const CODE: &str = "style/line-endings";
+
+/// Reports text files that don't use LF line endings, or that don't end with
+/// a new line.
+///
+/// At most one diagnostic is reported for each file.
+pub fn check(diags: &mut Diagnostics, world: &SystemWorld, inventory: &FileInventory) {
+    let text_files = inventory.included_files().filter(|entry| {
+        matches!(
+            entry.path.extension().and_then(|ext| ext.to_str()),
+            Some("typ" | "toml" | "md")
+        )
+    });
+    for entry in text_files {
+        let fid = FileId::new(None, VirtualPath::new(&entry.path));
+        let Ok(bytes) = world.file(fid) else {
+            continue;
+        };
+        if bytes.is_empty() {
+            continue;
+        }
+
+        let lines = bytes.iter().filter(|b| **b == b'\n').count();
+        let crlf = bytes.windows(2).filter(|w| w == b"\r\n").count();
+        let missing_newline = !bytes.ends_with(b"\n");
+
+        let diag = if crlf > 0 {
+            let diag = Diagnostic::warning().with_message(if crlf == lines {
+                "This file uses CRLF line endings. Please use LF line endings instead."
+            } else {
+                "This file uses both CRLF and LF line endings. Please only use LF line endings."
+            });
+            if missing_newline {
+                diag.with_notes(vec![
+                    "This file also doesn't end with a new line.".to_owned()
+                ])
+            } else {
+                diag
+            }
+        } else if missing_newline {
+            Diagnostic::note().with_message("This file doesn't end with a new line.")
+        } else {
+            continue;
+        };
+
+        diags.emit(
+            diag.with_code(CODE)
+                .with_labels(vec![Label::primary(fid, 0..0)]),
+        );
+    }
+}
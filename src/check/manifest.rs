@@ -1,23 +1,33 @@
 use std::{
-    ops::Range,
     os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use eyre::{Context, ContextCompat};
-use ignore::overrides::{Override, OverrideBuilder};
+use ignore::overrides::Override;
+use tokio::sync::Semaphore;
 use toml_edit::Item;
-use tracing::{debug, warn};
-use typst::syntax::{
-    package::{PackageSpec, PackageVersion},
-    FileId, VirtualPath,
+use tracing::debug;
+use typst::{
+    syntax::{
+        ast,
+        package::{PackageSpec, PackageVersion},
+        FileId, VirtualPath,
+    },
+    World,
 };
 
 use crate::{
-    check::{file_size, Diagnostics},
-    world::SystemWorld,
+    check::{
+        file_size,
+        files::{format_size, read_exclude, FileInventory},
+        fonts, line_endings, paths, placeholders, plugins, Diagnostics, Fix, TYPST_VERSION,
+    },
+    http::{http_client, is_offline},
+    world::{FileStore, SystemWorld},
 };
 
 pub struct Worlds {
@@ -29,6 +39,8 @@ pub async fn check(
     package_dir: &Path,
     diags: &mut Diagnostics,
     package_spec: Option<&PackageSpec>,
+    font_paths: &[PathBuf],
+    inventory: &FileInventory,
 ) -> eyre::Result<Worlds> {
     let manifest_path = package_dir.join("typst.toml");
     debug!("Reading manifest at {}", &manifest_path.display());
@@ -45,7 +57,9 @@ pub async fn check(
             .context("Packages must specify an `entrypoint` in their manifest")?,
     );
     let world = SystemWorld::new(entrypoint, package_dir.to_owned())
-        .map_err(|e| eyre::Report::msg(e).wrap_err("Failed to initialize the Typst compiler"))?;
+        .map_err(|e| eyre::Report::msg(e).wrap_err("Failed to initialize the Typst compiler"))?
+        .with_font_paths(font_paths);
+    diags.lap("compiler setup");
 
     let manifest_file_id = FileId::new(None, VirtualPath::new("typst.toml"));
 
@@ -54,6 +68,7 @@ pub async fn check(
         // have panicked before if the `package` table is missing.
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/package/missing")
                 .with_labels(vec![Label::primary(manifest_file_id, 0..0)])
                 .with_message(
                     "All `typst.toml` must contain a [package] section. \
@@ -75,15 +90,52 @@ pub async fn check(
     let res = check_universe_fields(diags, manifest_file_id, &manifest);
     diags.maybe_emit(res);
 
-    let res = check_file_names(diags, package_dir);
-    diags.maybe_emit(res);
+    check_entrypoints(diags, manifest_file_id, &manifest, package_dir);
+    diags.lap("manifest");
+    if diags.should_stop() {
+        return Ok(Worlds {
+            package: world,
+            template: None,
+        });
+    }
+
+    let (exclude, exclude_span) = read_exclude(package_dir, &manifest)?;
+
+    check_file_names(diags, inventory);
+    plugins::check(diags, &world, inventory, &exclude, exclude_span);
+    diags.lap("plugins");
+    line_endings::check(diags, &world, inventory);
+    placeholders::check(diags, &world, inventory);
+    diags.lap("file contents");
+    fonts::check(diags, &world, inventory);
+    diags.lap("fonts");
+    if diags.should_stop() {
+        return Ok(Worlds {
+            package: world,
+            template: None,
+        });
+    }
+    paths::check(diags, &world, inventory);
+    suggest_excluding_docs(diags, &world, inventory);
 
     let res = dont_over_exclude(diags, package_dir, manifest_file_id, &manifest);
     diags.maybe_emit(res);
+    diags.lap("exclusions");
+    if diags.should_stop() {
+        return Ok(Worlds {
+            package: world,
+            template: None,
+        });
+    }
 
     check_repo(diags, manifest_file_id, &manifest).await;
-
-    let (exclude, _) = read_exclude(package_dir, &manifest)?;
+    diags.lap("repository");
+    if diags.should_stop() {
+        return Ok(Worlds {
+            package: world,
+            template: None,
+        });
+    }
 
     let template_world = if let (Some(name), Some(version)) = (name, version) {
         let inferred_package_spec = PackageSpec {
@@ -96,17 +148,21 @@ pub async fn check(
             &manifest,
             package_dir,
             package_spec.unwrap_or(&inferred_package_spec),
-            exclude.clone(),
+            exclude,
+            world.file_store().clone(),
+            font_paths,
         )
     } else {
         None
     };
 
-    dont_exclude_template_files(diags, &manifest, package_dir, exclude);
+    dont_exclude_template_files(diags, package_dir, inventory);
     let thumbnail_path = check_thumbnail(diags, &manifest, manifest_file_id, package_dir);
+    diags.lap("template");
 
-    let res = exclude_large_files(diags, package_dir, &manifest, thumbnail_path);
+    let res = exclude_large_files(diags, package_dir, inventory, thumbnail_path).await;
     diags.maybe_emit(res);
+    diags.lap("large files");
 
     Ok(Worlds {
         package: world,
@@ -126,6 +182,7 @@ fn check_name(
     else {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/name/missing")
                 .with_labels(vec![Label::primary(manifest_file_id, 0..0)])
                 .with_message(
                     "All `typst.toml` must contain a `name` field. \
@@ -146,7 +203,11 @@ fn check_name(
     )]);
 
     let Some(name) = name.as_str() else {
-        diags.emit(error.with_message("`name` must be a string."));
+        diags.emit(
+            error
+                .with_code("manifest/name/invalid")
+                .with_message("`name` must be a string."),
+        );
         return None;
     };
 
@@ -154,23 +215,32 @@ fn check_name(
         diags.emit(
             error
                 .clone()
+                .with_code("manifest/name/kebab-case")
                 .with_message("Please use kebab-case for package names."),
         )
     }
 
     if name.contains("typst") {
-        diags.emit(warning.with_message("Package names should generally not include \"typst\"."));
+        diags.emit(
+            warning
+                .with_code("manifest/name/typst")
+                .with_message("Package names should generally not include \"typst\"."),
+        );
     }
 
     if let Some(package_spec) = package_spec {
         if name != package_spec.name {
-            diags.emit(error.with_message(format!(
-                "Unexpected package name. `{name}` was expected. \
+            diags.emit(
+                error
+                    .with_code("manifest/name/unexpected")
+                    .with_message(format!(
+                        "Unexpected package name. `{name}` was expected. \
                         If you want to publish a new package, create a new \
                         directory in `packages/{namespace}/`.",
-                name = package_spec.name,
-                namespace = package_spec.namespace,
-            )))
+                        name = package_spec.name,
+                        namespace = package_spec.namespace,
+                    )),
+            )
         }
     }
 
@@ -189,6 +259,7 @@ fn check_version(
     else {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/version/missing")
                 .with_labels(vec![Label::primary(manifest_file_id, 0..0)])
                 .with_message(
                     "All `typst.toml` must contain a `version` field. \
@@ -205,12 +276,16 @@ fn check_version(
     )]);
 
     let Some(version) = version.as_str() else {
-        diags.emit(error.with_message("`version` must be a string."));
+        diags.emit(
+            error
+                .with_code("manifest/version/invalid")
+                .with_message("`version` must be a string."),
+        );
         return None;
     };
 
     let Ok(version) = version.parse::<PackageVersion>() else {
-        diags.emit(error.with_message(
+        diags.emit(error.with_code("manifest/version/invalid").with_message(
             "`version` must be a valid semantic version \
                 (i.e follow the `MAJOR.MINOR.PATCH` format).",
         ));
@@ -219,14 +294,18 @@ fn check_version(
 
     if let Some(package_spec) = package_spec {
         if version != package_spec.version {
-            diags.emit(error.with_message(format!(
-                "Unexpected version number. `{version}` was expected. \
+            diags.emit(
+                error
+                    .with_code("manifest/version/unexpected")
+                    .with_message(format!(
+                        "Unexpected version number. `{version}` was expected. \
                         If you want to publish a new version, create a new \
                         directory in `packages/{namespace}/{name}`.",
-                version = package_spec.version,
-                name = package_spec.name,
-                namespace = package_spec.namespace,
-            )))
+                        version = package_spec.version,
+                        name = package_spec.name,
+                        namespace = package_spec.namespace,
+                    )),
+            )
         }
     }
 
@@ -242,38 +321,102 @@ fn check_compiler_version(
     let Some(compiler_str) = compiler.as_str() else {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/compiler/invalid")
                 .with_labels(vec![Label::primary(manifest_file_id, compiler.span()?)])
                 .with_message("Compiler version should be a string"),
         );
         return None;
     };
 
-    if PackageVersion::from_str(compiler_str).is_err() {
+    let Ok(compiler_version) = PackageVersion::from_str(compiler_str) else {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/compiler/invalid")
                 .with_labels(vec![Label::primary(manifest_file_id, compiler.span()?)])
                 .with_message("Compiler version should be a valid semantic version, with three components (for example `0.12.0`)"),
         );
         return None;
+    };
+
+    let checker_version = PackageVersion::from_str(TYPST_VERSION).ok()?;
+    if compiler_version > checker_version {
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("manifest/compiler/newer")
+                .with_labels(vec![Label::primary(manifest_file_id, compiler.span()?)])
+                .with_message(format!(
+                    "This package requires Typst {compiler_version}, but the checks use Typst {checker_version}."
+                ))
+                .with_notes(vec![
+                    "Compilation errors may come from features that this version doesn't have yet."
+                        .to_owned(),
+                ]),
+        );
     }
 
     Some(())
 }
 
-fn exclude_large_files(
+/// Maximum number of `wasm-opt` runs at the same time, across all the
+/// packages being checked.
+static WASM_OPT_PERMITS: Semaphore = Semaphore::const_new(2);
+
+/// Optimizes a WASM file for size, and returns how much smaller (in kB) it
+/// gets, if it does.
+///
+/// This can take a while for large plugins, and should not be called from
+/// the async runtime directly.
+fn wasm_opt_savings(path: &Path) -> Option<u64> {
+    // Several files with the same name may be optimized at the same time.
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let out = std::env::temp_dir().join(format!(
+        "{}-{run}-{}",
+        std::process::id(),
+        path.file_name()?.to_string_lossy()
+    ));
+
+    let wasm_opt_result = wasm_opt::OptimizationOptions::new_optimize_for_size()
+        // Explicitely enable and disable features to best match what wasmi supports
+        // https://github.com/wasmi-labs/wasmi?tab=readme-ov-file#webassembly-proposals
+        .enable_feature(wasm_opt::Feature::MutableGlobals)
+        .enable_feature(wasm_opt::Feature::TruncSat)
+        .enable_feature(wasm_opt::Feature::SignExt)
+        .enable_feature(wasm_opt::Feature::Multivalue)
+        .enable_feature(wasm_opt::Feature::BulkMemory)
+        .enable_feature(wasm_opt::Feature::ReferenceTypes)
+        .enable_feature(wasm_opt::Feature::TailCall)
+        .enable_feature(wasm_opt::Feature::ExtendedConst)
+        .enable_feature(wasm_opt::Feature::MultiMemory)
+        .disable_feature(wasm_opt::Feature::Simd)
+        .disable_feature(wasm_opt::Feature::RelaxedSimd)
+        .disable_feature(wasm_opt::Feature::Gc)
+        .disable_feature(wasm_opt::Feature::ExceptionHandling)
+        .run(path, &out);
+    wasm_opt_result.ok()?;
+
+    let original_size = std::fs::metadata(path).map(|m| m.size());
+    let new_size = std::fs::metadata(&out).map(|m| m.size());
+    std::fs::remove_file(out).ok();
+
+    match (new_size, original_size) {
+        (Ok(new_size), Ok(original_size)) if new_size < original_size => {
+            Some((original_size - new_size) / 1024)
+        }
+        _ => None,
+    }
+}
+
+async fn exclude_large_files(
     diags: &mut Diagnostics,
     package_dir: &Path,
-    manifest: &toml_edit::ImDocument<&String>,
+    inventory: &FileInventory,
     thumbnail_path: Option<PathBuf>,
 ) -> eyre::Result<()> {
-    let template_root = template_root(manifest);
-    let template_dir = template_root.and_then(|root| package_dir.join(&root).canonicalize().ok());
-    let (exclude, _) = read_exclude(package_dir, manifest)?;
-
     const REALLY_LARGE: u64 = 50 * 1024 * 1024;
 
-    let large_files = file_size::find_large_files(package_dir, exclude.clone());
-    for (path, size) in large_files? {
+    let large_files = file_size::find_large_files(inventory);
+    for (path, size) in large_files {
         if Some(path.as_ref())
             == thumbnail_path
                 .as_ref()
@@ -284,58 +427,24 @@ fn exclude_large_files(
         }
 
         if path.extension().and_then(|ext| ext.to_str()) == Some("wasm") {
-            let path = package_dir.join(&path);
-            if let Some(file_name) = path.file_name() {
-                let out = std::env::temp_dir().join(file_name);
-
-                let wasm_opt_result = wasm_opt::OptimizationOptions::new_optimize_for_size()
-                    // Explicitely enable and disable features to best match what wasmi supports
-                    // https://github.com/wasmi-labs/wasmi?tab=readme-ov-file#webassembly-proposals
-                    .enable_feature(wasm_opt::Feature::MutableGlobals)
-                    .enable_feature(wasm_opt::Feature::TruncSat)
-                    .enable_feature(wasm_opt::Feature::SignExt)
-                    .enable_feature(wasm_opt::Feature::Multivalue)
-                    .enable_feature(wasm_opt::Feature::BulkMemory)
-                    .enable_feature(wasm_opt::Feature::ReferenceTypes)
-                    .enable_feature(wasm_opt::Feature::TailCall)
-                    .enable_feature(wasm_opt::Feature::ExtendedConst)
-                    .enable_feature(wasm_opt::Feature::MultiMemory)
-                    .disable_feature(wasm_opt::Feature::Simd)
-                    .disable_feature(wasm_opt::Feature::RelaxedSimd)
-                    .disable_feature(wasm_opt::Feature::Gc)
-                    .disable_feature(wasm_opt::Feature::ExceptionHandling)
-                    .run(&path, &out);
-
-                if wasm_opt_result.is_ok() {
-                    let original_size = std::fs::metadata(&path).map(|m| m.size());
-                    let new_size = std::fs::metadata(&out).map(|m| m.size());
-
-                    match (new_size, original_size) {
-                        (Ok(new_size), Ok(original_size)) if new_size < original_size => {
-                            let diff = (original_size - new_size) / 1024;
-
-                            if diff > 20 {
-                                diags.emit(
-                                    Diagnostic::warning()
-                                        .with_labels(vec![Label::primary(
-                                            FileId::new(
-                                                None,
-                                                VirtualPath::new(path.strip_prefix(package_dir)?),
-                                            ),
-                                            0..0,
-                                        )])
-                                        .with_message(format!(
-                                        "This file could be {diff}kB smaller with `wasm-opt -Os`."
-                                    )),
-                                );
-                            }
-                        }
-                        _ => {}
-                    }
-
-                    // TODO: ideally this should be async
-                    std::fs::remove_file(out).ok();
-                }
+            let full_path = package_dir.join(&path);
+            let _permit = WASM_OPT_PERMITS.acquire().await?;
+            let savings = tokio::task::spawn_blocking(move || wasm_opt_savings(&full_path))
+                .await
+                .context("wasm-opt crashed")?;
+
+            if let Some(diff) = savings.filter(|diff| *diff > 20) {
+                diags.emit(
+                    Diagnostic::warning()
+                        .with_code("files/wasm-opt")
+                        .with_labels(vec![Label::primary(
+                            FileId::new(None, VirtualPath::new(&path)),
+                            0..0,
+                        )])
+                        .with_message(format!(
+                            "This file could be {diff}kB smaller with `wasm-opt -Os`."
+                        )),
+                );
             }
 
             // Don't suggest to exclude WASM files, they are generally necessary
@@ -351,7 +460,7 @@ fn exclude_large_files(
                 If possible, do not include it in this repository at all.",
                 size = size / 1024 / 1024
             )
-        } else if !exclude.matched(path, false).is_ignore() {
+        } else {
             format!(
                 "This file is quite large ({size}MB). \
                 If it is not required to use the package \
@@ -359,53 +468,34 @@ fn exclude_large_files(
                 it should be added to `exclude` in your `typst.toml`.",
                 size = size / 1024 / 1024
             )
-        } else {
-            continue;
         };
 
-        diags.emit(
-            Diagnostic::warning()
-                .with_labels(vec![Label::primary(fid, 0..0)])
-                .with_message(message),
-        )
+        let diag = Diagnostic::warning()
+            .with_code("files/large")
+            .with_labels(vec![Label::primary(fid, 0..0)])
+            .with_message(message);
+        if size > REALLY_LARGE {
+            diags.emit(diag)
+        } else {
+            diags.emit_with_fix(diag, Fix::Exclude(path))
+        }
     }
 
     // Also exclude examples
-    for ch in ignore::WalkBuilder::new(package_dir)
-        .overrides(exclude)
-        .build()
-    {
-        let Ok(ch) = ch else {
-            continue;
-        };
-
-        let Ok(metadata) = ch.metadata() else {
-            continue;
-        };
-
-        if metadata.is_dir() {
-            continue;
-        }
-
-        if template_dir
-            .as_ref()
-            .is_some_and(|template_dir| ch.path().starts_with(template_dir))
-        {
+    for entry in inventory.included_files() {
+        if entry.in_template {
             // Don't exclude template files, even if they contain "example" or "test" in their name.
             continue;
         }
 
-        let relative_path = ch
-            .path()
-            .strip_prefix(package_dir)
-            .context("Child path is not part of parent path")?;
-
-        let file_name = ch.file_name();
+        let Some(file_name) = entry.path.file_name() else {
+            continue;
+        };
         let file_name_str = file_name.to_string_lossy();
-        let file_id = FileId::new(None, VirtualPath::new(relative_path));
+        let file_id = FileId::new(None, VirtualPath::new(&entry.path));
         let warning = Diagnostic::warning().with_labels(vec![Label::primary(file_id, 0..0)]);
         if file_name_str.contains("example") {
-            diags.emit(warning.clone().with_message(
+            diags.emit(warning.clone().with_code("files/example").with_message(
                 "This file seems to be an example, \
                     and should probably be added to `exclude` in your `typst.toml`.",
             ));
@@ -413,7 +503,7 @@ fn exclude_large_files(
         }
 
         if file_name_str.contains("test") {
-            diags.emit(warning.clone().with_message(
+            diags.emit(warning.clone().with_code("files/test").with_message(
                 "This file seems to be a test, \
                     and should probably be added to `exclude` in your `typst.toml`.",
             ));
@@ -424,6 +514,100 @@ fn exclude_large_files(
     Ok(())
 }
 
+/// Directories that generally contain documentation.
+const DOCS_DIRS: &[&str] = &["docs", "manual"];
+
+/// Suggest to exclude documentation files, that are not needed to use the
+/// package.
+fn suggest_excluding_docs(diags: &mut Diagnostics, world: &SystemWorld, inventory: &FileInventory) {
+    let main = world.main();
+    let imported_from_main: Vec<PathBuf> = world
+        .lookup(main)
+        .map(|source| {
+            source
+                .root()
+                .children()
+                .filter_map(|child| {
+                    let path = match child.cast::<ast::Expr>()? {
+                        ast::Expr::Import(import) => import.source(),
+                        ast::Expr::Include(include) => include.source(),
+                        _ => return None,
+                    };
+                    let ast::Expr::Str(path) = path else {
+                        return None;
+                    };
+                    Some(
+                        main.vpath()
+                            .join(path.get().as_str())
+                            .as_rootless_path()
+                            .to_owned(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut docs_dirs = Vec::new();
+    for dir in DOCS_DIRS {
+        let dir = Path::new(dir);
+        let Some(entry) = inventory.get(dir) else {
+            continue;
+        };
+        if entry.excluded
+            || !entry.metadata.is_dir()
+            || main.vpath().as_rootless_path().starts_with(dir)
+            || imported_from_main.iter().any(|path| path.starts_with(dir))
+        {
+            continue;
+        }
+
+        let size: u64 = inventory
+            .included_files()
+            .filter(|entry| entry.path.starts_with(dir))
+            .map(|entry| entry.metadata.len())
+            .sum();
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("files/docs")
+                .with_labels(vec![Label::primary(
+                    FileId::new(None, VirtualPath::new(dir)),
+                    0..0,
+                )])
+                .with_message(format!(
+                    "This directory seems to contain documentation ({}), \
+                    that is not needed to use the package. \
+                    Consider adding \"{}/*\" to `exclude` in your `typst.toml`.",
+                    format_size(size),
+                    dir.display()
+                )),
+        );
+        docs_dirs.push(dir);
+    }
+
+    let pdfs = inventory.included_files().filter(|entry| {
+        !entry.in_template
+            && entry.path.extension().is_some_and(|ext| ext == "pdf")
+            && !docs_dirs.iter().any(|dir| entry.path.starts_with(dir))
+            // Large files are already reported.
+            && entry.metadata.len() <= file_size::SIZE_THRESHOLD
+    });
+    for pdf in pdfs {
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("files/pdf")
+                .with_labels(vec![Label::primary(
+                    FileId::new(None, VirtualPath::new(&pdf.path)),
+                    0..0,
+                )])
+                .with_message(format!(
+                    "This PDF file ({}) is probably not needed to use the package, \
+                    and should be added to `exclude` in your `typst.toml`.",
+                    format_size(pdf.metadata.len())
+                )),
+        );
+    }
+}
+
 fn dont_over_exclude(
     diags: &mut Diagnostics,
     package_dir: &Path,
@@ -438,47 +622,59 @@ fn dont_over_exclude(
         diags.emit(
             warning
                 .clone()
+                .with_code("exclude/license")
                 .with_message("Your LICENSE file should not be excluded."),
         );
     }
 
     if exclude.matched("README.md", false).is_ignore() {
-        diags.emit(warning.with_message("Your README.md file should not be excluded."));
+        diags.emit(
+            warning
+                .with_code("exclude/readme")
+                .with_message("Your README.md file should not be excluded."),
+        );
     }
 
     Ok(())
 }
 
-fn check_file_names(diags: &mut Diagnostics, package_dir: &Path) -> eyre::Result<()> {
-    for ch in std::fs::read_dir(package_dir).context("Failed to read package directory")? {
-        let mut error_for_file = |path, message| {
+fn check_file_names(diags: &mut Diagnostics, inventory: &FileInventory) {
+    // Only look at files at the root of the package.
+    let root_files = inventory
+        .entries()
+        .filter(|entry| entry.metadata.is_file() && entry.path.components().count() == 1);
+    for entry in root_files {
+        let mut error_for_file = |path: &Path, code: &str, message, fixed: String| {
             let file_id = FileId::new(None, VirtualPath::new(path));
-            diags.emit(
+            diags.emit_with_fix(
                 Diagnostic::error()
+                    .with_code(code)
                     .with_labels(vec![Label::primary(file_id, 0..0)])
                     .with_message(message),
+                Fix::Rename {
+                    from: path.to_owned(),
+                    to: PathBuf::from(fixed),
+                },
             )
         };
 
-        let Ok(ch) = ch else {
-            continue;
-        };
-        let Ok(meta) = ch.metadata() else {
-            continue;
-        };
-        if !meta.is_file() {
-            continue;
-        }
-
-        let file_name = ch.file_name();
-        let file_path = Path::new(&file_name);
+        let file_path = entry.path.as_path();
         let stem = file_path
             .file_stem()
             .map(|s| s.to_string_lossy().into_owned());
         let stem_uppercase = stem.as_ref().map(|s| s.to_uppercase());
 
         if stem_uppercase.as_deref() == Some("LICENCE") {
-            error_for_file(file_path, "This file should be named LICENSE.");
+            let fixed = match file_path.extension() {
+                Some(ext) => format!("LICENSE.{}", ext.to_string_lossy()),
+                None => "LICENSE".to_owned(),
+            };
+            error_for_file(
+                file_path,
+                "files/licence",
+                "This file should be named LICENSE.",
+                fixed,
+            );
         }
 
         if (stem_uppercase.as_deref() == Some("LICENSE")
@@ -496,15 +692,15 @@ fn check_file_names(diags: &mut Diagnostics, package_dir: &Path) -> eyre::Result
             };
             error_for_file(
                 file_path,
+                "files/all-caps",
                 &format!(
                     "To keep consistency, please use \
                         ALL CAPS for the name of this file (i.e. {fixed})"
                 ),
+                fixed,
             )
         }
     }
-
-    Ok(())
 }
 
 /// Some fields are optional for the bundler, but required to be published in Typst Universe.
@@ -519,6 +715,9 @@ fn check_universe_fields(
         .context("[package] not found")?
         .as_table()
         .context("[package] is not a table")?;
+    // Missing fields are reported on the header of the table.
+    let pkg_span = pkg.span().unwrap_or_default();
+    let span_of = |item: &Item| item.span().unwrap_or_else(|| pkg_span.clone());
 
     if let Some((license, span)) = pkg
         .get("license")
@@ -530,6 +729,7 @@ fn check_universe_fields(
                     if !id.is_osi_approved() {
                         diags.emit(
                             Diagnostic::error()
+                                .with_code("manifest/license/not-osi")
                                 .with_message("The `license` field should be OSI approved")
                                 .with_labels(vec![Label::primary(manifest_file_id, span.clone())]),
                         );
@@ -537,6 +737,7 @@ fn check_universe_fields(
                 } else {
                     diags.emit(
                         Diagnostic::error()
+                            .with_code("manifest/license/referencer")
                             .with_message("The `license` field should not contain a referencer")
                             .with_labels(vec![Label::primary(manifest_file_id, span.clone())]),
                     );
@@ -545,62 +746,164 @@ fn check_universe_fields(
         } else {
             diags.emit(
                 Diagnostic::error()
+                    .with_code("manifest/license/invalid")
                     .with_message("The `license` field should be a valid SPDX-2 expression")
                     .with_labels(vec![Label::primary(manifest_file_id, span.clone())]),
             );
         }
     } else {
+        let (code, message, span) = match pkg.get("license") {
+            Some(license) => (
+                "manifest/license/invalid",
+                "The `license` field should be a string",
+                span_of(license),
+            ),
+            None => (
+                "manifest/license/missing",
+                "The `license` field is missing",
+                pkg_span.clone(),
+            ),
+        };
         diags.emit(
             Diagnostic::error()
-                .with_message("The `license` field should be a string")
-                .with_labels(vec![Label::primary(manifest_file_id, 0..0)]),
+                .with_code(code)
+                .with_message(message)
+                .with_labels(vec![Label::primary(manifest_file_id, span)]),
         );
     }
 
-    if pkg.get("description").map(|d| !d.is_str()).unwrap_or(true) {
-        diags.emit(
+    match pkg.get("description") {
+        Some(description) if description.is_str() => {}
+        Some(description) => diags.emit(
             Diagnostic::error()
+                .with_code("manifest/description/invalid")
                 .with_message("The `description` field should be a string")
-                .with_labels(vec![Label::primary(manifest_file_id, 0..0)]),
-        );
+                .with_labels(vec![Label::primary(manifest_file_id, span_of(description))]),
+        ),
+        None => diags.emit(
+            Diagnostic::error()
+                .with_code("manifest/description/missing")
+                .with_message("The `description` field is missing")
+                .with_labels(vec![Label::primary(manifest_file_id, pkg_span.clone())]),
+        ),
     }
 
-    if pkg
-        .get("authors")
-        .and_then(|a| a.as_array())
-        .map(|a| a.iter().any(|item| !item.is_str()))
-        .unwrap_or(true)
-    {
-        diags.emit(
+    match pkg.get("authors") {
+        Some(authors) => match authors.as_array() {
+            Some(array) => {
+                for author in array.iter().filter(|author| !author.is_str()) {
+                    diags.emit(
+                        Diagnostic::error()
+                            .with_code("manifest/authors/invalid")
+                            .with_message("The `authors` field should only contain strings")
+                            .with_labels(vec![Label::primary(
+                                manifest_file_id,
+                                author.span().unwrap_or_else(|| span_of(authors)),
+                            )]),
+                    );
+                }
+                // TODO: check that the format is correct?
+            }
+            None => diags.emit(
+                Diagnostic::error()
+                    .with_code("manifest/authors/invalid")
+                    .with_message("The `authors` field should be an array of strings")
+                    .with_labels(vec![Label::primary(manifest_file_id, span_of(authors))]),
+            ),
+        },
+        None => diags.emit(
             Diagnostic::error()
-                .with_message("The `authors` field should be an array of strings")
-                .with_labels(vec![Label::primary(manifest_file_id, 0..0)]),
-        );
-        // TODO: check that the format is correct?
+                .with_code("manifest/authors/missing")
+                .with_message("The `authors` field is missing")
+                .with_labels(vec![Label::primary(manifest_file_id, pkg_span.clone())]),
+        ),
     }
 
+    check_redundant_keywords(diags, manifest_file_id, pkg);
+
     Ok(())
 }
 
-async fn check_url(diags: &mut Diagnostics, manifest_file_id: FileId, field: &Item) -> Option<()> {
-    if let Err(e) = reqwest::get(field.as_str()?)
-        .await
-        .and_then(|res| res.error_for_status())
-    {
+/// Keywords are searched separately from the categories, disciplines and
+/// name of the package: repeating them as keywords doesn't help.
+fn check_redundant_keywords(
+    diags: &mut Diagnostics,
+    manifest_file_id: FileId,
+    pkg: &toml_edit::Table,
+) {
+    let strings = |field| {
+        pkg.get(field)
+            .and_then(|item| item.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|value| Some((value.as_str()?, value.span().unwrap_or_default())))
+    };
+    let name = pkg.get("name").and_then(|name| name.as_str());
+
+    for (keyword, span) in strings("keywords") {
+        let same_as = if name.is_some_and(|name| name.eq_ignore_ascii_case(keyword)) {
+            "the name of the package"
+        } else if strings("categories").any(|(c, _)| c.eq_ignore_ascii_case(keyword)) {
+            "one of the categories"
+        } else if strings("disciplines").any(|(d, _)| d.eq_ignore_ascii_case(keyword)) {
+            "one of the disciplines"
+        } else {
+            continue;
+        };
+
         diags.emit(
+            Diagnostic::warning()
+                .with_code("manifest/keywords/redundant")
+                .with_labels(vec![Label::primary(manifest_file_id, span)])
+                .with_message(format!("This keyword is the same as {same_as}."))
+                .with_notes(vec![
+                    "It is already taken into account when searching for packages, \
+                    this keyword is redundant."
+                        .to_owned(),
+                ]),
+        );
+    }
+}
+
+/// Tries to fetch the URL in a field, and returns an error if that fails.
+///
+/// Offline, only the syntax of the URL is checked. Links that don't use
+/// `https://` are invalid in both cases.
+async fn check_url(manifest_file_id: FileId, field: &Item) -> Option<Diagnostic<FileId>> {
+    let url = reqwest::Url::parse(field.as_str()?).ok();
+    if !url.is_some_and(|url| url.scheme() == "https" && url.host().is_some()) {
+        return Some(
             Diagnostic::error()
+                .with_code("manifest/url/invalid")
                 .with_labels(vec![Label::primary(
                     manifest_file_id,
                     field.span().unwrap_or_default(),
                 )])
-                .with_message(format!(
-                    "We could not fetch this URL.\n\nDetails: {:#?}",
-                    e.without_url()
-                )),
-        )
+                .with_message("This is not a valid URL.")
+                .with_notes(vec!["Links should start with `https://`.".to_owned()]),
+        );
+    }
+    if is_offline() {
+        return None;
     }
 
-    Some(())
+    let e = http_client()
+        .get(field.as_str()?)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .err()?;
+
+    Some(
+        Diagnostic::error()
+            .with_code("manifest/url/unreachable")
+            .with_labels(vec![Label::primary(
+                manifest_file_id,
+                field.span().unwrap_or_default(),
+            )])
+            .with_message("We could not fetch this URL.")
+            .with_notes(vec![format!("Details: {:#?}", e.without_url())]),
+    )
 }
 
 async fn check_repo(
@@ -609,18 +912,51 @@ async fn check_repo(
     manifest: &toml_edit::ImDocument<&String>,
 ) -> Option<()> {
     let repo_field = manifest.get("package")?.get("repository")?;
-    check_url(diags, manifest_file_id, repo_field).await;
+    let homepage_field = manifest.get("package")?.get("homepage");
 
-    let homepage_field = manifest.get("package")?.get("homepage")?;
-    check_url(diags, manifest_file_id, homepage_field).await;
+    // Both URLs are fetched at the same time, but the errors are always
+    // reported in the same order.
+    let (repo_error, homepage_error) =
+        tokio::join!(check_url(manifest_file_id, repo_field), async {
+            check_url(manifest_file_id, homepage_field?).await
+        });
+    let repo_is_reachable = repo_error.is_none();
+    diags.emit_many(repo_error.into_iter().chain(homepage_error));
 
-    if repo_field.as_str() == homepage_field.as_str() {
+    if is_offline() {
         diags.emit(
-            Diagnostic::error()
+            Diagnostic::note()
+                .with_code("manifest/repository/offline")
                 .with_labels(vec![Label::primary(
                     manifest_file_id,
-                    homepage_field.span().unwrap_or_default(),
+                    repo_field.span().unwrap_or_default(),
                 )])
+                .with_message(
+                    "The checks are running offline: the links of the manifest were not \
+                    fetched, and the repository was not compared to the package.",
+                ),
+        );
+    } else if repo_is_reachable {
+        let name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str());
+        if let Some(name) = name {
+            verify_repository(diags, manifest_file_id, repo_field, name).await;
+        }
+    }
+
+    let homepage_field = homepage_field?;
+
+    if repo_field.as_str() == homepage_field.as_str() {
+        diags.emit(
+            Diagnostic::error()
+                .with_code("manifest/homepage/same-as-repository")
+                .with_labels(vec![
+                    Label::primary(manifest_file_id, homepage_field.span().unwrap_or_default()),
+                    Label::secondary(manifest_file_id, repo_field.span().unwrap_or_default())
+                        .with_message("same as the repository"),
+                ])
                 .with_message("Use the homepage field only if there is a dedicated website. Otherwise, prefer the `repository` field.".to_owned()),
         )
     }
@@ -628,39 +964,106 @@ async fn check_repo(
     Some(())
 }
 
-fn read_exclude(
-    package_dir: &Path,
-    manifest: &toml_edit::ImDocument<&String>,
-) -> eyre::Result<(Override, Range<usize>)> {
-    let empty_array = toml_edit::Array::new();
-    let exclude = manifest
-        .get("package")
-        .and_then(|package| package.get("exclude"))
-        .and_then(|item| item.as_array())
-        .unwrap_or(&empty_array);
-
-    let mut exclude_globs = OverrideBuilder::new(
-        package_dir
-            .canonicalize()
-            .context("Failed to canonicalize package directory")?,
-    );
-    for exclusion in exclude {
-        let Some(exclusion) = exclusion.as_str() else {
+/// Looks for the manifest of the package in its repository, and warns if it
+/// can't be found there.
+///
+/// Only repositories on GitHub, GitLab and Codeberg are supported, with an
+/// `https://` URL. If any request fails for other reasons than the file not
+/// existing, nothing is reported.
+async fn verify_repository(
+    diags: &mut Diagnostics,
+    manifest_file_id: FileId,
+    repo_field: &Item,
+    name: &str,
+) -> Option<()> {
+    let url = repo_field.as_str()?.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (host, path) = url.strip_prefix("https://")?.split_once('/')?;
+    let mut segments = path.split('/');
+    let (owner, repo) = (segments.next()?, segments.next()?);
+    if segments.next().is_some() {
+        // Probably a link to a subdirectory, we don't know which branch to
+        // look at.
+        return None;
+    }
+
+    let raw_url = |file: &str| match host {
+        "github.com" => Some(format!(
+            "https://raw.githubusercontent.com/{owner}/{repo}/HEAD/{file}"
+        )),
+        "gitlab.com" => Some(format!(
+            "https://gitlab.com/{owner}/{repo}/-/raw/HEAD/{file}"
+        )),
+        "codeberg.org" => Some(format!("https://codeberg.org/{owner}/{repo}/raw/{file}")),
+        _ => None,
+    };
+
+    let candidates = [
+        "typst.toml".to_owned(),
+        format!("{name}/typst.toml"),
+        format!("packages/{name}/typst.toml"),
+    ]
+    .iter()
+    .map(|candidate| raw_url(candidate))
+    .collect::<Option<Vec<_>>>()?;
+    find_manifest(diags, manifest_file_id, repo_field, name, &candidates).await
+}
+
+/// Fetches the manifests that may be at these URLs, and warns if none of them
+/// is the one of the package.
+async fn find_manifest(
+    diags: &mut Diagnostics,
+    manifest_file_id: FileId,
+    repo_field: &Item,
+    name: &str,
+    candidates: &[String],
+) -> Option<()> {
+    let mut found_names = Vec::new();
+    for candidate in candidates {
+        let res = http_client().get(candidate).send().await.ok()?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            continue;
+        }
+        let contents = res.error_for_status().ok()?.text().await.ok()?;
+        let Ok(manifest) = toml_edit::ImDocument::parse(contents) else {
             continue;
         };
-
-        if exclusion.starts_with('!') {
-            warn!("globs with '!' are not supported");
+        let Some(found_name) = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+        else {
             continue;
+        };
+        if found_name == name {
+            return Some(());
         }
-
-        let exclusion = exclusion.trim_start_matches("./");
-        exclude_globs.add(&format!("!{exclusion}")).ok();
+        found_names.push(found_name.to_owned());
     }
-    Ok((
-        exclude_globs.build().context("Invalid exclude globs")?,
-        exclude.span().unwrap_or(0..0),
-    ))
+
+    let message = if found_names.is_empty() {
+        "We could not find the manifest of this package in its repository.".to_owned()
+    } else {
+        format!(
+            "The manifest found in this repository is for another package ({}).",
+            found_names.join(", ")
+        )
+    };
+    diags.emit(
+        Diagnostic::warning()
+            .with_code("manifest/repository/unverified")
+            .with_labels(vec![Label::primary(
+                manifest_file_id,
+                repo_field.span().unwrap_or_default(),
+            )])
+            .with_message(message)
+            .with_notes(vec![
+                "Please double-check that this link points to the source of this package."
+                    .to_owned(),
+            ]),
+    );
+
+    Some(())
 }
 
 fn world_for_template(
@@ -668,6 +1071,8 @@ fn world_for_template(
     package_dir: &Path,
     package_spec: &PackageSpec,
     exclude: Override,
+    store: FileStore,
+    font_paths: &[PathBuf],
 ) -> Option<SystemWorld> {
     let template = manifest.get("template")?.as_table()?;
     let template_path = package_dir.join(template.get("path")?.as_str()?);
@@ -675,30 +1080,35 @@ fn world_for_template(
 
     let mut world = SystemWorld::new(template_main, template_path)
         .ok()?
-        .with_package_override(package_spec, package_dir);
+        .with_package_override(package_spec, package_dir)
+        .with_template_mode()
+        .with_file_store(store)
+        .with_font_paths(font_paths);
     world.exclude(exclude);
     Some(world)
 }
 
 fn dont_exclude_template_files(
     diags: &mut Diagnostics,
-    manifest: &toml_edit::ImDocument<&String>,
     package_dir: &Path,
-    exclude: Override,
-) -> Option<()> {
-    let template_root = template_root(manifest)?;
-    for entry in ignore::Walk::new(package_dir.join(template_root)).flatten() {
+    inventory: &FileInventory,
+) {
+    for entry in inventory.entries().filter(|entry| entry.in_template) {
+        let relative_path = entry.path.as_path();
+
         // For build artifacts, ask the package author to delete them.
-        let ext = entry.path().extension().and_then(|e| e.to_str());
-        if matches!(ext, Some("pdf" | "png" | "svg")) && entry.path().with_extension("typ").exists()
+        let ext = relative_path.extension().and_then(|e| e.to_str());
+        if matches!(ext, Some("pdf" | "png" | "svg"))
+            && package_dir
+                .join(relative_path)
+                .with_extension("typ")
+                .exists()
         {
             diags.emit(
                 Diagnostic::error()
+                    .with_code("template/compiled-document")
                     .with_labels(vec![Label::primary(
-                        FileId::new(
-                            None,
-                            VirtualPath::new(entry.path().strip_prefix(package_dir).ok()?),
-                        ),
+                        FileId::new(None, VirtualPath::new(relative_path)),
                         0..0,
                     )])
                     .with_message(
@@ -711,37 +1121,85 @@ fn dont_exclude_template_files(
         }
 
         // For other files, check that they are indeed not excluded.
-        if exclude
-            .matched(
-                entry.path().canonicalize().ok()?,
-                entry.metadata().ok()?.is_dir(),
-            )
-            .is_ignore()
-        {
+        if entry.excluded {
             diags.emit(
                 Diagnostic::error()
+                    .with_code("exclude/template")
                     .with_message("This file is part of the template and should not be excluded.")
                     .with_labels(vec![Label::primary(
-                        FileId::new(
-                            None,
-                            VirtualPath::new(entry.path().strip_prefix(package_dir).ok()?),
-                        ),
+                        FileId::new(None, VirtualPath::new(relative_path)),
                         0..0,
                     )]),
             )
         }
     }
-
-    Some(())
 }
 
-fn template_root(manifest: &toml_edit::ImDocument<&String>) -> Option<PathBuf> {
-    Some(PathBuf::from(
-        manifest
-            .get("template")
-            .and_then(|t| t.get("path"))?
-            .as_str()?,
-    ))
+/// Checks that the entrypoint of the package is not part of the template, and
+/// that the entrypoint of the template is.
+fn check_entrypoints(
+    diags: &mut Diagnostics,
+    manifest_file_id: FileId,
+    manifest: &toml_edit::ImDocument<&String>,
+    package_dir: &Path,
+) -> Option<()> {
+    let template = manifest.get("template")?;
+    let template_root = package_dir
+        .join(template.get("path")?.as_str()?)
+        .canonicalize()
+        .ok()?;
+
+    let resolve = |field: &Item, dir: &Path| {
+        let path = dir.join(field.as_str()?).canonicalize().ok()?;
+        Some((path, field.span().unwrap_or_default()))
+    };
+
+    if let Some((entrypoint, span)) = manifest
+        .get("package")
+        .and_then(|package| package.get("entrypoint"))
+        .and_then(|field| resolve(field, package_dir))
+    {
+        if entrypoint.starts_with(&template_root) {
+            diags.emit(
+                Diagnostic::error()
+                    .with_code("manifest/entrypoint/in-template")
+                    .with_labels(vec![Label::primary(manifest_file_id, span)])
+                    .with_message(
+                        "The entrypoint of the package should not be in the template directory.",
+                    )
+                    .with_notes(vec![
+                        "The entrypoint is what users get when importing your package, \
+                        and the template directory is what is copied by `typst init`: \
+                        the library should be outside of the template, \
+                        and the template should import it."
+                            .to_owned(),
+                    ]),
+            );
+        }
+    }
+
+    if let Some((template_entrypoint, span)) = template
+        .get("entrypoint")
+        .and_then(|field| resolve(field, &template_root))
+    {
+        if !template_entrypoint.starts_with(&template_root) {
+            diags.emit(
+                Diagnostic::warning()
+                    .with_code("manifest/template/entrypoint-outside")
+                    .with_labels(vec![Label::primary(manifest_file_id, span)])
+                    .with_message(
+                        "The entrypoint of the template should be in the template directory.",
+                    )
+                    .with_notes(vec![
+                        "Only the template directory is copied by `typst init`, \
+                        so this file will be missing in new projects."
+                            .to_owned(),
+                    ]),
+            );
+        }
+    }
+
+    Some(())
 }
 
 fn check_thumbnail(
@@ -750,12 +1208,48 @@ fn check_thumbnail(
     manifest_file_id: FileId,
     package_dir: &Path,
 ) -> Option<PathBuf> {
-    let thumbnail = manifest.get("template")?.as_table()?.get("thumbnail")?;
+    let Some(template) = manifest.get("template") else {
+        if let Some(span) = manifest
+            .get("package")
+            .and_then(|package| package.get("thumbnail"))
+            .and_then(|thumbnail| thumbnail.span())
+        {
+            diags.emit(
+                Diagnostic::warning()
+                    .with_code("manifest/thumbnail/not-template")
+                    .with_labels(vec![Label::primary(manifest_file_id, span)])
+                    .with_message(
+                        "Only templates have a thumbnail, this field has no effect. \
+                        For template packages, it should be in the `[template]` section.",
+                    ),
+            );
+        }
+        return None;
+    };
+
+    let Some(thumbnail) = template.as_table()?.get("thumbnail") else {
+        diags.emit(
+            Diagnostic::error()
+                .with_code("manifest/thumbnail/missing")
+                .with_labels(vec![Label::primary(
+                    manifest_file_id,
+                    template.span().unwrap_or_default(),
+                )])
+                .with_message(
+                    "Template packages must have a thumbnail. \
+                    Add a `thumbnail` field to this section, \
+                    with the path of a PNG or WebP image, \
+                    at least 1080px on its longer edge.",
+                ),
+        );
+        return None;
+    };
     let thumbnail_path = package_dir.join(thumbnail.as_str()?);
 
     if !thumbnail_path.exists() {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/thumbnail/not-found")
                 .with_labels(vec![Label::primary(manifest_file_id, thumbnail.span()?)])
                 .with_message("This file does not exist."),
         )
@@ -767,6 +1261,7 @@ fn check_thumbnail(
     ) {
         diags.emit(
             Diagnostic::error()
+                .with_code("manifest/thumbnail/format")
                 .with_labels(vec![Label::primary(manifest_file_id, thumbnail.span()?)])
                 .with_message("Thumbnails should be PNG or WebP files."),
         )
@@ -774,3 +1269,152 @@ fn check_thumbnail(
 
     Some(thumbnail_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Router};
+
+    use super::*;
+
+    /// Serves the manifests of `greet` and `other`, in directories named
+    /// after them. Returns the URL of the server.
+    async fn serve_manifests() -> String {
+        let app = Router::new()
+            .route(
+                "/greet/typst.toml",
+                get(|| async { "[package]\nname = \"greet\"\n" }),
+            )
+            .route(
+                "/other/typst.toml",
+                get(|| async { "[package]\nname = \"other\"\n" }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    fn manifest(repository: &str) -> toml_edit::ImDocument<String> {
+        toml_edit::ImDocument::parse(format!(
+            "[package]\nname = \"greet\"\nrepository = \"{repository}\"\n"
+        ))
+        .unwrap()
+    }
+
+    /// The warnings about the repository, if the manifests of the package
+    /// are looked for in these directories of the server.
+    async fn find_in(dirs: &[&str]) -> Vec<String> {
+        let url = serve_manifests().await;
+        let candidates = dirs
+            .iter()
+            .map(|dir| format!("{url}/{dir}/typst.toml"))
+            .collect::<Vec<_>>();
+        let manifest = manifest("https://github.com/me/greet");
+        let file_id = FileId::new(None, VirtualPath::new("typst.toml"));
+        let mut diags = Diagnostics::default();
+        find_manifest(
+            &mut diags,
+            file_id,
+            &manifest["package"]["repository"],
+            "greet",
+            &candidates,
+        )
+        .await;
+        diags.warnings().iter().map(|d| d.message.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn repositories_with_the_manifest_are_verified() {
+        assert!(find_in(&["missing", "greet"]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn repositories_of_other_packages_are_reported() {
+        assert_eq!(
+            find_in(&["missing", "other"]).await,
+            ["The manifest found in this repository is for another package (other)."]
+        );
+    }
+
+    #[tokio::test]
+    async fn repositories_without_the_manifest_are_reported() {
+        assert_eq!(
+            find_in(&["missing"]).await,
+            ["We could not find the manifest of this package in its repository."]
+        );
+    }
+
+    /// A WebAssembly module with a large data segment, that is only made of
+    /// zeros and can be optimized away.
+    fn zeroed_module(len: usize) -> Vec<u8> {
+        fn leb128(mut n: usize, out: &mut Vec<u8>) {
+            loop {
+                let byte = (n & 0x7f) as u8;
+                n >>= 7;
+                if n == 0 {
+                    out.push(byte);
+                    return;
+                }
+                out.push(byte | 0x80);
+            }
+        }
+
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        // A memory of 64 pages (4MB).
+        module.extend([0x05, 0x03, 0x01, 0x00, 0x40]);
+        // An active segment at offset 0 of the memory.
+        let mut data = vec![0x01, 0x00, 0x41, 0x00, 0x0b];
+        leb128(len, &mut data);
+        data.resize(data.len() + len, 0);
+        module.push(0x0b);
+        leb128(data.len(), &mut module);
+        module.extend(data);
+        module
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn plugins_are_optimized_off_the_runtime() {
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-large-plugin-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("typst.toml"),
+            "[package]\nname = \"plugin\"\nentrypoint = \"lib.typ\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("plugin.wasm"), zeroed_module(3 * 1024 * 1024)).unwrap();
+
+        let inventory = FileInventory::for_package(&dir).unwrap();
+        let mut diags = Diagnostics::default();
+        let res = exclude_large_files(&mut diags, &dir, &inventory, None).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        res.unwrap();
+        let codes = diags
+            .warnings()
+            .iter()
+            .map(|d| d.code.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [Some("files/wasm-opt")]);
+        assert_eq!(WASM_OPT_PERMITS.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn insecure_links_are_invalid() {
+        let manifest = manifest("http://github.com/me/greet");
+        let repository = &manifest["package"]["repository"];
+        let file_id = FileId::new(None, VirtualPath::new("typst.toml"));
+
+        let error = check_url(file_id, repository).await.unwrap();
+        assert_eq!(error.code.as_deref(), Some("manifest/url/invalid"));
+
+        let mut diags = Diagnostics::default();
+        assert!(verify_repository(&mut diags, file_id, repository, "greet")
+            .await
+            .is_none());
+        assert!(diags.warnings().is_empty());
+    }
+}
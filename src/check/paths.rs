@@ -0,0 +1,113 @@
+use codespan_reporting::diagnostic::Diagnostic;
+use typst::syntax::{
+    ast::{self, AstNode},
+    FileId, SyntaxNode, VirtualPath,
+};
+
+use crate::world::SystemWorld;
+
+use super::{files::FileInventory, label, Diagnostics};
+
+const CODE: &str = "paths/absolute";
+
+/// Functions that take the path of a file as their first argument.
+const FILE_FUNCTIONS: &[&str] = &[
+    "read",
+    "image",
+    "json",
+    "csv",
+    "yaml",
+    "toml",
+    "xml",
+    "cbor",
+    "bibliography",
+    "plugin",
+];
+
+/// Prefixes of paths that are absolute on most systems, but that wouldn't be
+/// used in a package.
+const SYSTEM_PREFIXES: &[&str] = &["/home/", "/Users/", "/tmp/", "/etc/", "/usr/", "/var/"];
+
+/// Checks the absolute paths given to functions that load files.
+///
+/// In Typst, these paths are relative to the root of the package, but they
+/// are often meant as paths on the system of the author.
+pub fn check(diags: &mut Diagnostics, world: &SystemWorld, inventory: &FileInventory) {
+    let sources = inventory
+        .included_files()
+        .filter(|entry| entry.path.extension().is_some_and(|ext| ext == "typ"));
+    for entry in sources {
+        let fid = FileId::new(None, VirtualPath::new(&entry.path));
+        let Ok(source) = world.lookup(fid) else {
+            continue;
+        };
+
+        let mut paths = Vec::new();
+        find_paths(source.root(), &mut paths);
+        for path in paths {
+            let value = path.get();
+            let looks_like_system_path = SYSTEM_PREFIXES
+                .iter()
+                .any(|prefix| value.starts_with(prefix))
+                || is_windows_path(&value);
+            if !value.starts_with('/') && !looks_like_system_path {
+                continue;
+            }
+
+            let diag = Diagnostic::warning()
+                .with_code(CODE)
+                .with_labels(label(world, path.span()).into_iter().collect());
+            if looks_like_system_path {
+                diags.emit(
+                    diag.with_message(
+                        "This seems to be a path on your computer, \
+                        it won't exist for the users of the package.",
+                    )
+                    .with_notes(vec![
+                        "In Typst, paths starting with `/` are relative to the root of the \
+                        package. Files that are needed by the package should be part of it."
+                            .to_owned(),
+                    ]),
+                );
+            } else if !world
+                .root()
+                .join(VirtualPath::new(value.as_str()).as_rootless_path())
+                .exists()
+            {
+                diags.emit(
+                    diag.with_message("This file does not exist in the package.")
+                        .with_notes(vec![
+                            "Paths starting with `/` are relative to the root of the \
+                            package, not of your file system."
+                                .to_owned(),
+                        ]),
+                );
+            }
+        }
+    }
+}
+
+/// Whether a path starts with a Windows drive letter, like `C:\`.
+fn is_windows_path(path: &str) -> bool {
+    let mut chars = path.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.next() == Some(':')
+        && matches!(chars.next(), Some('\\' | '/'))
+}
+
+/// Collects the string literals given as paths to functions that load files.
+fn find_paths<'a>(node: &'a SyntaxNode, paths: &mut Vec<ast::Str<'a>>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        if let ast::Expr::Ident(ident) = call.callee() {
+            if FILE_FUNCTIONS.contains(&ident.as_str()) {
+                if let Some(ast::Arg::Pos(ast::Expr::Str(path))) = call.args().items().next() {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+
+    for child in node.children() {
+        find_paths(child, paths);
+    }
+}
@@ -0,0 +1,95 @@
+use std::ops::Range;
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use typst::syntax::{FileId, LinkedNode, SyntaxKind, VirtualPath};
+
+use crate::world::SystemWorld;
+
+use super::{files::FileInventory, Diagnostics};
+
+/// Text that is generally a placeholder that the author of the template
+/// forgot to replace.
+///
+/// Patterns are matched case-insensitively, as whole words.
+const PLACEHOLDERS: &[&str] = &[
+    "TODO",
+    "FIXME",
+    "your name here",
+    "lorem ipsum",
+    "replace me",
+];
+
+/// Maximum number of placeholders reported for each file.
+const MAX_PER_FILE: usize = 3;
+
+/// Looks for placeholders in the Typst files of the template.
+///
+/// Comments are not checked, as they won't appear in documents created from
+/// the template.
+pub fn check(diags: &mut Diagnostics, world: &SystemWorld, inventory: &FileInventory) {
+    let template_sources = inventory.included_files().filter(|entry| {
+        entry.in_template && entry.path.extension().is_some_and(|ext| ext == "typ")
+    });
+    for entry in template_sources {
+        let fid = FileId::new(None, VirtualPath::new(&entry.path));
+        let Ok(source) = world.lookup(fid) else {
+            continue;
+        };
+
+        let mut comments = Vec::new();
+        find_comments(&LinkedNode::new(source.root()), &mut comments);
+
+        let text = source.text().to_ascii_lowercase();
+        let mut found = Vec::new();
+        for placeholder in PLACEHOLDERS {
+            let pattern = placeholder.to_ascii_lowercase();
+            for (start, _) in text.match_indices(&pattern) {
+                let end = start + pattern.len();
+                let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+                if is_word_char(text[..start].chars().next_back())
+                    || is_word_char(text[end..].chars().next())
+                {
+                    // Part of another word.
+                    continue;
+                }
+                if comments.iter().any(|comment| comment.contains(&start)) {
+                    continue;
+                }
+                found.push((placeholder, start..end));
+            }
+        }
+        found.sort_by_key(|(_, range)| range.start);
+
+        let extra = found.len().saturating_sub(MAX_PER_FILE);
+        for (i, (placeholder, range)) in found.into_iter().take(MAX_PER_FILE).enumerate() {
+            let mut diag = Diagnostic::warning()
+                .with_code("template/placeholder")
+                .with_labels(vec![Label::primary(fid, range)])
+                .with_message(format!(
+                    "This seems to be a placeholder (\"{placeholder}\"). \
+                    It will be part of every document created from this template."
+                ));
+            if extra > 0 && i == MAX_PER_FILE - 1 {
+                diag = diag.with_notes(vec![format!(
+                    "There are {extra} other placeholders in this file."
+                )]);
+            }
+            diags.emit(diag);
+        }
+    }
+}
+
+/// Lists the ranges of all the comments in a syntax tree.
+fn find_comments(node: &LinkedNode, comments: &mut Vec<Range<usize>>) {
+    if matches!(
+        node.kind(),
+        SyntaxKind::LineComment | SyntaxKind::BlockComment
+    ) {
+        comments.push(node.range());
+        return;
+    }
+
+    for child in node.children() {
+        find_comments(&child, comments);
+    }
+}
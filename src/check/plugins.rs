@@ -0,0 +1,137 @@
+use std::{collections::HashSet, ops::Range};
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use ignore::overrides::Override;
+use typst::syntax::{
+    ast::{self, AstNode},
+    FileId, SyntaxNode, VirtualPath,
+};
+
+use crate::world::SystemWorld;
+
+use super::{files::FileInventory, label, Diagnostics};
+
+/// Checks that the files loaded with `plugin` are part of the package, and
+/// that all the WASM files of the package are loaded somewhere.
+pub fn check(
+    diags: &mut Diagnostics,
+    world: &SystemWorld,
+    inventory: &FileInventory,
+    exclude: &Override,
+    exclude_span: Range<usize>,
+) {
+    let manifest_file_id = FileId::new(None, VirtualPath::new("typst.toml"));
+    let mut used = HashSet::new();
+    // If a plugin is loaded from a path that is not a string literal, we
+    // can't tell which files are actually used.
+    let mut all_known = true;
+
+    let sources = inventory
+        .included_files()
+        .filter(|entry| entry.path.extension().is_some_and(|ext| ext == "typ"));
+    for entry in sources {
+        let fid = FileId::new(None, VirtualPath::new(&entry.path));
+        let Ok(source) = world.lookup(fid) else {
+            continue;
+        };
+
+        let mut calls = Vec::new();
+        find_plugin_calls(source.root(), &mut calls);
+        for call in calls {
+            let Some(ast::Arg::Pos(ast::Expr::Str(path))) = call.args().items().next() else {
+                all_known = false;
+                continue;
+            };
+
+            let vpath = fid.vpath().join(path.get().as_str());
+            let relative = vpath.as_rootless_path().to_owned();
+            used.insert(relative.clone());
+
+            let error = |code: &str, message: String| {
+                Diagnostic::error()
+                    .with_code(code)
+                    .with_labels(label(world, path.span()).into_iter().collect())
+                    .with_message(message)
+            };
+
+            let excluded = relative
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .any(|p| exclude.matched(p, p != relative).is_ignore());
+            if excluded {
+                let mut diag = error(
+                    "plugins/excluded",
+                    "This plugin is excluded from the package, \
+                    it will not be available to users."
+                        .to_owned(),
+                );
+                diag.labels.push(
+                    Label::secondary(manifest_file_id, exclude_span.clone())
+                        .with_message("excluded here"),
+                );
+                diags.emit(diag);
+            } else if inventory.get(&relative).is_none() {
+                let other_case = inventory.entries().find(|entry| {
+                    entry
+                        .path
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(&relative.to_string_lossy())
+                });
+                if let Some(other_case) = other_case {
+                    diags.emit(error(
+                        "plugins/case",
+                        format!(
+                            "This plugin is named {} in the package. \
+                        File names are case-sensitive on some systems.",
+                            other_case.path.display()
+                        ),
+                    ));
+                } else if !world.root().join(&relative).is_file() {
+                    diags.emit(error(
+                        "plugins/missing",
+                        "This plugin does not exist.".to_owned(),
+                    ));
+                }
+            }
+        }
+    }
+
+    if !all_known {
+        return;
+    }
+
+    let wasm_files = inventory
+        .included_files()
+        .filter(|entry| entry.path.extension().is_some_and(|ext| ext == "wasm"));
+    for entry in wasm_files {
+        if used.contains(&entry.path) {
+            continue;
+        }
+
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("plugins/unused")
+                .with_labels(vec![Label::primary(
+                    FileId::new(None, VirtualPath::new(&entry.path)),
+                    0..0,
+                )])
+                .with_message(
+                    "This WebAssembly file is never loaded with `plugin`. \
+                    If it is not needed, it should be removed or excluded.",
+                ),
+        );
+    }
+}
+
+/// Collects all the calls to `plugin` in a syntax tree.
+fn find_plugin_calls<'a>(node: &'a SyntaxNode, calls: &mut Vec<ast::FuncCall<'a>>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        if matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "plugin") {
+            calls.push(call);
+        }
+    }
+
+    for child in node.children() {
+        find_plugin_calls(child, calls);
+    }
+}
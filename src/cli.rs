@@ -1,35 +1,1473 @@
-use std::path::Path;
+use std::{
+    collections::VecDeque,
+    io::{IsTerminal, Write},
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
-use codespan_reporting::{diagnostic::Diagnostic, term};
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Severity},
+    files::Files,
+    term,
+};
+use eyre::Context;
 use ignore::overrides::Override;
 use tracing::error;
-use typst::syntax::{package::PackageSpec, FileId, Source};
+use typst::{
+    syntax::{
+        package::{PackageSpec, PackageVersion, VersionlessPackageSpec},
+        FileId, Source,
+    },
+    World,
+};
 
-use crate::{check::all_checks, package::PackageExt, world::SystemWorld};
+use crate::{
+    check::{
+        all_checks,
+        archive::{self, ExtractedArchive},
+        explanations, fatal_error,
+        files::{
+            binary_kind, format_size, is_binary, read_exclude_patterns, template_root,
+            ExcludePattern, FileInventory,
+        },
+        imports, jobs, Diagnostics, Timings, TYPST_VERSION,
+    },
+    github::git,
+    package::{latest_version_in, PackageExt, VersionlessPackageExt},
+    world::SystemWorld,
+};
 
-pub async fn main(package_spec: String) {
-    let package_spec: Option<PackageSpec> = package_spec.parse().ok();
-    let package_dir = if let Some(ref package_spec) = package_spec {
-        package_spec.directory()
+mod fix;
+mod json;
+mod junit;
+mod report;
+
+/// Options of the check command, for all the packages it checks.
+#[derive(Default, Clone)]
+pub struct CheckOptions {
+    /// Show how long each phase of the checks took.
+    pub timings: bool,
+    /// Print diagnostics as JSON, one per line, instead of human-readable
+    /// text.
+    pub json: bool,
+    /// Produce a JUnit XML report, printed instead of the text output unless
+    /// it is written to `report_file`.
+    pub junit: bool,
+    /// Where to write the report: the JUnit report with `junit`, a JSON
+    /// summary of the run otherwise.
+    pub report_file: Option<PathBuf>,
+    /// Exit successfully when only warnings were found.
+    pub ignore_warnings: bool,
+    /// Fail like when there are errors if more warnings than that were
+    /// found.
+    pub max_warnings: Option<usize>,
+    /// Apply the fixes of the diagnostics before reporting them.
+    pub fix: bool,
+    /// Add the explanation of their code to the diagnostics.
+    pub verbose_diagnostics: bool,
+    /// Stop the checks of a package at the first error.
+    pub fail_fast: bool,
+    /// Whether the text output uses colors.
+    pub color: Color,
+    /// New severities for diagnostic codes, `None` meaning that they should
+    /// not be reported. Later entries take precedence.
+    pub severities: Vec<(String, Option<Severity>)>,
+    /// How many packages are checked at the same time, when there are
+    /// several of them. Defaults to the `JOBS` environment variable.
+    pub jobs: Option<usize>,
+    /// Only report the diagnostics about files that changed since this Git
+    /// reference.
+    pub changed_since: Option<String>,
+    /// Directories with additional fonts for the compilation of the package
+    /// and of its template.
+    pub font_paths: Vec<PathBuf>,
+}
+
+/// When to use colors in the text output.
+#[derive(Default, Clone, Copy)]
+pub enum Color {
+    /// Only if the output is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    fn choice(self) -> term::termcolor::ColorChoice {
+        use term::termcolor::ColorChoice;
+
+        match self {
+            Color::Always => ColorChoice::Always,
+            Color::Never => ColorChoice::Never,
+            Color::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                if no_color || !std::io::stdout().is_terminal() {
+                    ColorChoice::Never
+                } else {
+                    // Still lets `termcolor` look at `TERM`.
+                    ColorChoice::Auto
+                }
+            }
+        }
+    }
+}
+
+impl CheckOptions {
+    /// Whether the results are printed as text, for humans.
+    fn human_output(&self) -> bool {
+        !self.json && !(self.junit && self.report_file.is_none())
+    }
+}
+
+/// Exit code used when errors were found in the package, or more warnings
+/// than allowed with `--max-warnings`.
+const EXIT_ERRORS: u8 = 1;
+/// Exit code used when only warnings were found in the package.
+const EXIT_WARNINGS: u8 = 2;
+/// Exit code used when the checks could not run until the end.
+const EXIT_FATAL: u8 = 3;
+
+/// Checks a single package.
+///
+/// With `as_spec`, `package` is the directory of the package, that is checked
+/// as if it was submitted with this specification.
+///
+/// `version` selects the version of a package given without one, instead of
+/// the latest.
+pub async fn main(
+    package: String,
+    options: CheckOptions,
+    as_spec: Option<PackageSpec>,
+    version: Option<PackageVersion>,
+) -> ExitCode {
+    let package = match version {
+        Some(version)
+            if package.parse::<PackageSpec>().is_err()
+                && package.parse::<VersionlessPackageSpec>().is_ok() =>
+        {
+            format!("{package}:{version}")
+        }
+        Some(_) => {
+            println!(
+                "error: --version can only be used with a package specification \
+                without a version, like @preview/name."
+            );
+            return ExitCode::from(EXIT_FATAL);
+        }
+        None => package,
+    };
+    if archive::is_archive(Path::new(&package)) {
+        return check_archive(Path::new(&package), options, as_spec).await;
+    }
+
+    let resolved = match as_spec {
+        Some(spec) if package.is_empty() => Ok((Some(spec), PathBuf::from("."), false)),
+        Some(spec) => Ok((Some(spec), PathBuf::from(package), false)),
+        None => resolve_package(package),
+    };
+    let (package_spec, package_dir, inferred) = match resolved {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            println!("error: {message}");
+            return ExitCode::from(EXIT_FATAL);
+        }
+    };
+
+    ExitCode::from(
+        check(package_spec, package_dir, inferred, &options)
+            .await
+            .exit_code(&options),
+    )
+}
+
+/// Checks the package in an archive, like the ones created by the `bundle`
+/// command.
+///
+/// The archive is extracted to a temporary directory, so the paths in the
+/// diagnostics are relative to its root.
+async fn check_archive(
+    path: &Path,
+    options: CheckOptions,
+    as_spec: Option<PackageSpec>,
+) -> ExitCode {
+    if options.fix {
+        println!("error: --fix can't be used with an archive.");
+        return ExitCode::from(EXIT_FATAL);
+    }
+    let extracted = match ExtractedArchive::extract(path) {
+        Ok(extracted) => extracted,
+        Err(e) => {
+            println!("error: could not extract {} ({e:#})", path.display());
+            return ExitCode::from(EXIT_FATAL);
+        }
+    };
+
+    let summary = check(as_spec, extracted.package_dir(), false, &options).await;
+    ExitCode::from(summary.exit_code(&options))
+}
+
+/// Checks several packages, given on the command line or listed in a file.
+///
+/// `specs_from` is the path of a file with one package specification or
+/// directory per line, or `-` to read them from the standard input. Empty
+/// lines and comments starting with `#` are ignored.
+pub async fn check_list(
+    args: Vec<String>,
+    specs_from: Option<String>,
+    options: CheckOptions,
+) -> ExitCode {
+    let mut entries: Vec<(String, String)> = args
+        .into_iter()
+        .map(|arg| ("command line".to_owned(), arg))
+        .collect();
+
+    if let Some(specs_from) = specs_from {
+        let contents = if specs_from == "-" {
+            std::io::read_to_string(std::io::stdin())
+        } else {
+            std::fs::read_to_string(&specs_from)
+        };
+        let contents = match contents {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("error: could not read {specs_from} ({e})");
+                return ExitCode::from(EXIT_FATAL);
+            }
+        };
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if !line.is_empty() {
+                entries.push((format!("{specs_from}:{}", i + 1), line.to_owned()));
+            }
+        }
+    }
+
+    // Everything is validated before starting, to not fail after a long run.
+    let mut packages: Vec<(Option<PackageSpec>, PathBuf)> = Vec::new();
+    for (origin, entry) in entries {
+        let resolved = match entry.parse::<PackageSpec>() {
+            Ok(spec) => package_dir_for(&spec).map(|dir| (Some(spec), dir)),
+            Err(_) if Path::new(&entry).join("typst.toml").exists() => {
+                Ok((spec_from_path(Path::new(&entry)), PathBuf::from(&entry)))
+            }
+            Err(_) if entry.parse::<VersionlessPackageSpec>().is_ok() => {
+                latest_spec(&entry.parse().unwrap())
+                    .and_then(|spec| package_dir_for(&spec).map(|dir| (Some(spec), dir)))
+            }
+            Err(_) => Err(format!(
+                "`{entry}` is neither a package specification nor the directory of a package."
+            )),
+        };
+        match resolved {
+            Ok(package) => {
+                if !packages.iter().any(|(_, dir)| *dir == package.1) {
+                    packages.push(package);
+                }
+            }
+            Err(message) => {
+                println!("error: {origin}: {message}");
+                return ExitCode::from(EXIT_FATAL);
+            }
+        }
+    }
+
+    let count = packages.len();
+    let (total, _) = check_many(packages, &options).await;
+
+    if count > 1 && options.human_output() {
+        println!("\n## Total\n");
+        total.print(None);
+    }
+
+    ExitCode::from(total.exit_code(&options))
+}
+
+/// Finds the package to check from the argument of the command line.
+///
+/// Returns its specification (if known), its directory, and whether the
+/// specification was inferred from the path of the directory.
+///
+/// Specifications without a version, like `@preview/name`, are resolved to
+/// the latest version that is found.
+fn resolve_package(arg: String) -> Result<(Option<PackageSpec>, PathBuf, bool), String> {
+    let versionless = arg.parse::<VersionlessPackageSpec>();
+    Ok(match arg.parse::<PackageSpec>() {
+        Ok(spec) => {
+            let dir = package_dir_for(&spec)?;
+            (Some(spec), dir, false)
+        }
+        Err(_) if versionless.is_ok() => {
+            let spec = latest_spec(&versionless.unwrap())?;
+            let dir = package_dir_for(&spec)?;
+            (Some(spec), dir, false)
+        }
+        Err(_) => {
+            let start = if arg.is_empty() { "." } else { &arg };
+            let dir = find_package_root(Path::new(start))?;
+            match spec_from_path(&dir) {
+                Some(spec) => (Some(spec), dir, true),
+                None => (None, dir, false),
+            }
+        }
+    })
+}
+
+/// Finds the root of the package containing `start`.
+///
+/// If `start` has no manifest, its parents are looked at, up to the root of
+/// the Git repository. The manifest of a template is ignored in favor of the
+/// one of the package it belongs to.
+fn find_package_root(start: &Path) -> Result<PathBuf, String> {
+    if start.join("typst.toml").exists() {
+        return Ok(start.to_owned());
+    }
+
+    let start = start
+        .canonicalize()
+        .map_err(|e| format!("{} can't be opened ({e})", start.display()))?;
+    let mut candidates = Vec::new();
+    for dir in start.ancestors() {
+        if dir.join("typst.toml").exists() {
+            candidates.push(dir.to_owned());
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    // Remove the templates of the packages that were found.
+    let templates: Vec<PathBuf> = candidates
+        .iter()
+        .filter_map(|dir| {
+            let manifest = std::fs::read_to_string(dir.join("typst.toml")).ok()?;
+            let manifest = toml_edit::ImDocument::parse(&manifest).ok()?;
+            let template = template_root(&manifest)?;
+            dir.join(template).canonicalize().ok()
+        })
+        .collect();
+    candidates.retain(|dir| !templates.contains(dir));
+
+    match candidates.as_slice() {
+        // Let the checks report the missing manifest.
+        [] => Ok(start),
+        [root] => {
+            eprintln!(
+                "note: using the manifest of the package in {}",
+                root.display()
+            );
+            Ok(root.clone())
+        }
+        [..] => Err(format!(
+            "There are several packages containing {}: {}. \
+            Please give the path of the one to use.",
+            start.display(),
+            candidates
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Creates the archive of a package, with the files that would be published.
+///
+/// Packages with errors are not bundled, unless `force` is set.
+pub async fn bundle(package: String, output: PathBuf, force: bool) -> ExitCode {
+    let (package_spec, package_dir, _) = match resolve_package(package) {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            println!("error: {message}");
+            return ExitCode::from(EXIT_FATAL);
+        }
+    };
+
+    if !force {
+        match all_checks(
+            package_spec.as_ref(),
+            package_dir.clone(),
+            false,
+            &[],
+            false,
+            jobs(),
+        )
+        .await
+        {
+            Ok((_, diags)) if diags.errors().is_empty() => {}
+            Ok(_) => {
+                println!(
+                    "error: this package has errors, run the check command to see them. \
+                    Use --force to bundle it anyway."
+                );
+                return ExitCode::from(EXIT_ERRORS);
+            }
+            Err(e) => {
+                println!("error: {}", fatal_error(&e).message);
+                return ExitCode::from(EXIT_FATAL);
+            }
+        }
+    }
+
+    match write_bundle(&package_dir, &output) {
+        Ok(files) => {
+            let mut total = 0;
+            for (path, size) in files {
+                println!("{:>10}  {}", size, path.display());
+                total += size;
+            }
+            println!("{:>10}  total, written to {}", total, output.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("error: {}", fatal_error(&e).message);
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+/// Files that are always part of a package, even if they are excluded.
+const ALWAYS_INCLUDED: &[&str] = &["typst.toml", "README.md", "LICENSE"];
+
+/// Writes a reproducible archive of a package: files are sorted, and their
+/// metadata doesn't depend on the system.
+///
+/// Returns the bundled files with their sizes.
+fn write_bundle(package_dir: &Path, output: &Path) -> eyre::Result<Vec<(PathBuf, u64)>> {
+    let inventory = FileInventory::for_package(package_dir)?;
+    let thumbnail = thumbnail_path(package_dir);
+
+    let mut files: Vec<_> = inventory
+        .entries()
+        .filter(|entry| {
+            entry.metadata.is_file()
+                && (!entry.excluded
+                    || ALWAYS_INCLUDED.contains(&entry.path.to_string_lossy().as_ref()))
+                // The thumbnail is published separately.
+                && Some(&entry.path) != thumbnail.as_ref()
+        })
+        .map(|entry| (entry.path.clone(), entry.metadata.len()))
+        .collect();
+    files.sort();
+
+    let archive = std::fs::File::create(output).context("Failed to create the archive")?;
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        archive,
+        flate2::Compression::default(),
+    ));
+    for (path, size) in &files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(*size);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        let file = std::fs::File::open(package_dir.join(path))
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        tar.append_data(&mut header, path, file)
+            .context("Failed to write the archive")?;
+    }
+    tar.into_inner()?.finish()?;
+
+    Ok(files)
+}
+
+/// Lists the files of a package, telling which `exclude` glob of the manifest
+/// matches each of them, and which globs match nothing.
+pub async fn show_excluded(package: String, json: bool) -> ExitCode {
+    let package_dir = match resolve_package(package) {
+        Ok((_, package_dir, _)) => package_dir,
+        Err(message) => {
+            println!("error: {message}");
+            return ExitCode::from(EXIT_FATAL);
+        }
+    };
+
+    match explain_excludes(&package_dir, json) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            println!("error: {}", fatal_error(&e).message);
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+fn explain_excludes(package_dir: &Path, json: bool) -> eyre::Result<()> {
+    let manifest_contents = std::fs::read_to_string(package_dir.join("typst.toml"))
+        .context("Failed to read manifest contents.")?;
+    let manifest = toml_edit::ImDocument::parse(&manifest_contents)
+        .context("Failed to parse manifest contents")?;
+    let patterns = read_exclude_patterns(package_dir, &manifest)?;
+    let inventory = FileInventory::for_package(package_dir)?;
+
+    let mut used = vec![false; patterns.len()];
+    let mut files = Vec::new();
+    for entry in inventory.entries() {
+        let is_dir = entry.metadata.is_dir();
+        // Directories are only interesting when they are excluded: their
+        // contents are then not listed.
+        if is_dir && !entry.excluded {
+            continue;
+        }
+
+        let matching: Vec<_> = patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, pattern)| pattern.matches(&entry.path, is_dir))
+            .map(|(i, pattern)| {
+                used[i] = true;
+                pattern
+            })
+            .collect();
+        files.push((entry, matching));
+    }
+    let unused: Vec<_> = patterns
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(pattern, _)| pattern)
+        .collect();
+
+    if json {
+        let pattern_json =
+            |p: &ExcludePattern| serde_json::json!({ "glob": p.glob, "line": p.line });
+        let output = serde_json::json!({
+            "files": files.iter().map(|(entry, matching)| serde_json::json!({
+                "path": entry.path,
+                "directory": entry.metadata.is_dir(),
+                "excluded": entry.excluded,
+                "matched_by": matching.iter().map(|p| pattern_json(p)).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "unused_globs": unused.iter().map(|p| pattern_json(p)).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for (entry, matching) in &files {
+        let path = if entry.metadata.is_dir() {
+            format!("{}/", entry.path.display())
+        } else {
+            entry.path.display().to_string()
+        };
+        if entry.excluded {
+            let globs = matching
+                .iter()
+                .map(|p| format!("{} (line {})", p.glob, p.line))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("excluded  {path}  by {globs}");
+        } else {
+            println!("included  {path}");
+        }
+    }
+
+    if !unused.is_empty() {
+        println!();
+        println!("These globs don't match any file:");
+        for pattern in unused {
+            println!("  {} (line {})", pattern.glob, pattern.line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the packages a package depends on, and whether they are outdated.
+pub async fn deps(package: String, json: bool) -> ExitCode {
+    let (package_spec, package_dir, _) = match resolve_package(package) {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            println!("error: {message}");
+            return ExitCode::from(EXIT_FATAL);
+        }
+    };
+
+    match list_dependencies(package_spec.as_ref(), &package_dir, json) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            println!("error: {}", fatal_error(&e).message);
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+fn list_dependencies(
+    package_spec: Option<&PackageSpec>,
+    package_dir: &Path,
+    json: bool,
+) -> eyre::Result<()> {
+    let manifest = std::fs::read_to_string(package_dir.join("typst.toml"))
+        .context("Failed to read manifest contents.")?;
+    let manifest =
+        toml_edit::ImDocument::parse(&manifest).context("Failed to parse manifest contents")?;
+    let entrypoint = manifest
+        .get("package")
+        .and_then(|package| package.get("entrypoint"))
+        .and_then(|entrypoint| entrypoint.as_str())
+        .context("Packages must specify an `entrypoint` in their manifest")?;
+    let mut world = SystemWorld::new(package_dir.join(entrypoint), package_dir.to_owned())
+        .map_err(|e| eyre::Report::msg(e).wrap_err("Failed to initialize the Typst compiler"))?;
+    if let Some(spec) = package_spec {
+        world = world.with_package_override(spec, package_dir);
+    }
+
+    // Imports are found in the sources, and completed with the packages that
+    // are actually used by the compiler, for dependencies of dependencies.
+    let direct = imports::package_imports(package_dir, &world)?;
+    let _ = typst::compile(&world);
+    let mut all = world.packages_used();
+    all.extend(direct.iter().cloned());
+    all.sort_by_key(|spec| spec.to_string());
+    all.dedup();
+    all.retain(|dep| {
+        !package_spec.is_some_and(|spec| dep.namespace == spec.namespace && dep.name == spec.name)
+    });
+
+    let rows: Vec<_> = all
+        .iter()
+        .map(|dep| {
+            let latest = dep.versionless().latest_version();
+            let outdated = latest.is_some_and(|latest| latest > dep.version);
+            (dep, direct.contains(dep), latest, outdated)
+        })
+        .collect();
+
+    if json {
+        let output = rows
+            .iter()
+            .map(|(dep, direct, latest, outdated)| {
+                serde_json::json!({
+                    "namespace": dep.namespace.as_str(),
+                    "name": dep.name.as_str(),
+                    "version": dep.version.to_string(),
+                    "direct": direct,
+                    "latest": latest.map(|latest| latest.to_string()),
+                    "outdated": outdated,
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!("This package has no dependencies.");
+        return Ok(());
+    }
+    println!(
+        "{:<40} {:<10} {:<10} {:<10}",
+        "Package", "Version", "Latest", "Imported"
+    );
+    for (dep, direct, latest, outdated) in rows {
+        let name = format!("@{}/{}", dep.namespace, dep.name);
+        let latest = match latest {
+            Some(latest) if outdated => format!("{latest} (outdated)"),
+            Some(latest) => latest.to_string(),
+            None => "unknown".to_owned(),
+        };
+        let imported = if direct { "directly" } else { "indirectly" };
+        println!(
+            "{:<40} {:<10} {:<10} {:<10}",
+            name, dep.version, latest, imported
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the long explanation of a diagnostic code.
+///
+/// Without a code, all the documented codes are listed.
+pub fn explain(code: Option<String>) -> ExitCode {
+    let Some(code) = code else {
+        for explanation in explanations::EXPLANATIONS {
+            println!("{:<40} {}", explanation.code, explanation.summary);
+        }
+        return ExitCode::SUCCESS;
+    };
+
+    match explanations::find(&code) {
+        Some(explanation) => {
+            println!("# {}: {}", explanation.code, explanation.summary);
+            println!();
+            print!("{}", explanation.description);
+            println!();
+            if let Some(url) = explanation.doc_url {
+                println!("More info: {url}");
+                println!();
+            }
+            println!(
+                "To ignore it, add `{code}` to the `disabled` list of `typst-check.toml`, \
+                or pass `--allow {code}` to `check`."
+            );
+            ExitCode::SUCCESS
+        }
+        None => {
+            println!("error: no explanation for `{code}`.");
+            let close_matches = explanations::close_matches(&code);
+            if !close_matches.is_empty() {
+                println!("Did you mean one of these codes?");
+                for close_match in close_matches {
+                    println!("  {close_match}");
+                }
+            }
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+/// Lists the diagnostic codes that the checks can report, with their default
+/// severity.
+pub fn list_checks(json: bool) -> ExitCode {
+    let severity_name = |severity| match severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    if json {
+        let output: Vec<_> = explanations::EXPLANATIONS
+            .iter()
+            .map(|explanation| {
+                serde_json::json!({
+                    "code": explanation.code,
+                    "severity": severity_name(explanation.severity),
+                    "summary": explanation.summary,
+                    "doc_url": explanation.doc_url,
+                })
+            })
+            .collect();
+        match serde_json::to_string_pretty(&output) {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                println!("error: {e}");
+                return ExitCode::from(EXIT_FATAL);
+            }
+        }
     } else {
-        Path::new(".").to_owned()
+        for explanation in explanations::EXPLANATIONS {
+            println!(
+                "{:<40} {:<8} {}",
+                explanation.code,
+                severity_name(explanation.severity),
+                explanation.summary
+            );
+        }
+        println!();
+        println!("Run `explain CODE` for details about one of them.");
+    }
+    ExitCode::SUCCESS
+}
+
+/// Prints the version of Typst used by the checks.
+pub fn typst_version(json: bool) -> ExitCode {
+    if json {
+        let output = serde_json::json!({
+            "typst": TYPST_VERSION,
+            "package-check": env!("CARGO_PKG_VERSION"),
+        });
+        println!("{output}");
+    } else {
+        println!("{TYPST_VERSION}");
+    }
+    ExitCode::SUCCESS
+}
+
+/// The path of the thumbnail of a template, relative to the package root.
+fn thumbnail_path(package_dir: &Path) -> Option<PathBuf> {
+    let manifest = std::fs::read_to_string(package_dir.join("typst.toml")).ok()?;
+    let manifest = toml_edit::ImDocument::parse(manifest).ok()?;
+    let thumbnail = manifest.get("template")?.get("thumbnail")?.as_str()?;
+    Some(PathBuf::from(thumbnail.trim_start_matches("./")))
+}
+
+/// Checks the packages that were changed in the local clone of
+/// `typst/packages`, compared to `base` (the main branch by default).
+///
+/// Uncommitted and untracked files are taken into account too.
+pub async fn check_changed(base: Option<String>, options: CheckOptions) -> ExitCode {
+    let repo_dir = git::repo_dir();
+    let repo = git::GitRepo::open(&repo_dir);
+    let base = base.as_deref().unwrap_or("main");
+    let files = match repo.files_changed_since(base).await {
+        Ok(files) => files,
+        Err(e) => {
+            println!("error: could not list the changed files ({e})");
+            return ExitCode::from(EXIT_FATAL);
+        }
     };
 
-    match all_checks(package_spec.as_ref(), package_dir, true).await {
-        Ok((mut world, diags)) => {
-            if let Err(err) = print_diagnostics(&mut world, diags.errors(), diags.warnings()) {
-                error!("failed to print diagnostics ({err})")
+    let mut packages: Vec<(PackageSpec, bool)> = Vec::new();
+    for file in files {
+        let Some(spec) = git::package_of(&file.path) else {
+            continue;
+        };
+        match packages.iter_mut().find(|(p, _)| *p == spec) {
+            Some((_, deleted)) => *deleted &= file.deleted,
+            None => packages.push((spec, file.deleted)),
+        }
+    }
+    packages.sort_by_key(|(spec, _)| spec.to_string());
+
+    if packages.is_empty() {
+        println!("No package was changed since {base}.");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut to_check = Vec::new();
+    for (spec, deleted) in packages {
+        if deleted {
+            if options.human_output() {
+                println!("\n## {spec}\n\nThis version was deleted.");
             }
+            continue;
         }
-        Err(e) => println!("Fatal error: {}", e),
+        let package_dir = spec.directory();
+        to_check.push((Some(spec), package_dir));
     }
+
+    let checked = to_check.len();
+    let (total, _) = check_many(to_check, &options).await;
+
+    if checked > 1 && options.human_output() {
+        println!("\n## Total\n");
+        total.print(None);
+    }
+
+    ExitCode::from(total.exit_code(&options))
+}
+
+/// Checks all the packages of the local clone of `typst/packages`.
+///
+/// They can be restricted to a namespace, and to the packages that changed
+/// since `since`. A table with the results of each package is printed at the
+/// end, or a JSON object per package with `--json`.
+pub async fn check_all(
+    namespace: Option<String>,
+    since: Option<String>,
+    options: CheckOptions,
+) -> ExitCode {
+    let changed = match &since {
+        Some(base) => {
+            let repo_dir = git::repo_dir();
+            match git::GitRepo::open(&repo_dir)
+                .files_changed_since(base)
+                .await
+            {
+                Ok(files) => Some(
+                    files
+                        .iter()
+                        .filter_map(|file| git::package_of(&file.path))
+                        .collect::<Vec<_>>(),
+                ),
+                Err(e) => {
+                    println!("error: could not list the changed files ({e})");
+                    return ExitCode::from(EXIT_FATAL);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let in_dir = |path: &Path| std::fs::read_dir(path).into_iter().flatten().flatten();
+    let mut packages = Vec::new();
+    for namespace_dir in in_dir(&crate::package::dir()) {
+        let Some(ns) = namespace_dir.file_name().to_str().map(str::to_owned) else {
+            continue;
+        };
+        if namespace.as_ref().is_some_and(|namespace| *namespace != ns) {
+            continue;
+        }
+        for name_dir in in_dir(&namespace_dir.path()) {
+            for version_dir in in_dir(&name_dir.path()) {
+                let (Some(name), Some(version)) = (
+                    name_dir.file_name().to_str(),
+                    version_dir
+                        .file_name()
+                        .to_str()
+                        .and_then(|version| version.parse().ok()),
+                ) else {
+                    continue;
+                };
+                if !version_dir.path().join("typst.toml").exists() {
+                    continue;
+                }
+                let spec = PackageSpec {
+                    namespace: ns.as_str().into(),
+                    name: name.into(),
+                    version,
+                };
+                if changed
+                    .as_ref()
+                    .is_some_and(|changed| !changed.contains(&spec))
+                {
+                    continue;
+                }
+                packages.push(spec);
+            }
+        }
+    }
+    packages.sort_by_key(|spec| spec.to_string());
+
+    if packages.is_empty() {
+        println!(
+            "No package was found in {}.",
+            crate::package::dir().display()
+        );
+        return ExitCode::SUCCESS;
+    }
+
+    let to_check = packages
+        .iter()
+        .map(|spec| (Some(spec.clone()), spec.directory()))
+        .collect();
+    let (total, summaries) = check_many(to_check, &options).await;
+
+    if options.json {
+        for (spec, summary) in packages.iter().zip(&summaries) {
+            let output = serde_json::json!({
+                "package": spec.to_string(),
+                "errors": summary.errors,
+                "warnings": summary.warnings,
+                "fatal": summary.fatal,
+            });
+            println!("{output}");
+        }
+    } else if options.human_output() {
+        println!("\n## Total\n");
+        let width = packages
+            .iter()
+            .map(|spec| spec.to_string().len())
+            .max()
+            .unwrap_or_default();
+        println!("{:<width$} {:>8} {:>8}", "Package", "Errors", "Warnings");
+        for (spec, summary) in packages.iter().zip(&summaries) {
+            let errors = if summary.fatal {
+                "fatal".to_owned()
+            } else {
+                summary.errors.to_string()
+            };
+            println!(
+                "{:<width$} {:>8} {:>8}",
+                spec.to_string(),
+                errors,
+                summary.warnings
+            );
+        }
+        println!();
+        total.print(None);
+    }
+
+    ExitCode::from(total.exit_code(&options))
+}
+
+/// Checks several packages, with at most `options.jobs` of them at the same
+/// time.
+///
+/// The results are printed in the order of `packages`, each under a heading,
+/// once the checks of this package are done. Packages are not checked too
+/// far ahead of the one being reported, so that only a few of them are kept
+/// in memory.
+///
+/// Returns the summary of all the packages, and the summary of each of them.
+async fn check_many(
+    packages: Vec<(Option<PackageSpec>, PathBuf)>,
+    options: &CheckOptions,
+) -> (CheckSummary, Vec<CheckSummary>) {
+    let start = Instant::now();
+    let window = options.jobs.unwrap_or_else(jobs);
+    let mut to_spawn = packages.iter();
+    let mut runs = VecDeque::new();
+
+    let mut total = CheckSummary::default();
+    let mut summaries = Vec::new();
+    let mut reports = Reports::default();
+    for (spec, package_dir) in &packages {
+        while runs.len() < window {
+            let Some((spec, package_dir)) = to_spawn.next() else {
+                break;
+            };
+            let (spec, package_dir) = (spec.clone(), package_dir.clone());
+            let options = options.clone();
+            runs.push_back(tokio::spawn(async move {
+                run_checks(spec, package_dir, &options).await
+            }));
+        }
+        let run = runs
+            .pop_front()
+            .expect("the package should have been spawned");
+
+        if options.human_output() {
+            match spec {
+                Some(spec) => println!("\n## {spec}\n"),
+                None => println!("\n## {}\n", package_dir.display()),
+            }
+        }
+        let summary = match run.await {
+            Ok(run) => report(spec.clone(), package_dir, false, options, run, &mut reports),
+            Err(e) => {
+                println!("error: the checks crashed ({e})");
+                let package_name = match spec {
+                    Some(spec) => spec.to_string(),
+                    None => package_dir.display().to_string(),
+                };
+                reports
+                    .packages
+                    .push(report::PackageReport::fatal(&package_name, Duration::ZERO));
+                CheckSummary {
+                    fatal: true,
+                    ..Default::default()
+                }
+            }
+        };
+        total.add(&summary);
+        summaries.push(summary);
+    }
+    // The packages were checked in parallel, the sum of their durations
+    // would be misleading.
+    total.elapsed = start.elapsed();
+    reports.write(options, total.elapsed);
+    (total, summaries)
+}
+
+/// The reports that are written once all the packages are checked.
+#[derive(Default)]
+struct Reports {
+    /// For `--format junit`.
+    suites: Vec<junit::TestSuite>,
+    /// For `--report-file`, without `--format junit`.
+    packages: Vec<report::PackageReport>,
+}
+
+impl Reports {
+    /// Prints or writes the reports that were requested.
+    fn write(&self, options: &CheckOptions, elapsed: Duration) {
+        if options.junit {
+            if let Err(err) = junit::write(&self.suites, options.report_file.as_deref()) {
+                error!("failed to write the JUnit report ({err})")
+            }
+        } else if let Some(report_file) = &options.report_file {
+            if let Err(err) = report::write(report_file, &self.packages, elapsed) {
+                error!("failed to write the report ({err})")
+            }
+        }
+    }
+}
+
+/// Runs all the checks on a package and prints the results.
+///
+/// Returns the exit code for these results.
+async fn check(
+    package_spec: Option<PackageSpec>,
+    package_dir: PathBuf,
+    inferred: bool,
+    options: &CheckOptions,
+) -> CheckSummary {
+    let run = run_checks(package_spec.clone(), package_dir.clone(), options).await;
+    let mut reports = Reports::default();
+    let summary = report(
+        package_spec,
+        &package_dir,
+        inferred,
+        options,
+        run,
+        &mut reports,
+    );
+    reports.write(options, summary.elapsed);
+    summary
+}
+
+/// The results of the checks of a package, that are not printed yet.
+struct CheckRun {
+    /// The changes made to the package, if fixes were requested and the
+    /// checks could run.
+    fixes: Option<eyre::Result<Vec<String>>>,
+    /// With `--changed-since`, the files that changed, relative to the
+    /// package root.
+    changed_files: Option<Vec<PathBuf>>,
+    result: eyre::Result<(SystemWorld, Diagnostics)>,
+    elapsed: Duration,
+}
+
+/// Runs all the checks on a package, without printing anything.
+///
+/// With `options.fix`, the fixes are applied first and the checks run a
+/// second time.
+async fn run_checks(
+    package_spec: Option<PackageSpec>,
+    package_dir: PathBuf,
+    options: &CheckOptions,
+) -> CheckRun {
+    let start = Instant::now();
+    let changed_files = match &options.changed_since {
+        Some(base) => match git::GitRepo::open(&package_dir)
+            .files_changed_since(base)
+            .await
+        {
+            Ok(files) => Some(files.into_iter().map(|file| file.path).collect()),
+            Err(e) => {
+                return CheckRun {
+                    fixes: None,
+                    changed_files: None,
+                    result: Err(e.wrap_err("Failed to list the files that changed")),
+                    elapsed: start.elapsed(),
+                }
+            }
+        },
+        None => None,
+    };
+    let font_paths = &options.font_paths;
+    let jobs = options.jobs.unwrap_or_else(jobs);
+    let mut fixes = None;
+    if options.fix {
+        // Errors are reported by the second run of the checks.
+        if let Ok((_, diags)) = all_checks(
+            package_spec.as_ref(),
+            package_dir.clone(),
+            true,
+            font_paths,
+            false,
+            jobs,
+        )
+        .await
+        {
+            fixes = Some(fix::apply(&package_dir, &diags));
+        }
+    }
+    CheckRun {
+        fixes,
+        changed_files,
+        result: all_checks(
+            package_spec.as_ref(),
+            package_dir,
+            true,
+            font_paths,
+            options.fail_fast,
+            jobs,
+        )
+        .await,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Prints the results of the checks of a package.
+///
+/// They are also added to the reports that were requested.
+fn report(
+    package_spec: Option<PackageSpec>,
+    package_dir: &Path,
+    inferred: bool,
+    options: &CheckOptions,
+    run: CheckRun,
+    reports: &mut Reports,
+) -> CheckSummary {
+    let package_name = match &package_spec {
+        Some(spec) => spec.to_string(),
+        None => package_dir.display().to_string(),
+    };
+    match run.fixes.filter(|_| options.human_output()) {
+        Some(Ok(changes)) if changes.is_empty() => println!("Nothing could be fixed."),
+        Some(Ok(changes)) => {
+            println!("Fixed:");
+            for change in changes {
+                println!("  {change}");
+            }
+            println!("\nRemaining issues:\n");
+        }
+        Some(Err(e)) => println!("error: the fixes could not be applied ({e:#})"),
+        None => {}
+    }
+    let summary = match run.result {
+        Ok((mut world, mut diags)) => {
+            if let Some(spec) = package_spec.as_ref().filter(|_| inferred) {
+                diags.emit(Diagnostic::note().with_message(format!(
+                    "The package was checked as {spec}, based on the path of its directory."
+                )));
+            }
+            diags.override_severities(&options.severities);
+            if let Some(changed_files) = &run.changed_files {
+                diags.retain_files(changed_files);
+            }
+            if options.verbose_diagnostics {
+                diags.add_explanations();
+            }
+            if diags.stopped_early() {
+                diags.emit(Diagnostic::note().with_message(
+                    "The checks stopped at the first error because of --fail-fast, \
+                    other issues may exist.",
+                ));
+            }
+            if let Some(spec) = &package_spec {
+                let versions_dir = spec.versionless().directory();
+                if !versions_dir.exists() {
+                    diags.emit(Diagnostic::note().with_message(format!(
+                        "No other version of {} was found in {}, the checks comparing \
+                        this version to the previous one were skipped.",
+                        spec.versionless(),
+                        crate::package::dir().display(),
+                    )));
+                }
+            }
+
+            if options.json {
+                if let Err(err) = json::print_diagnostics(&package_name, &mut world, &diags) {
+                    error!("failed to print diagnostics ({err})")
+                }
+            } else if options.human_output() {
+                if let Err(err) = print_diagnostics(&mut world, &diags, options.color) {
+                    error!("failed to print diagnostics ({err})")
+                }
+            }
+
+            if options.junit {
+                // Excluded files are read too, see `print_diagnostics`.
+                world.exclude(Override::empty());
+                world.reset_file_cache();
+                reports.suites.push(junit::TestSuite::new(
+                    &package_name,
+                    &world,
+                    &diags,
+                    run.elapsed,
+                ));
+            }
+
+            if let Some(summary_path) = std::env::var_os("GITHUB_STEP_SUMMARY") {
+                if let Err(err) = write_step_summary(
+                    Path::new(&summary_path),
+                    &world,
+                    package_spec.as_ref(),
+                    &diags,
+                ) {
+                    error!("failed to write the job summary ({err})")
+                }
+            }
+
+            if options.timings && options.json {
+                if let Err(err) = json::print_timings(&package_name, diags.timings()) {
+                    error!("failed to print timings ({err})")
+                }
+            } else if options.timings && options.human_output() {
+                print_timings(diags.timings());
+            }
+
+            if options.report_file.is_some() {
+                reports.packages.push(report::PackageReport::new(
+                    &package_name,
+                    &diags,
+                    run.elapsed,
+                ));
+            }
+
+            let stats = diags.stats();
+            CheckSummary {
+                errors: stats.errors,
+                warnings: stats.warnings,
+                fatal: false,
+                elapsed: run.elapsed,
+            }
+        }
+        Err(e) => {
+            let diag = fatal_error(&e);
+            if options.json {
+                let json_diag = json::JsonDiagnostic::new(&package_name, None, &diag);
+                if let Err(err) = json::print_diagnostic(&json_diag) {
+                    error!("failed to print diagnostics ({err})")
+                }
+            } else if options.human_output() {
+                println!("error: {}", diag.message);
+                for note in &diag.notes {
+                    println!("  = {}", note);
+                }
+            }
+            if options.junit {
+                reports
+                    .suites
+                    .push(junit::TestSuite::fatal(&package_name, &diag, run.elapsed));
+            }
+            if options.report_file.is_some() {
+                reports
+                    .packages
+                    .push(report::PackageReport::fatal(&package_name, run.elapsed));
+            }
+            CheckSummary {
+                errors: 0,
+                warnings: 0,
+                fatal: true,
+                elapsed: run.elapsed,
+            }
+        }
+    };
+
+    if options.human_output() {
+        println!();
+        summary.print(package_spec.as_ref());
+    }
+    summary
+}
+
+fn print_timings(timings: &Timings) {
+    println!();
+    println!("Timings:");
+    for (phase, duration) in timings.sorted() {
+        println!("  {:<24} {:>8.3}s", phase, duration.as_secs_f64());
+    }
+}
+
+/// The outcome of the checks of one or more packages.
+#[derive(Default)]
+struct CheckSummary {
+    errors: usize,
+    warnings: usize,
+    /// Whether the checks of (one of) the packages could not be completed.
+    fatal: bool,
+    elapsed: Duration,
+}
+
+impl CheckSummary {
+    fn exit_code(&self, options: &CheckOptions) -> u8 {
+        if self.fatal {
+            EXIT_FATAL
+        } else if self.errors > 0
+            || options
+                .max_warnings
+                .is_some_and(|max_warnings| self.warnings > max_warnings)
+        {
+            EXIT_ERRORS
+        } else if self.warnings > 0 && !options.ignore_warnings {
+            EXIT_WARNINGS
+        } else {
+            0
+        }
+    }
+
+    fn add(&mut self, other: &CheckSummary) {
+        self.errors += other.errors;
+        self.warnings += other.warnings;
+        self.fatal |= other.fatal;
+        self.elapsed += other.elapsed;
+    }
+
+    /// Prints a line like `✖ 3 errors, ⚠ 7 warnings in @preview/foo:1.2.0 (4.2s)`.
+    fn print(&self, package: Option<&PackageSpec>) {
+        let plural = |count: usize, word: &str| {
+            format!("{count} {word}{}", if count == 1 { "" } else { "s" })
+        };
+        let target = package
+            .map(|spec| format!(" in {spec}"))
+            .unwrap_or_default();
+        let elapsed = self.elapsed.as_secs_f64();
+        if self.fatal {
+            println!("✖ the checks could not be completed{target} ({elapsed:.1}s)");
+        } else if self.errors > 0 || self.warnings > 0 {
+            let mut counts = Vec::new();
+            if self.errors > 0 {
+                counts.push(format!("✖ {}", plural(self.errors, "error")));
+            }
+            if self.warnings > 0 {
+                counts.push(format!("⚠ {}", plural(self.warnings, "warning")));
+            }
+            println!("{}{target} ({elapsed:.1}s)", counts.join(", "));
+        } else {
+            println!("✓ all checks passed{target} ({elapsed:.1}s)");
+        }
+    }
+}
+
+/// The specification of the latest version of a package.
+fn latest_spec(versionless: &VersionlessPackageSpec) -> Result<PackageSpec, String> {
+    let versions_dir = match versionless.namespace.as_str() {
+        "local" => dirs::data_dir()
+            .map(|data_dir| {
+                data_dir
+                    .join("typst/packages/local")
+                    .join(versionless.name.as_str())
+            })
+            .unwrap_or_default(),
+        _ => versionless.directory(),
+    };
+    let version = latest_version_in(&versions_dir).ok_or_else(|| {
+        format!(
+            "No version of {versionless} was found, its versions should be in {}.",
+            versions_dir.display()
+        )
+    })?;
+    Ok(PackageSpec {
+        namespace: versionless.namespace.clone(),
+        name: versionless.name.clone(),
+        version,
+    })
+}
+
+/// Finds the directory of a package given by its specification.
+///
+/// Preview packages are looked up in the `typst/packages` repository. Local
+/// packages are looked up where Typst would find them.
+fn package_dir_for(spec: &PackageSpec) -> Result<PathBuf, String> {
+    match spec.namespace.as_str() {
+        "preview" => Ok(spec.directory()),
+        "local" => dirs::data_dir()
+            .map(|data_dir| {
+                data_dir
+                    .join("typst/packages/local")
+                    .join(spec.name.as_str())
+                    .join(spec.version.to_string())
+            })
+            .filter(|dir| dir.exists())
+            .ok_or_else(|| {
+                format!(
+                    "{spec} was not found in your local packages. \
+                    To check a local package, you can also run this command \
+                    in its directory, without any argument."
+                )
+            }),
+        namespace => Err(format!(
+            "Unsupported namespace: @{namespace}. \
+            Only @preview and @local packages can be checked."
+        )),
+    }
+}
+
+/// Infers the specification of a package from a path like
+/// `packages/NAMESPACE/NAME/VERSION`, as in the `typst/packages` repository.
+fn spec_from_path(path: &Path) -> Option<PackageSpec> {
+    let mut components = path
+        .components()
+        .rev()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .map(|c| c.as_os_str().to_str());
+    let version = components.next()??.parse().ok()?;
+    let name = components.next()??;
+    let namespace = components.next()??;
+    if components.next()?? != "packages" {
+        return None;
+    }
+
+    Some(PackageSpec {
+        namespace: namespace.into(),
+        name: name.into(),
+        version,
+    })
 }
 
 /// Print diagnostic messages to the terminal.
 pub fn print_diagnostics(
     world: &mut SystemWorld,
-    errors: &[Diagnostic<FileId>],
-    warnings: &[Diagnostic<FileId>],
+    diags: &Diagnostics,
+    color: Color,
+) -> Result<(), codespan_reporting::files::Error> {
+    write_diagnostics(
+        &mut term::termcolor::StandardStream::stdout(color.choice()),
+        world,
+        diags,
+    )
+}
+
+/// Write diagnostic messages, in the same format as on the terminal.
+fn write_diagnostics(
+    writer: &mut impl term::termcolor::WriteColor,
+    world: &mut SystemWorld,
+    diags: &Diagnostics,
 ) -> Result<(), codespan_reporting::files::Error> {
     let config = term::Config {
         tab_width: 2,
@@ -42,18 +1480,144 @@ pub fn print_diagnostics(
     world.exclude(Override::empty());
     world.reset_file_cache();
 
-    for diagnostic in warnings.iter().chain(errors) {
-        term::emit(
-            &mut term::termcolor::StandardStream::stdout(term::termcolor::ColorChoice::Always),
-            &config,
-            world,
-            diagnostic,
-        )?;
+    for diagnostic in diags
+        .notes()
+        .iter()
+        .chain(diags.warnings())
+        .chain(diags.errors())
+    {
+        // Binary files can't be shown in a snippet, describe them instead.
+        let mut diagnostic = diagnostic.clone();
+        let mut headers = Vec::new();
+        diagnostic
+            .labels
+            .retain(|label| match binary_file_header(world, label.file_id) {
+                Some(header) => {
+                    headers.push(format!("in {header}"));
+                    false
+                }
+                None => true,
+            });
+        headers.dedup();
+        diagnostic.notes.splice(0..0, headers);
+        // Links are only shown here, to keep the messages short.
+        if let Some(url) = diagnostic.code.as_deref().and_then(explanations::doc_url) {
+            diagnostic.notes.push(format!("more info: {url}"));
+        }
+
+        term::emit(writer, &config, world, &diagnostic)?;
     }
 
     Ok(())
 }
 
+/// Describes a file that can't be displayed in a code snippet, because it is
+/// binary or can't be read, with its path, size and type.
+///
+/// Returns `None` for text files.
+fn binary_file_header(world: &SystemWorld, id: FileId) -> Option<String> {
+    let name = world
+        .name(id)
+        .unwrap_or_else(|_| id.vpath().as_rootless_path().display().to_string());
+    match world.file(id) {
+        Ok(bytes) if !is_binary(&bytes) => None,
+        Ok(bytes) => Some(format!(
+            "{name} ({}, {})",
+            binary_kind(id.vpath().as_rootless_path()),
+            format_size(bytes.len() as u64)
+        )),
+        Err(_) => Some(format!("{name} (could not be read)")),
+    }
+}
+
+/// GitHub doesn't display job summaries that are larger than that.
+const MAX_STEP_SUMMARY_LEN: usize = 1024 * 1024;
+
+/// Append a Markdown summary of the diagnostics to a file.
+///
+/// This is meant to be used in GitHub Actions, where the path of this file is
+/// given by `$GITHUB_STEP_SUMMARY`.
+fn write_step_summary(
+    path: &Path,
+    world: &SystemWorld,
+    package_spec: Option<&PackageSpec>,
+    diags: &Diagnostics,
+) -> std::io::Result<()> {
+    let mut summary = match package_spec {
+        Some(spec) => format!("## `{spec}`\n\n"),
+        None => "## Package check\n\n".to_owned(),
+    };
+
+    for (title, list) in [
+        ("Errors", diags.errors()),
+        ("Warnings", diags.warnings()),
+        ("Notes", diags.notes()),
+    ] {
+        if list.is_empty() {
+            continue;
+        }
+
+        summary += &format!("### {title}\n\n| Location | Message |\n|---|---|\n");
+        for diag in list {
+            let location = diag
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    let name = world.name(label.file_id).ok()?;
+                    let line = world.line_index(label.file_id, label.range.start).ok()?;
+                    Some(format!("`{}:{}`", name, line + 1))
+                })
+                .collect::<Vec<_>>()
+                .join("<br>");
+            let mut message = escape_markdown(&diag.message);
+            for note in &diag.notes {
+                message += "<br>";
+                message += &escape_markdown(note);
+            }
+            let row = format!("| {} | {} |\n", location, message);
+
+            if summary.len() + row.len() > MAX_STEP_SUMMARY_LEN - 128 {
+                summary += "\n*This summary was truncated.*\n";
+                return append(path, &summary);
+            }
+            summary += &row;
+        }
+        summary += "\n";
+    }
+
+    if diags.errors().is_empty() && diags.warnings().is_empty() {
+        summary += "No issues were found.\n";
+    }
+    summary += "\n";
+    summary += &diags.timings().to_markdown();
+
+    append(path, &summary)
+}
+
+fn append(path: &Path, text: &str) -> std::io::Result<()> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(text.as_bytes())
+}
+
+/// Escape a message to be displayed in a Markdown table cell.
+fn escape_markdown(message: &str) -> String {
+    let mut escaped = String::with_capacity(message.len());
+    for c in message.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 type CodespanResult<T> = Result<T, CodespanError>;
 type CodespanError = codespan_reporting::files::Error;
 
@@ -123,3 +1687,181 @@ impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Label;
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// The world of a fixture, with a relative import of its entrypoint
+    /// reported in its example.
+    fn relative_import() -> (SystemWorld, Diagnostics) {
+        let dir = fixture("disabled-codes");
+        let world = SystemWorld::new(dir.join("lib.typ"), dir).unwrap();
+        let mut diags = Diagnostics::default();
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("import/relative")
+                .with_message("This import should use the package specification.")
+                .with_labels(vec![Label::primary(
+                    FileId::new(None, VirtualPath::new("examples/demo.typ")),
+                    0..23,
+                )]),
+        );
+        (world, diags)
+    }
+
+    fn options_with(flag: Option<Severity>) -> CheckOptions {
+        CheckOptions {
+            json: true,
+            severities: vec![("import/relative".to_owned(), flag)],
+            ..CheckOptions::default()
+        }
+    }
+
+    fn exit_code_with(flag: Option<Severity>) -> u8 {
+        let (world, diags) = relative_import();
+        let run = CheckRun {
+            fixes: None,
+            changed_files: None,
+            result: Ok((world, diags)),
+            elapsed: Duration::ZERO,
+        };
+        let options = options_with(flag);
+        let summary = report(
+            None,
+            &fixture("disabled-codes"),
+            false,
+            &options,
+            run,
+            &mut Reports::default(),
+        );
+        summary.exit_code(&options)
+    }
+
+    fn summary(errors: usize, warnings: usize, fatal: bool) -> CheckSummary {
+        CheckSummary {
+            errors,
+            warnings,
+            fatal,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        let default = CheckOptions::default();
+        let ignore_warnings = CheckOptions {
+            ignore_warnings: true,
+            ..CheckOptions::default()
+        };
+        let max_warnings = CheckOptions {
+            max_warnings: Some(2),
+            ..CheckOptions::default()
+        };
+        let both = CheckOptions {
+            ignore_warnings: true,
+            max_warnings: Some(2),
+            ..CheckOptions::default()
+        };
+
+        let cases = [
+            // errors, warnings, fatal, options, expected
+            (0, 0, false, &default, 0),
+            (0, 1, false, &default, EXIT_WARNINGS),
+            (1, 0, false, &default, EXIT_ERRORS),
+            (1, 1, false, &default, EXIT_ERRORS),
+            (0, 0, true, &default, EXIT_FATAL),
+            (1, 1, true, &default, EXIT_FATAL),
+            (0, 1, false, &ignore_warnings, 0),
+            (1, 1, false, &ignore_warnings, EXIT_ERRORS),
+            (0, 2, false, &max_warnings, EXIT_WARNINGS),
+            (0, 3, false, &max_warnings, EXIT_ERRORS),
+            (0, 2, false, &both, 0),
+            (0, 3, false, &both, EXIT_ERRORS),
+            (0, 3, true, &both, EXIT_FATAL),
+        ];
+        for (errors, warnings, fatal, options, expected) in cases {
+            assert_eq!(
+                summary(errors, warnings, fatal).exit_code(options),
+                expected,
+                "{errors} errors, {warnings} warnings, fatal: {fatal}"
+            );
+        }
+    }
+
+    fn written_diagnostics(color: Color) -> String {
+        let (mut world, diags) = relative_import();
+        let mut buffer = term::termcolor::BufferWriter::stdout(color.choice()).buffer();
+        write_diagnostics(&mut buffer, &mut world, &diags).unwrap();
+        String::from_utf8(buffer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn no_colors_when_disabled() {
+        let output = written_diagnostics(Color::Never);
+        assert!(output.contains("import/relative"));
+        assert!(!output.contains('\x1b'));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn colors_when_forced() {
+        assert!(written_diagnostics(Color::Always).contains('\x1b'));
+    }
+
+    #[test]
+    fn severity_overrides_change_the_exit_code() {
+        assert_eq!(exit_code_with(Some(Severity::Error)), EXIT_ERRORS);
+        assert_eq!(exit_code_with(Some(Severity::Warning)), EXIT_WARNINGS);
+        assert_eq!(exit_code_with(None), 0);
+    }
+
+    #[test]
+    fn severity_overrides_change_the_json_severity() {
+        let (world, mut diags) = relative_import();
+        diags.override_severities(&options_with(Some(Severity::Error)).severities);
+        assert!(diags.warnings().is_empty());
+        let [denied] = diags.errors() else {
+            panic!("expected one error");
+        };
+        let json =
+            serde_json::to_value(json::JsonDiagnostic::new("test", Some(&world), denied)).unwrap();
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["code"], "import/relative");
+        assert_eq!(json["file"], "examples/demo.typ");
+    }
+
+    #[test]
+    fn documented_codes_link_to_their_documentation() {
+        let (mut world, mut diags) = relative_import();
+        diags.emit(
+            Diagnostic::warning()
+                .with_code("files/fonts")
+                .with_message("This font is not embedded in Typst."),
+        );
+        let url = explanations::doc_url("files/fonts").unwrap();
+
+        let mut buffer = term::termcolor::BufferWriter::stdout(Color::Never.choice()).buffer();
+        write_diagnostics(&mut buffer, &mut world, &diags).unwrap();
+        let output = String::from_utf8(buffer.into_inner()).unwrap();
+        assert!(output.contains(&format!("more info: {url}")), "{output}");
+        assert_eq!(output.matches("more info: ").count(), 1);
+
+        let [relative, fonts] = diags.warnings() else {
+            panic!("expected two warnings");
+        };
+        let json = serde_json::to_value(json::JsonDiagnostic::new("test", Some(&world), fonts));
+        assert_eq!(json.unwrap()["help_uri"], url);
+        let json = serde_json::to_value(json::JsonDiagnostic::new("test", Some(&world), relative));
+        assert!(json.unwrap()["help_uri"].is_null());
+    }
+}
@@ -0,0 +1,133 @@
+//! Automatic fixes for the `check --fix` command.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, ContextCompat};
+use typst::syntax::FileId;
+
+use crate::check::{Diagnostics, Fix};
+
+/// Applies the suggestions and fixes of the diagnostics of a package.
+///
+/// Returns a description of each change that was made. Changes that would
+/// affect files outside of the package directory are ignored.
+pub fn apply(package_dir: &Path, diags: &Diagnostics) -> eyre::Result<Vec<String>> {
+    let root = package_dir
+        .canonicalize()
+        .context("Failed to canonicalize package directory")?;
+    let mut changes = Vec::new();
+
+    // Text replacements first, as renames would change the paths they apply
+    // to.
+    let mut files: Vec<FileId> = diags
+        .suggestions()
+        .iter()
+        .map(|suggestion| suggestion.file_id)
+        .collect();
+    files.sort_by_key(|id| id.vpath().as_rootless_path().to_owned());
+    files.dedup();
+    for file_id in files {
+        if file_id.package().is_some() {
+            continue;
+        }
+        let Some(path) = inside(&root, file_id.vpath().as_rootless_path()) else {
+            continue;
+        };
+
+        let mut suggestions: Vec<_> = diags
+            .suggestions()
+            .iter()
+            .filter(|suggestion| suggestion.file_id == file_id)
+            .collect();
+        // From the end of the file, so that ranges stay valid.
+        suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.range.start));
+
+        let mut contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut applied_until = usize::MAX;
+        for suggestion in suggestions {
+            let range = suggestion.range.clone();
+            // Overlapping suggestions can't all be applied.
+            if range.end > applied_until || contents.get(range.clone()).is_none() {
+                continue;
+            }
+            contents.replace_range(range.clone(), &suggestion.replacement);
+            applied_until = range.start;
+            changes.push(format!(
+                "{}: replaced with {} ({})",
+                file_id.vpath().as_rootless_path().display(),
+                suggestion.replacement,
+                suggestion.message
+            ));
+        }
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    let to_exclude: Vec<&PathBuf> = diags
+        .fixes()
+        .iter()
+        .filter_map(|fix| match fix {
+            Fix::Exclude(path) => Some(path),
+            _ => None,
+        })
+        .filter(|path| inside(&root, path).is_some())
+        .collect();
+    if !to_exclude.is_empty() {
+        let manifest_path = root.join("typst.toml");
+        let mut manifest: toml_edit::DocumentMut = std::fs::read_to_string(&manifest_path)
+            .context("Failed to read manifest contents.")?
+            .parse()
+            .context("Failed to parse manifest contents")?;
+        let exclude = manifest
+            .get_mut("package")
+            .and_then(|package| package.as_table_like_mut())
+            .context("[package] not found")?
+            .entry("exclude")
+            .or_insert(toml_edit::value(toml_edit::Array::new()))
+            .as_array_mut()
+            .context("`exclude` should be an array")?;
+        for path in to_exclude {
+            let path = path.to_string_lossy();
+            if !exclude
+                .iter()
+                .any(|glob| glob.as_str() == Some(path.as_ref()))
+            {
+                exclude.push(path.as_ref());
+                changes.push(format!("typst.toml: added {path} to `exclude`"));
+            }
+        }
+        std::fs::write(&manifest_path, manifest.to_string()).context("Failed to write manifest")?;
+    }
+
+    for fix in diags.fixes() {
+        let Fix::Rename { from, to } = fix else {
+            continue;
+        };
+        let (Some(from_path), Some(to_path)) = (inside(&root, from), inside(&root, to)) else {
+            continue;
+        };
+        // On case-insensitive file systems, the new name may already exist,
+        // but be the same file.
+        let same_file = to_path.canonicalize().ok() == from_path.canonicalize().ok();
+        if to_path.exists() && !same_file {
+            continue;
+        }
+
+        let temp_path = from_path.with_extension("package-check-rename");
+        std::fs::rename(&from_path, &temp_path)
+            .and_then(|_| std::fs::rename(&temp_path, &to_path))
+            .with_context(|| format!("Failed to rename {}", from.display()))?;
+        changes.push(format!("renamed {} to {}", from.display(), to.display()));
+    }
+
+    Ok(changes)
+}
+
+/// The full path of a file of the package, if it doesn't escape from the
+/// package directory.
+fn inside(root: &Path, path: &Path) -> Option<PathBuf> {
+    let full_path = root.join(path);
+    let parent = full_path.parent()?.canonicalize().ok()?;
+    parent.starts_with(root).then_some(full_path)
+}
@@ -0,0 +1,180 @@
+//! Machine-readable output of the checks, with one JSON object per line and
+//! per diagnostic.
+
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label, LabelStyle, Severity},
+    files::Files,
+};
+use ignore::overrides::Override;
+use serde::Serialize;
+use typst::{syntax::FileId, World};
+
+use crate::{
+    check::{explanations, files::is_binary, Diagnostics, Timings},
+    world::SystemWorld,
+};
+
+#[derive(Serialize)]
+pub struct JsonDiagnostic<'a> {
+    /// The package this diagnostic is about: its specification
+    /// (`@namespace/name:version`) when it is known, its directory otherwise.
+    pub package: &'a str,
+    pub severity: &'static str,
+    pub code: Option<&'a str>,
+    /// The documentation of the code, named like in SARIF.
+    pub help_uri: Option<&'static str>,
+    pub message: &'a str,
+    pub notes: &'a [String],
+    /// Where the diagnostic is, given by its primary label. All the fields
+    /// are `null` if it has none.
+    #[serde(flatten)]
+    pub location: JsonLocation,
+    /// The other labels of the diagnostic.
+    pub related: Vec<JsonLabel<'a>>,
+}
+
+#[derive(Serialize, Default)]
+pub struct JsonLocation {
+    /// Path of the file, relative to the package root.
+    pub file: Option<String>,
+    /// Byte range in the file.
+    pub range: Option<JsonRange>,
+    /// The line and column of the start of the range, starting at 1, when
+    /// they are known.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Whether the file is not text, or could not be read. In this case,
+    /// the range is usually empty.
+    pub binary: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct JsonRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize)]
+pub struct JsonLabel<'a> {
+    #[serde(flatten)]
+    pub location: JsonLocation,
+    pub message: &'a str,
+}
+
+impl<'a> JsonDiagnostic<'a> {
+    /// Converts a diagnostic.
+    ///
+    /// Without a world, for instance when the checks could not complete, line
+    /// numbers are not computed.
+    pub fn new(
+        package: &'a str,
+        world: Option<&SystemWorld>,
+        diag: &'a Diagnostic<FileId>,
+    ) -> Self {
+        let primary = diag
+            .labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary);
+        JsonDiagnostic {
+            package,
+            severity: match diag.severity {
+                Severity::Bug | Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note | Severity::Help => "note",
+            },
+            code: diag.code.as_deref(),
+            help_uri: diag.code.as_deref().and_then(explanations::doc_url),
+            message: &diag.message,
+            notes: &diag.notes,
+            location: primary
+                .map(|label| JsonLocation::new(world, label))
+                .unwrap_or_default(),
+            related: diag
+                .labels
+                .iter()
+                .filter(|label| !primary.is_some_and(|primary| std::ptr::eq(*label, primary)))
+                .map(|label| JsonLabel {
+                    location: JsonLocation::new(world, label),
+                    message: &label.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl JsonLocation {
+    fn new(world: Option<&SystemWorld>, label: &Label<FileId>) -> Self {
+        let binary = world
+            .map(|world| !matches!(world.file(label.file_id), Ok(bytes) if !is_binary(&bytes)));
+        // Lines and columns are computed like for the terminal output.
+        let text_world = world.filter(|_| binary == Some(false));
+        let line =
+            text_world.and_then(|world| world.line_index(label.file_id, label.range.start).ok());
+        JsonLocation {
+            file: Some(
+                label
+                    .file_id
+                    .vpath()
+                    .as_rootless_path()
+                    .display()
+                    .to_string(),
+            ),
+            range: Some(JsonRange {
+                start: label.range.start,
+                end: label.range.end,
+            }),
+            line: line.map(|line| line + 1),
+            column: text_world.zip(line).and_then(|(world, line)| {
+                world
+                    .column_number(label.file_id, line, label.range.start)
+                    .ok()
+                    // Typst counts columns from 0.
+                    .map(|column| column + 1)
+            }),
+            binary,
+        }
+    }
+}
+
+/// Prints all the diagnostics of a package, errors first.
+pub fn print_diagnostics(
+    package: &str,
+    world: &mut SystemWorld,
+    diags: &Diagnostics,
+) -> serde_json::Result<()> {
+    // Excluded files are read too, see `cli::print_diagnostics`.
+    world.exclude(Override::empty());
+    world.reset_file_cache();
+
+    for diag in diags
+        .errors()
+        .iter()
+        .chain(diags.warnings())
+        .chain(diags.notes())
+    {
+        print_diagnostic(&JsonDiagnostic::new(package, Some(world), diag))?;
+    }
+    Ok(())
+}
+
+/// Prints how long each phase of the checks of a package took, after its
+/// diagnostics, as an object with a `timings` field instead of a `severity`.
+///
+/// Durations are in seconds, and phases are sorted from the slowest.
+pub fn print_timings(package: &str, timings: &Timings) -> serde_json::Result<()> {
+    let phases: Vec<_> = timings
+        .sorted()
+        .into_iter()
+        .map(|(phase, duration)| {
+            serde_json::json!({ "phase": phase, "seconds": duration.as_secs_f64() })
+        })
+        .collect();
+    let output = serde_json::json!({ "package": package, "timings": phases });
+    println!("{}", serde_json::to_string(&output)?);
+    Ok(())
+}
+
+pub fn print_diagnostic(diag: &JsonDiagnostic) -> serde_json::Result<()> {
+    println!("{}", serde_json::to_string(diag)?);
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! Reports in the JUnit XML format, that most CI services can display.
+//!
+//! Each package is a test suite, with one test case per diagnostic code.
+//! Errors and warnings are failures of these test cases, notes are ignored.
+
+use std::{fmt::Write, path::Path, time::Duration};
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use typst::syntax::FileId;
+
+use super::json::JsonDiagnostic;
+use crate::{
+    check::{explanations::EXPLANATIONS, Diagnostics},
+    world::SystemWorld,
+};
+
+/// Name of the test case for the diagnostics that don't have a code.
+const UNCATEGORIZED: &str = "uncategorized";
+
+pub struct TestSuite {
+    name: String,
+    cases: Vec<TestCase>,
+    time: Duration,
+}
+
+struct TestCase {
+    name: String,
+    /// The errors and warnings with the code of this test case.
+    failures: Vec<String>,
+    /// Whether one of the failures is an error.
+    has_errors: bool,
+    /// Set when the checks could not run at all.
+    error: Option<String>,
+}
+
+impl TestSuite {
+    /// The results of the checks of a package.
+    pub fn new(package: &str, world: &SystemWorld, diags: &Diagnostics, time: Duration) -> Self {
+        let mut cases: Vec<TestCase> = EXPLANATIONS
+            .iter()
+            .map(|explanation| TestCase::new(explanation.code))
+            .collect();
+        cases.push(TestCase::new(UNCATEGORIZED));
+
+        for diag in diags.errors().iter().chain(diags.warnings()) {
+            let name = diag.code.as_deref().unwrap_or(UNCATEGORIZED);
+            let index = match cases.iter().position(|case| case.name == name) {
+                Some(index) => index,
+                None => {
+                    cases.push(TestCase::new(name));
+                    cases.len() - 1
+                }
+            };
+            let case = &mut cases[index];
+            case.has_errors |= matches!(diag.severity, Severity::Bug | Severity::Error);
+            case.failures.push(describe(package, world, diag));
+        }
+
+        TestSuite {
+            name: package.to_owned(),
+            cases,
+            time,
+        }
+    }
+
+    /// A package for which the checks could not be completed.
+    pub fn fatal(package: &str, diag: &Diagnostic<FileId>, time: Duration) -> Self {
+        let mut message = diag.message.clone();
+        for note in &diag.notes {
+            message += &format!("\n= {note}");
+        }
+        TestSuite {
+            name: package.to_owned(),
+            cases: vec![TestCase {
+                error: Some(message),
+                ..TestCase::new("checks")
+            }],
+            time,
+        }
+    }
+}
+
+impl TestCase {
+    fn new(name: &str) -> Self {
+        TestCase {
+            name: name.to_owned(),
+            failures: Vec::new(),
+            has_errors: false,
+            error: None,
+        }
+    }
+}
+
+/// A diagnostic as text, starting with its location like compilers do.
+fn describe(package: &str, world: &SystemWorld, diag: &Diagnostic<FileId>) -> String {
+    let json = JsonDiagnostic::new(package, Some(world), diag);
+    let location = &json.location;
+    let mut text = match (&location.file, location.line, location.column) {
+        (Some(file), Some(line), Some(column)) => format!("{file}:{line}:{column}: "),
+        (Some(file), _, _) => format!("{file}: "),
+        _ => String::new(),
+    };
+    text += json.severity;
+    text += ": ";
+    text += json.message;
+    for note in json.notes {
+        text += &format!("\n  = {note}");
+    }
+    text
+}
+
+/// Renders the test suites of all the packages as a JUnit XML document.
+pub fn render(suites: &[TestSuite]) -> String {
+    let count = |suite: &TestSuite, f: fn(&TestCase) -> bool| {
+        suite.cases.iter().filter(|case| f(case)).count()
+    };
+    let tests: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    let failures: usize = suites
+        .iter()
+        .map(|suite| count(suite, |case| !case.failures.is_empty()))
+        .sum();
+    let errors: usize = suites
+        .iter()
+        .map(|suite| count(suite, |case| case.error.is_some()))
+        .sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        xml,
+        "<testsuites name=\"typst-package-check\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\">"
+    )
+    .ok();
+    for suite in suites {
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            escape(&suite.name),
+            suite.cases.len(),
+            count(suite, |case| !case.failures.is_empty()),
+            count(suite, |case| case.error.is_some()),
+            suite.time.as_secs_f64(),
+        )
+        .ok();
+        for case in &suite.cases {
+            let name = escape(&case.name);
+            let classname = escape(&suite.name);
+            if let Some(error) = &case.error {
+                writeln!(
+                    xml,
+                    "    <testcase name=\"{name}\" classname=\"{classname}\">\n      \
+                    <error message=\"the checks could not be completed\">{}</error>\n    \
+                    </testcase>",
+                    escape(error)
+                )
+                .ok();
+            } else if case.failures.is_empty() {
+                writeln!(
+                    xml,
+                    "    <testcase name=\"{name}\" classname=\"{classname}\"/>"
+                )
+                .ok();
+            } else {
+                let kind = if case.has_errors { "error" } else { "warning" };
+                let message = match case.failures.len() {
+                    1 => format!("1 {kind}"),
+                    n => format!("{n} issues"),
+                };
+                writeln!(
+                    xml,
+                    "    <testcase name=\"{name}\" classname=\"{classname}\">\n      \
+                    <failure message=\"{message}\" type=\"{kind}\">{}</failure>\n    \
+                    </testcase>",
+                    escape(&case.failures.join("\n\n"))
+                )
+                .ok();
+            }
+        }
+        xml += "  </testsuite>\n";
+    }
+    xml += "</testsuites>\n";
+    xml
+}
+
+/// Prints the report, or writes it to `report_file`.
+pub fn write(suites: &[TestSuite], report_file: Option<&Path>) -> std::io::Result<()> {
+    let xml = render(suites);
+    match report_file {
+        Some(path) => std::fs::write(path, xml),
+        None => {
+            print!("{xml}");
+            Ok(())
+        }
+    }
+}
+
+/// Escapes text for XML attributes and contents.
+///
+/// Control characters other than tabs and new lines are not allowed in XML
+/// 1.0, even escaped, so they are replaced.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            '\n' => escaped += "&#10;",
+            '\r' => escaped += "&#13;",
+            '\t' => escaped.push(c),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use codespan_reporting::diagnostic::Label;
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    fn suite(diags: &Diagnostics) -> TestSuite {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/disabled-codes");
+        let world = SystemWorld::new(dir.join("lib.typ"), dir).unwrap();
+        TestSuite::new("@preview/<test>:0.1.0", &world, diags, Duration::ZERO)
+    }
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape("a\nb\r\n\tc"), "a&#10;b&#13;&#10;\tc");
+        assert_eq!(escape("bell\u{7}"), "bell\u{FFFD}");
+        assert_eq!(escape("déjà vu"), "déjà vu");
+    }
+
+    #[test]
+    fn render_compiler_errors() {
+        let mut diags = Diagnostics::default();
+        diags.emit(
+            Diagnostic::error()
+                .with_code("compile/error")
+                .with_message(
+                    "The following error was reported by the Typst compiler: \
+                    expected expression\nhint: use `#` for <code> & \"markup\"",
+                )
+                .with_labels(vec![Label::primary(
+                    FileId::new(None, VirtualPath::new("lib.typ")),
+                    0..4,
+                )]),
+        );
+        let xml = render(&[suite(&diags)]);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(xml.contains(&format!(
+            "<testsuites name=\"typst-package-check\" tests=\"{}\" failures=\"1\" errors=\"0\">",
+            EXPLANATIONS.len() + 1
+        )));
+        assert!(xml.contains(
+            "<testcase name=\"compile/error\" classname=\"@preview/&lt;test&gt;:0.1.0\">\n      \
+            <failure message=\"1 error\" type=\"error\">lib.typ:1:1: error: \
+            The following error was reported by the Typst compiler: expected expression\
+            &#10;hint: use `#` for &lt;code&gt; &amp; &quot;markup&quot;</failure>"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"uncategorized\" classname=\"@preview/&lt;test&gt;:0.1.0\"/>"
+        ));
+        // The failure is on a single line of the document.
+        assert_eq!(
+            xml.lines().filter(|line| line.contains("<failure")).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn render_fatal_errors() {
+        let diag = Diagnostic::error()
+            .with_message("The manifest can't be read")
+            .with_notes(vec!["caused by: <io error>".to_owned()]);
+        let xml = render(&[TestSuite::fatal("pkg", &diag, Duration::ZERO)]);
+        assert!(xml.contains("tests=\"1\" failures=\"0\" errors=\"1\""));
+        assert!(xml.contains(
+            "<error message=\"the checks could not be completed\">\
+            The manifest can&apos;t be read&#10;= caused by: &lt;io error&gt;</error>"
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! Summary of a run of the checks, written as JSON with `--report-file`.
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde::Serialize;
+
+use crate::check::{Diagnostics, TYPST_VERSION};
+
+/// Version of the format of [`RunReport`].
+const REPORT_VERSION: u32 = 1;
+
+/// The results of a run of the checks, for CI jobs.
+///
+/// This format is stable: fields can be added, but existing fields keep
+/// their name and meaning as long as `version` is the same.
+#[derive(Serialize)]
+pub struct RunReport<'a> {
+    pub version: u32,
+    /// Version of the Typst compiler used to check the packages.
+    pub typst: &'static str,
+    /// Wall-clock duration of the whole run, in seconds.
+    pub duration: f64,
+    pub packages: &'a [PackageReport],
+}
+
+/// The results of the checks of one package.
+#[derive(Serialize)]
+pub struct PackageReport {
+    /// The specification of the package (`@namespace/name:version`) when it
+    /// is known, its directory otherwise.
+    pub package: String,
+    /// Whether the checks could not be completed. The counts are all zero
+    /// in this case.
+    pub fatal: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub notes: usize,
+    /// Number of diagnostics of each code, diagnostics without a code being
+    /// counted with an empty one.
+    pub codes: BTreeMap<String, usize>,
+    /// Duration of the checks of this package, in seconds.
+    pub duration: f64,
+    /// Duration of each phase of the checks, in seconds, from the slowest.
+    pub timings: Vec<PhaseTiming>,
+}
+
+#[derive(Serialize)]
+pub struct PhaseTiming {
+    pub phase: &'static str,
+    pub duration: f64,
+}
+
+impl PackageReport {
+    pub fn new(package: &str, diags: &Diagnostics, duration: Duration) -> Self {
+        let stats = diags.stats();
+        PackageReport {
+            package: package.to_owned(),
+            fatal: false,
+            errors: stats.errors,
+            warnings: stats.warnings,
+            notes: stats.notes,
+            codes: stats.by_code.clone(),
+            duration: duration.as_secs_f64(),
+            timings: diags
+                .timings()
+                .sorted()
+                .into_iter()
+                .map(|(phase, duration)| PhaseTiming {
+                    phase,
+                    duration: duration.as_secs_f64(),
+                })
+                .collect(),
+        }
+    }
+
+    /// A package for which the checks could not be completed.
+    pub fn fatal(package: &str, duration: Duration) -> Self {
+        PackageReport {
+            package: package.to_owned(),
+            fatal: true,
+            errors: 0,
+            warnings: 0,
+            notes: 0,
+            codes: BTreeMap::new(),
+            duration: duration.as_secs_f64(),
+            timings: Vec::new(),
+        }
+    }
+}
+
+/// Writes the report of a run to a file.
+pub fn write(path: &Path, packages: &[PackageReport], duration: Duration) -> eyre::Result<()> {
+    let report = RunReport {
+        version: REPORT_VERSION,
+        typst: TYPST_VERSION,
+        duration: duration.as_secs_f64(),
+        packages,
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&report)? + "\n")?;
+    Ok(())
+}
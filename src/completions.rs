@@ -0,0 +1,278 @@
+//! Shell completions for the command line interface.
+//!
+//! The arguments are parsed by hand in `main`, so the scripts are written by
+//! hand too, and must be kept in sync with it.
+//!
+//! Packages to check are completed dynamically, by running `completions
+//! packages`.
+
+use std::{path::Path, process::ExitCode};
+
+use crate::{check::explanations::EXPLANATIONS, package};
+
+/// The subcommands, with the flags they accept.
+const SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("serve", &["--port"]),
+    (
+        "check",
+        &[
+            "--changed",
+            "--all",
+            "--namespace",
+            "--since",
+            "--changed-since",
+            "--specs-from",
+            "--jobs",
+            "--font-path",
+            "--as",
+            "--version",
+            "--timings",
+            "--json",
+            "--format",
+            "--report-file",
+            "--ignore-warnings",
+            "--max-warnings",
+            "--allow",
+            "--warn",
+            "--deny",
+            "--fix",
+            "--color",
+            "--fail-fast",
+            "--verbose-diagnostics",
+            "--offline",
+        ],
+    ),
+    ("bundle", &["--output", "--force"]),
+    ("show-excluded", &["--json"]),
+    ("deps", &["--json"]),
+    ("explain", &["--list"]),
+    ("list-checks", &["--json"]),
+    ("typst-version", &["--json"]),
+    ("completions", &[]),
+];
+
+const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// How deep package directories are searched from the current directory.
+const MAX_PACKAGE_DEPTH: usize = 3;
+
+/// Prints the completion script for a shell.
+///
+/// With `packages` instead of a shell, prints the packages that can be
+/// checked, one per line, for the scripts.
+pub fn main(shell: Option<String>) -> ExitCode {
+    let script = match shell.as_deref() {
+        Some("bash") => bash(),
+        Some("zsh") => zsh(),
+        Some("fish") => fish(),
+        Some("powershell") => powershell(),
+        Some("packages") => {
+            for package in packages(Path::new(".")) {
+                println!("{package}");
+            }
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!(
+                "completions should be followed by the name of a shell ({}).",
+                SHELLS.join(", ")
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    print!("{script}");
+    ExitCode::SUCCESS
+}
+
+/// The packages that can be given to `check`: the directories with a
+/// manifest below `dir`, and the specifications of the packages of
+/// `typst/packages` when it is found.
+fn packages(dir: &Path) -> Vec<String> {
+    let mut packages = Vec::new();
+    find_package_dirs(dir, 0, &mut packages);
+
+    let in_dir = |path: &Path| std::fs::read_dir(path).into_iter().flatten().flatten();
+    for namespace in in_dir(&package::dir()) {
+        for name in in_dir(&namespace.path()) {
+            for version in in_dir(&name.path()) {
+                packages.push(format!(
+                    "@{}/{}:{}",
+                    namespace.file_name().to_string_lossy(),
+                    name.file_name().to_string_lossy(),
+                    version.file_name().to_string_lossy()
+                ));
+            }
+        }
+    }
+    packages.sort();
+    packages
+}
+
+fn find_package_dirs(dir: &Path, depth: usize, packages: &mut Vec<String>) {
+    if depth > 0 && dir.join("typst.toml").exists() {
+        let path = dir.strip_prefix(".").unwrap_or(dir);
+        packages.push(path.display().to_string());
+    }
+    if depth == MAX_PACKAGE_DEPTH {
+        return;
+    }
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            find_package_dirs(&entry.path(), depth + 1, packages);
+        }
+    }
+}
+
+fn subcommand_names() -> String {
+    SUBCOMMANDS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn codes() -> String {
+    EXPLANATIONS
+        .iter()
+        .map(|explanation| explanation.code)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for (name, flags) in SUBCOMMANDS {
+        let words = match *name {
+            "check" => format!(
+                "{} $(typst-package-check completions packages 2>/dev/null)",
+                flags.join(" ")
+            ),
+            "explain" => format!("{} {}", flags.join(" "), codes()),
+            "completions" => SHELLS.join(" "),
+            _ => flags.join(" "),
+        };
+        cases += &format!("        {name}) words=\"{words}\" ;;\n");
+    }
+
+    format!(
+        r#"_typst_package_check() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{subcommands}" -- "$cur"))
+        return
+    fi
+    local words=""
+    case "${{COMP_WORDS[1]}}" in
+{cases}    esac
+    COMPREPLY=($(compgen -W "$words" -- "$cur") $(compgen -d -- "$cur"))
+}}
+complete -F _typst_package_check typst-package-check
+"#,
+        subcommands = subcommand_names(),
+    )
+}
+
+fn zsh() -> String {
+    let mut cases = String::new();
+    for (name, flags) in SUBCOMMANDS {
+        let words = match *name {
+            "check" => format!(
+                "{} $(typst-package-check completions packages 2>/dev/null)",
+                flags.join(" ")
+            ),
+            "explain" => format!("{} {}", flags.join(" "), codes()),
+            "completions" => SHELLS.join(" "),
+            _ => flags.join(" "),
+        };
+        cases += &format!("        {name}) compadd -- {words} ;;\n");
+    }
+
+    format!(
+        r#"#compdef typst-package-check
+
+_typst_package_check() {{
+    if (( CURRENT == 2 )); then
+        compadd -- {subcommands}
+        return
+    fi
+    case "$words[2]" in
+{cases}    esac
+    _files -/
+}}
+
+compdef _typst_package_check typst-package-check
+"#,
+        subcommands = subcommand_names(),
+    )
+}
+
+fn fish() -> String {
+    let mut script = format!(
+        "complete -c typst-package-check -n __fish_use_subcommand -f -a '{}'\n",
+        subcommand_names()
+    );
+    for (name, flags) in SUBCOMMANDS {
+        for flag in *flags {
+            script += &format!(
+                "complete -c typst-package-check -n '__fish_seen_subcommand_from {name}' -l {}\n",
+                flag.trim_start_matches("--")
+            );
+        }
+    }
+    script += "complete -c typst-package-check -n '__fish_seen_subcommand_from check' -f \
+        -a '(typst-package-check completions packages 2>/dev/null)'\n";
+    script += &format!(
+        "complete -c typst-package-check -n '__fish_seen_subcommand_from explain' -f -a '{}'\n",
+        codes()
+    );
+    script += &format!(
+        "complete -c typst-package-check -n '__fish_seen_subcommand_from completions' -f -a '{}'\n",
+        SHELLS.join(" ")
+    );
+    script
+}
+
+fn powershell() -> String {
+    let quoted = |words: &[&str]| {
+        words
+            .iter()
+            .map(|word| format!("'{word}'"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut cases = String::new();
+    for (name, flags) in SUBCOMMANDS {
+        let mut words = quoted(flags);
+        match *name {
+            "check" => words = format!("@({words}) + @(typst-package-check completions packages)"),
+            "explain" => {
+                let codes: Vec<_> = EXPLANATIONS.iter().map(|e| e.code).collect();
+                words = format!("@({words}) + @({})", quoted(&codes))
+            }
+            "completions" => words = quoted(SHELLS),
+            _ => words = format!("@({words})"),
+        }
+        cases += &format!("        '{name}' {{ $candidates = {words} }}\n");
+    }
+
+    let subcommands: Vec<_> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    format!(
+        r#"Register-ArgumentCompleter -Native -CommandName typst-package-check -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})
+    $candidates = @()
+    if ($words.Count -eq 1 -or ($words.Count -eq 2 -and $wordToComplete)) {{
+        $candidates = @({subcommands})
+    }} else {{
+        switch ($words[1]) {{
+{cases}        }}
+    }}
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        subcommands = quoted(&subcommands),
+    )
+}
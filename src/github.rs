@@ -1,6 +1,9 @@
 use std::{
+    collections::{HashMap, VecDeque},
     ffi::OsStr,
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
 };
 
 use axum::{
@@ -12,13 +15,20 @@ use axum::{
     Router,
 };
 use codespan_reporting::{
-    diagnostic::{Diagnostic, Severity},
+    diagnostic::{Diagnostic, Label, Severity},
     files::Files,
 };
 use eyre::Context;
-use hook::{CheckRunPayload, PullRequestAction, PullRequestPayload};
+use hook::{
+    CheckRunPayload, DeliveryId, IssueCommentAction, IssueCommentPayload, PullRequestAction,
+    PullRequestPayload,
+};
 use jwt_simple::prelude::*;
-use pr::{AnyPullRequest, MinimalPullRequest, PullRequest, PullRequestUpdate};
+use parking_lot::Mutex;
+use pr::{
+    AnyPullRequest, Comment, MinimalPullRequest, PullRequest, PullRequestUpdate, ReviewComment,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
 use tracing::{debug, error, info, warn};
 use typst::syntax::{package::PackageSpec, FileId};
 
@@ -30,14 +40,27 @@ use api::{
 };
 
 mod api;
+mod config;
 pub mod git;
+mod suggestions;
+mod summary;
 
 use self::{
-    api::check::{Annotation, AnnotationLevel, CheckRunOutput, CheckSuite, CheckSuiteAction},
+    api::check::{
+        Annotation, AnnotationLevel, CheckRunConclusion, CheckRunOutput, CheckSuite,
+        CheckSuiteAction, MAX_ANNOTATIONS_PER_REQUEST,
+    },
     git::GitRepo,
     hook::{CheckSuitePayload, HookPayload},
 };
 
+/// Label for pull requests adding a new package.
+const LABEL_NEW: &str = "new";
+/// Label for pull requests adding a new version of an existing package.
+const LABEL_UPDATE: &str = "update";
+/// Label for pull requests in which errors were found.
+const LABEL_HAS_ERRORS: &str = "has-errors";
+
 /// Application configuration, read from .env file.
 #[derive(Clone)]
 struct AppState {
@@ -45,19 +68,88 @@ struct AppState {
     private_key: String,
     app_id: String,
     git_dir: String,
+    /// The root of the GitHub API.
+    api_url: String,
+    /// Comments starting with this command re-run the checks of a pull request.
+    check_command: String,
+    installation_tokens: InstallationTokenCache,
+    /// The login of the bot of the app, asked to GitHub the first time it is
+    /// needed.
+    bot_login: Arc<tokio::sync::OnceCell<String>>,
+    /// Whether check runs with warnings but no errors should fail, instead of
+    /// being neutral.
+    fail_on_warnings: bool,
+    /// The commit that is being checked for each package of each pull request.
+    in_flight_checks: InFlightChecks,
+    /// The repositories (with their owner) in which packages are checked.
+    /// Events from other repositories are ignored.
+    target_repos: Vec<String>,
+    /// The webhook deliveries that were recently handled, as GitHub sometimes
+    /// delivers the same event more than once.
+    recent_deliveries: RecentSet<String>,
+    /// The packages that were recently checked, with the commit and the pull
+    /// request they were checked for. Some events don't say which pull
+    /// request the commit is in: the checks they trigger don't update any, so
+    /// they don't prevent the pull request from being updated later.
+    recent_checks: RecentSet<RecentCheck>,
+    /// Pull requests adding more new packages than that get a warning.
+    max_new_packages: usize,
+    /// Pull requests adding more versions of a single package than that get
+    /// a warning.
+    max_versions_per_package: usize,
+}
+
+/// A set of the most recently inserted values, shared between threads.
+#[derive(Clone)]
+struct RecentSet<T>(Arc<Mutex<VecDeque<T>>>);
+
+impl<T: PartialEq> RecentSet<T> {
+    /// How many values are remembered.
+    const CAPACITY: usize = 1024;
+
+    /// Inserts a value, forgetting the oldest one if needed. Returns `false`
+    /// if the value was already in the set.
+    fn insert(&self, value: T) -> bool {
+        let mut values = self.0.lock();
+        if values.contains(&value) {
+            return false;
+        }
+        if values.len() >= Self::CAPACITY {
+            values.pop_front();
+        }
+        values.push_back(value);
+        true
+    }
+
+    /// Forgets a value, so that it can be inserted again.
+    fn remove(&self, value: &T) {
+        self.0.lock().retain(|v| v != value);
+    }
+}
+
+impl<T> Default for RecentSet<T> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
 }
 
+/// A commit, the pull request it was checked in, if known, and a package.
+type RecentCheck = (String, Option<usize>, PackageSpec);
+
+/// Commits being checked, by repository, pull request and package.
+type InFlightChecks = Arc<Mutex<HashMap<(String, usize, PackageSpec), String>>>;
+
 /// Runs an HTTP server to handle GitHub hooks
-pub async fn hook_server() {
-    let state = AppState {
-        webhook_secret: std::env::var("GITHUB_WEBHOOK_SECRET")
-            .expect("GITHUB_WEBHOOK_SECRET is not set.")
-            .into_bytes(),
-        private_key: std::env::var("GITHUB_PRIVATE_KEY")
-            .expect("GITHUB_PRIVATE_KEY is not set.")
-            .replace('&', "\n"),
-        app_id: std::env::var("GITHUB_APP_IDENTIFIER").expect("GITHUB_APP_IDENTIFIER is not set."),
-        git_dir: std::env::var("PACKAGES_DIR").expect("PACKAGES_DIR is not set."),
+pub async fn hook_server(port: u16) -> ExitCode {
+    let state = match AppState::try_read() {
+        Ok(state) => state,
+        Err(errors) => {
+            for e in errors {
+                error!("{}", e);
+            }
+            error!("The server is not correctly configured, see the README for details.");
+            return ExitCode::FAILURE;
+        }
     };
 
     GitRepo::open(Path::new(&state.git_dir[..]))
@@ -65,18 +157,55 @@ pub async fn hook_server() {
         .await
         .expect("Can't clone the packages repository");
 
-    let app = Router::new()
+    let app = router(state);
+
+    info!("Starting on port {port}…");
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+        .await
+        .unwrap_or_else(|_| panic!("Can't listen on 0.0.0.0:{port}"));
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .expect("Server error");
+
+    ExitCode::SUCCESS
+}
+
+/// All the routes of the webhook server.
+fn router(state: AppState) -> Router {
+    Router::new()
         .route("/", get(index))
+        .route("/health", get(health))
+        .route("/hook", post(github_hook::<GitHub<AuthJwt>>))
         .route("/github-hook", post(github_hook::<GitHub<AuthJwt>>))
         .route("/force-review/:install/:sha", get(force))
         .layer(tower_http::trace::TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state)
+}
 
-    info!("Starting…");
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:7878")
-        .await
-        .expect("Can't listen on 0.0.0.0:7878");
-    axum::serve(listener, app).await.expect("Server error");
+/// Resolves when the server is asked to stop, with Ctrl+C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Can't install the Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Can't install the SIGTERM handler")
+            .recv()
+            .await;
+    };
+    // There is no SIGTERM elsewhere, only Ctrl+C can stop the server.
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down…");
 }
 
 /// The page served on `/`, just to check that everything runs properly.
@@ -84,13 +213,18 @@ async fn index() -> &'static str {
     "typst-package-check is running"
 }
 
+/// Health check endpoint, for load balancers and container orchestrators.
+async fn health() -> StatusCode {
+    StatusCode::OK
+}
+
 async fn force(
     state: State<AppState>,
     api_client: GitHub,
     axum::extract::Path((install, pr)): axum::extract::Path<(String, usize)>,
 ) -> Result<&'static str, &'static str> {
     debug!("Force review for #{pr}");
-    let repository = Repository::new("typst/packages").map_err(|e| {
+    let repository = Repository::new(&state.target_repos[0]).map_err(|e| {
         error!("{}", e);
         "Invalid repository path"
     })?;
@@ -99,7 +233,7 @@ async fn force(
         id: str::parse(&install).map_err(|_| "Invalid installation ID")?,
     };
     let api_client = api_client
-        .auth_installation(&installation)
+        .auth_installation(&installation, &repository)
         .await
         .map_err(|e| {
             debug!("Failed to authenticate installation: {}", e);
@@ -119,13 +253,15 @@ async fn force(
     github_hook(
         state,
         api_client,
+        DeliveryId(None),
         HookPayload::CheckSuite(CheckSuitePayload {
-            action: CheckSuiteAction::Requested,
+            action: CheckSuiteAction::Rerequested,
             installation,
             check_suite: CheckSuite {
                 head_sha: sha,
                 pull_requests: vec![AnyPullRequest::Full(full_pr)],
             },
+            repository,
         }),
     )
     .await
@@ -137,27 +273,103 @@ async fn force(
     Ok("OK!")
 }
 
-/// The route to handle GitHub hooks. Mounted on `/github-hook`.
+/// The route to handle GitHub hooks. Mounted on `/hook`, and on `/github-hook`
+/// for compatibility with existing app configurations.
 async fn github_hook<G: GitHubAuth>(
     State(state): State<AppState>,
     api_client: G,
+    delivery: DeliveryId,
     payload: HookPayload,
 ) -> Result<(), WebError> {
+    let Some(checks) = checks_for_event(state, api_client, delivery, payload).await? else {
+        return Ok(());
+    };
+    tokio::spawn(async move {
+        if let Err(e) = checks.run().await {
+            warn!("Error in hook handler: {:#}", e)
+        }
+    });
+
+    Ok(())
+}
+
+/// Answers a hook event, and returns the checks it asks for, if any.
+///
+/// Only what is needed to know which commit to check is done here, the
+/// checks themselves take longer and are run once GitHub got a response.
+async fn checks_for_event<G: GitHubAuth>(
+    state: AppState,
+    api_client: G,
+    delivery: DeliveryId,
+    payload: HookPayload,
+) -> Result<Option<CommitChecks>, WebError> {
     debug!("GitHub hook was triggered");
-    let api_client = api_client.auth_installation(&payload).await?;
-    debug!("Successfully authenticated application");
+    let Some(delivery) = delivery.0 else {
+        return checks_for_payload(state, api_client, payload).await;
+    };
+    if !state.recent_deliveries.insert(delivery.clone()) {
+        debug!("Delivery {} was already handled", delivery);
+        return Ok(None);
+    }
 
-    let repository = Repository::new("typst/packages").map_err(|e| {
-        error!("Invalid repository path: {}", e);
-        WebError::UnexpectedEvent
-    })?;
+    // The event is handled again if GitHub delivers it again after a
+    // failure.
+    match checks_for_payload(state.clone(), api_client, payload).await {
+        Ok(checks) => Ok(checks.map(|checks| CommitChecks {
+            delivery: Some(delivery),
+            ..checks
+        })),
+        Err(e) => {
+            state.recent_deliveries.remove(&delivery);
+            Err(e)
+        }
+    }
+}
+
+/// Answers a hook event, regardless of whether it was already delivered.
+async fn checks_for_payload<G: GitHubAuth>(
+    state: AppState,
+    api_client: G,
+    payload: HookPayload,
+) -> Result<Option<CommitChecks>, WebError> {
+    let repository = match payload.repository() {
+        Some(repository) => repository.clone(),
+        None => Repository::new(&state.target_repos[0]).map_err(|e| {
+            error!("Invalid repository path: {}", e);
+            WebError::UnexpectedEvent
+        })?,
+    };
+    if !state
+        .target_repos
+        .iter()
+        .any(|r| r == repository.full_name())
+    {
+        debug!("Ignoring event from {}", repository.full_name());
+        return Ok(None);
+    }
+
+    // Comments of the app are recognized by their author.
+    let bot_login = state
+        .bot_login
+        .get_or_try_init(|| api_client.bot_login())
+        .await?
+        .clone();
+    let api_client = api_client.auth_installation(&payload, &repository).await?;
+    debug!("Successfully authenticated application");
 
-    let (head_sha, pr, previous_check_run) = match payload {
+    // `rerun` is set when the checks were explicitly asked for, in which case
+    // they run even if the same commit was already checked.
+    let (head_sha, pr, previous_check_run, rerun) = match payload {
         HookPayload::CheckSuite(CheckSuitePayload {
-            action: CheckSuiteAction::Requested | CheckSuiteAction::Rerequested,
+            action: action @ (CheckSuiteAction::Requested | CheckSuiteAction::Rerequested),
             mut check_suite,
             ..
-        }) => (check_suite.head_sha, check_suite.pull_requests.pop(), None),
+        }) => (
+            check_suite.head_sha,
+            check_suite.pull_requests.pop(),
+            None,
+            matches!(action, CheckSuiteAction::Rerequested),
+        ),
         HookPayload::CheckRun(CheckRunPayload {
             action: CheckRunAction::Rerequested,
             mut check_run,
@@ -166,21 +378,72 @@ async fn github_hook<G: GitHubAuth>(
             check_run.check_suite.head_sha.clone(),
             check_run.check_suite.pull_requests.pop(),
             Some(check_run),
+            true,
         ),
         HookPayload::PullRequest(PullRequestPayload {
-            action: PullRequestAction::Opened | PullRequestAction::Synchronize,
+            action:
+                action @ (PullRequestAction::Opened
+                | PullRequestAction::Synchronize
+                | PullRequestAction::Reopened
+                | PullRequestAction::ReadyForReview),
             pull_request,
             ..
         }) => (
             pull_request.head.sha.clone(),
             Some(AnyPullRequest::Full(pull_request)),
             None,
+            // The pull request itself needs to be updated, even if its
+            // commits were already checked.
+            matches!(
+                action,
+                PullRequestAction::Reopened | PullRequestAction::ReadyForReview
+            ),
         ),
+        HookPayload::IssueComment(IssueCommentPayload {
+            action: IssueCommentAction::Created,
+            issue,
+            comment,
+            ..
+        }) if issue.pull_request.is_some()
+            && is_check_command(&comment.body, &state.check_command) =>
+        {
+            let permission = api_client
+                .user_permission(repository.owner(), repository.name(), &comment.user.login)
+                .await?;
+            if !permission.can_write() {
+                debug!(
+                    "{} asked for checks to be run again, but is not allowed to",
+                    comment.user.login
+                );
+                return Ok(None);
+            }
+
+            if let Err(e) = api_client
+                .react_to_comment(repository.owner(), repository.name(), comment.id, "+1")
+                .await
+            {
+                warn!("Failed to react to comment: {:?}", e);
+            }
+
+            let pr = MinimalPullRequest {
+                number: issue.number,
+            }
+            .get_full(&api_client, repository.owner(), repository.name())
+            .await?;
+            (
+                pr.head.sha.clone(),
+                Some(AnyPullRequest::Full(pr)),
+                None,
+                true,
+            )
+        }
         HookPayload::CheckRun(_)
+        | HookPayload::PullRequest(_)
+        | HookPayload::IssueComment(_)
         | HookPayload::CheckSuite(CheckSuitePayload {
             action: CheckSuiteAction::Completed,
             ..
-        }) => return Ok(()),
+        }) => return Ok(None),
         other => {
             debug!("Unexpected payload: {:?}", other);
             return Err(WebError::UnexpectedEvent);
@@ -204,405 +467,2451 @@ async fn github_hook<G: GitHubAuth>(
             String::new()
         }
     );
-    tokio::spawn(async move {
-        async fn inner(
-            state: AppState,
-            head_sha: String,
-            api_client: GitHub<AuthInstallation>,
-            repository: Repository,
-            previous_check_run: Option<CheckRun>,
-            pr: Option<PullRequest>,
-        ) -> eyre::Result<()> {
-            let git_repo = GitRepo::open(Path::new(&state.git_dir));
-            git_repo.pull_main().await?;
-            git_repo.fetch_commit(&head_sha).await?;
-            let touched_files = git_repo.files_touched_by(&head_sha).await?;
-
-            let mut touches_outside_of_packages = false;
-
-            let touched_packages = touched_files
-                .into_iter()
-                .filter_map(|line| {
-                    let mut components = line.components();
-                    if components.next()?.as_os_str() != OsStr::new("packages") {
-                        touches_outside_of_packages = true;
-                        return None;
-                    }
+    Ok(Some(CommitChecks {
+        state,
+        delivery: None,
+        bot_login,
+        head_sha,
+        api_client,
+        repository,
+        previous_check_run,
+        pr,
+        rerun,
+    }))
+}
 
-                    let namespace = components.next()?.as_os_str().to_str()?.into();
-                    let name = components.next()?.as_os_str().to_str()?.into();
-                    let version = components.next()?.as_os_str().to_str()?.parse().ok()?;
-                    Some(PackageSpec {
-                        namespace,
-                        name,
-                        version,
-                    })
-                })
-                .collect::<HashSet<_>>();
-
-            if let Some(pr) = &pr {
-                // Update labels
-                let mut has_new_packages = false;
-                let mut has_updated_packages = false;
-                for package in &touched_packages {
-                    if git_repo
-                        .has_previous_version(package)
-                        .await
-                        .unwrap_or(false)
-                    {
-                        has_updated_packages = true;
-                    } else {
-                        has_new_packages = true;
-                    }
-                }
-                let mut labels = Vec::new();
-                if has_new_packages {
-                    labels.push("new".to_owned())
+/// The checks of the packages touched by a commit.
+struct CommitChecks {
+    state: AppState,
+    /// The delivery of the event that asked for the checks, if known.
+    delivery: Option<String>,
+    bot_login: String,
+    head_sha: String,
+    api_client: GitHub<AuthInstallation>,
+    repository: Repository,
+    /// The check run that was re-requested, if only its package should be
+    /// checked again.
+    previous_check_run: Option<CheckRun>,
+    pr: Option<PullRequest>,
+    /// Whether the checks were explicitly asked for, in which case they run
+    /// even if the same commit was already checked.
+    rerun: bool,
+}
+
+impl CommitChecks {
+    /// Runs the checks.
+    ///
+    /// If they fail, the event and the packages they were for are forgotten,
+    /// so that they are checked again if the event is delivered again.
+    async fn run(self) -> eyre::Result<()> {
+        let state = self.state.clone();
+        let delivery = self.delivery.clone();
+        let mut recorded = Vec::new();
+        let res = self.check(&mut recorded).await;
+        if res.is_err() {
+            if let Some(delivery) = &delivery {
+                state.recent_deliveries.remove(delivery);
+            }
+            for key in &recorded {
+                state.recent_checks.remove(key);
+            }
+        }
+        res
+    }
+
+    /// Runs the checks, and records the packages they are for in
+    /// [`AppState::recent_checks`].
+    async fn check(self, recorded: &mut Vec<RecentCheck>) -> eyre::Result<()> {
+        let CommitChecks {
+            state,
+            delivery: _,
+            bot_login,
+            head_sha,
+            api_client,
+            repository,
+            previous_check_run,
+            pr,
+            rerun,
+        } = self;
+
+        let git_repo = GitRepo::open(Path::new(&state.git_dir));
+        git_repo.pull_main().await?;
+        git_repo.fetch_commit(&head_sha).await?;
+        let touched_files = git_repo.files_touched_by(&head_sha).await?;
+
+        let mut touches_outside_of_packages = false;
+
+        // Packages for which every touched file was deleted.
+        let mut removed_packages = HashSet::new();
+        let mut partially_removed_packages = HashSet::new();
+        let touched_packages = touched_files
+            .into_iter()
+            .filter_map(|file| {
+                let mut components = file.path.components();
+                if components.next()?.as_os_str() != OsStr::new("packages") {
+                    touches_outside_of_packages = true;
+                    return None;
                 }
-                if has_updated_packages {
-                    labels.push("update".to_owned());
+
+                let namespace = components.next()?.as_os_str().to_str()?.into();
+                let name = components.next()?.as_os_str().to_str()?.into();
+                let version = components.next()?.as_os_str().to_str()?.parse().ok()?;
+                let spec = PackageSpec {
+                    namespace,
+                    name,
+                    version,
+                };
+                if file.deleted {
+                    if !partially_removed_packages.contains(&spec) {
+                        removed_packages.insert(spec.clone());
+                    }
+                } else {
+                    removed_packages.remove(&spec);
+                    partially_removed_packages.insert(spec.clone());
                 }
+                Some(spec)
+            })
+            .collect::<HashSet<_>>();
+
+        let mut has_new_packages = false;
+        let mut has_updated_packages = false;
+        let mut new_packages = HashSet::new();
+        for package in &touched_packages {
+            if git_repo
+                .has_previous_version(package)
+                .await
+                .unwrap_or(false)
+            {
+                has_updated_packages = true;
+            } else {
+                has_new_packages = true;
+                new_packages.insert((&package.namespace, &package.name));
+            }
+        }
 
-                // Update checks in PR body if needed
-                let mut body_changed = false;
-                let new_body = pr
-                    .body
-                    .lines()
-                    .map(|l| {
-                        let line = l.trim();
-                        if line.starts_with("-") {
-                            let marked = line.contains("[x]");
-                            if line.ends_with("a new package") {
-                                body_changed |= marked != has_new_packages;
-                                if has_new_packages {
-                                    return "- [x] a new package";
-                                } else {
-                                    return "- [ ] a new package";
-                                }
+        // Draft pull requests are still checked, but they are not
+        // modified, and nobody is notified until they are ready.
+        if let Some(pr) = pr.as_ref().filter(|pr| !pr.draft) {
+            // Update checks in PR body if needed
+            let mut body_changed = false;
+            let new_body = pr
+                .body
+                .lines()
+                .map(|l| {
+                    let line = l.trim();
+                    if line.starts_with("-") {
+                        let marked = line.contains("[x]");
+                        if line.ends_with("a new package") {
+                            body_changed |= marked != has_new_packages;
+                            if has_new_packages {
+                                return "- [x] a new package";
+                            } else {
+                                return "- [ ] a new package";
                             }
+                        }
 
-                            if line.ends_with("an update for a package") {
-                                body_changed |= marked != has_updated_packages;
-                                if has_updated_packages {
-                                    return "- [x] an update for a package";
-                                } else {
-                                    return "- [ ] an update for a package";
-                                }
+                        if line.ends_with("an update for a package") {
+                            body_changed |= marked != has_updated_packages;
+                            if has_updated_packages {
+                                return "- [x] an update for a package";
+                            } else {
+                                return "- [ ] an update for a package";
                             }
                         }
+                    }
 
-                        l
-                    })
-                    .fold(String::with_capacity(pr.body.len()), |body, line| {
-                        body + "\n" + line
-                    });
-                let body = if body_changed { Some(new_body) } else { None };
-
-                // Update title
-                let mut package_names = touched_packages
-                    .iter()
-                    .map(|p| format!("{}:{}", p.name, p.version))
-                    .collect::<Vec<_>>();
-                package_names.sort();
-                let last_package = package_names.pop();
-                let penultimate_package = package_names.pop();
-                let expected_pr_title = if let Some((penultimate_package, last_package)) =
-                    penultimate_package.as_ref().zip(last_package.as_ref())
-                {
-                    package_names.push(format!("{} and {}", penultimate_package, last_package));
-                    Some(package_names.join(", "))
-                } else {
-                    last_package
-                };
+                    l
+                })
+                .fold(String::with_capacity(pr.body.len()), |body, line| {
+                    body + "\n" + line
+                });
+            let body = if body_changed { Some(new_body) } else { None };
 
-                // Actually update the PR, if needed
-                if let Some(expected_pr_title) = expected_pr_title {
-                    if pr.title != expected_pr_title || !labels.is_empty() || body.is_some() {
-                        api_client
-                            .update_pull_request(
-                                repository.owner(),
-                                repository.name(),
-                                pr.number,
-                                PullRequestUpdate {
-                                    title: expected_pr_title,
-                                    labels,
-                                    body,
-                                },
-                            )
-                            .await
-                            .context("Failed to update pull request")?;
-                    }
-                }
-            }
+            // Update title
+            let mut package_names = touched_packages
+                .iter()
+                .map(|p| format!("{}:{}", p.name, p.version))
+                .collect::<Vec<_>>();
+            package_names.sort();
+            let last_package = package_names.pop();
+            let penultimate_package = package_names.pop();
+            let expected_pr_title = if let Some((penultimate_package, last_package)) =
+                penultimate_package.as_ref().zip(last_package.as_ref())
+            {
+                package_names.push(format!("{} and {}", penultimate_package, last_package));
+                Some(package_names.join(", "))
+            } else {
+                last_package
+            };
 
-            for ref package in touched_packages {
-                let check_run_name = format!(
-                    "@{}/{}:{}",
-                    package.namespace, package.name, package.version
-                );
+            let title = expected_pr_title
+                .map(|packages| pr_title(&pr.title, &packages))
+                .filter(|title| *title != pr.title);
 
-                let check_run = if let Some(previous) = previous_check_run
-                    .as_ref()
-                    .filter(|p| p.name == check_run_name)
-                {
-                    previous.clone().without_suite()
-                } else {
+            // Actually update the PR, if needed
+            if title.is_some() || body.is_some() {
+                skip_if_forbidden(
                     api_client
-                        .create_check_run(
+                        .update_pull_request(
                             repository.owner(),
                             repository.name(),
-                            check_run_name,
-                            &head_sha,
+                            pr.number,
+                            PullRequestUpdate { title, body },
                         )
-                        .await
-                        .context("Failed to create a new check run")?
-                        .without_suite()
-                };
+                        .await,
+                    "Failed to update pull request",
+                )?;
+            }
+        }
 
-                if touches_outside_of_packages {
-                    api_client.update_check_run(
+        // When a single check run is re-run, only check the corresponding
+        // package.
+        let rerun_package = previous_check_run
+            .as_ref()
+            .and_then(|run| check_run_package(&run.name));
+
+        if previous_check_run.is_none() {
+            if let Some(warning) = pr_size_warning(
+                &touched_packages,
+                new_packages.len(),
+                state.max_new_packages,
+                state.max_versions_per_package,
+            ) {
+                let check_run = api_client
+                    .create_check_run(
+                        repository.owner(),
+                        repository.name(),
+                        "Pull request size".to_owned(),
+                        &head_sha,
+                    )
+                    .await
+                    .context("Failed to create the pull request size check run")?;
+                api_client
+                    .update_check_run(
                         repository.owner(),
                         repository.name(),
                         check_run.id,
-                        false,
+                        CheckRunConclusion::Neutral,
                         CheckRunOutput {
-                            title: "This PR does too many things",
-                            summary: "A PR should either change packages/, or the rest of the repository, but not both.",
+                            title: "This pull request is large",
+                            summary: &warning,
                             annotations: &[],
                         },
-                    ).await
-                    .context("Failed to cancel a check run because the branch does too many things")?;
-                    continue;
-                }
-
-                let checkout_dir = format!("checkout-{}", head_sha);
-                git_repo
-                    .checkout_commit(&head_sha, &checkout_dir)
+                    )
                     .await
-                    .context("Failed to checkout commit")?;
-
-                // Check that the author of this PR is the same as the one of
-                // the previous version.
-                if let Some(current_pr) = &pr {
-                    debug!("There is a current PR");
-                    if let Some(previous_commit) =
-                        check::authors::commit_for_previous_version(package)
-                    {
-                        debug!("Found previous commit: {previous_commit}");
-                        if let Ok(Some(previous_pr)) = api_client
-                            .prs_for_commit(repository.owner(), repository.name(), previous_commit)
-                            .await
-                            .map(|prs| prs.into_iter().next())
-                        {
-                            debug!(
-                                "Found previous PR: #{} (author: {})",
-                                previous_pr.number, previous_pr.user.login
-                            );
-                            if previous_pr.user.login != current_pr.user.login {
-                                if let Err(e) = api_client
-                                    .post_pr_comment(
-                                        repository.owner(),
-                                        repository.name(),
-                                        current_pr.number,
-                                        format!(
-                                            "@{} You released {}:{}, so you probably \
-                                            want to have a look at this pull request. \
-                                            If you want this update to be merged, \
-                                            please leave a comment stating so. \
-                                            Without your permission, the pull request \
-                                            will not be merged.",
-                                            previous_pr.user.login,
-                                            package.name,
-                                            package.previous_version()
-                                                .expect("If there is no previous version, this branch should not be reached")
-                                                .version
-                                        ),
-                                    )
-                                    .await
-                                    {
-                                        warn!("Error while posting PR comment: {:?}", e)
-                                    }
-                            }
-                        }
-                    }
-                }
+                    .context("Failed to report the size of the pull request")?;
+            }
+        }
 
-                let (world, diags) = match check::all_checks(
-                    Some(package),
-                    PathBuf::new()
-                        .join(&checkout_dir)
-                        .join("packages")
-                        .join(package.namespace.as_str())
-                        .join(package.name.as_str())
-                        .join(package.version.to_string()),
-                    false,
-                )
-                .await
-                {
-                    Ok(x) => x,
-                    Err(e) => {
-                        api_client
-                            .update_check_run(
-                                repository.owner(),
-                                repository.name(),
-                                check_run.id,
-                                false,
-                                CheckRunOutput {
-                                    title: "Fatal error",
-                                    summary: &format!(
-                                        "The following error was encountered:\n\n{}",
-                                        e
-                                    ),
-                                    annotations: &[],
-                                },
-                            )
-                            .await
-                            .context("Failed to report fatal error")?;
-                        return Err(e);
-                    }
-                };
+        // Check runs are all created before any check starts, so that
+        // they all appear as queued on the pull request.
+        let mut to_check = Vec::new();
+        let mut already_checked = false;
+        for package in touched_packages
+            .iter()
+            .filter(|p| rerun_package.as_ref().map_or(true, |only| only == *p))
+        {
+            // Different events are often sent for the same commit.
+            let key = (
+                head_sha.clone(),
+                pr.as_ref().map(|pr| pr.number),
+                package.clone(),
+            );
+            if state.recent_checks.insert(key.clone()) {
+                recorded.push(key);
+            } else if !rerun {
+                debug!("{} was already checked for {}", package, head_sha);
+                already_checked = true;
+                continue;
+            }
+
+            let check_run_name = check_run_name(package);
+
+            let check_run = if let Some(previous) = previous_check_run
+                .as_ref()
+                .filter(|p| p.name == check_run_name)
+            {
+                previous.clone().without_suite()
+            } else {
+                api_client
+                    .create_check_run(
+                        repository.owner(),
+                        repository.name(),
+                        check_run_name,
+                        &head_sha,
+                    )
+                    .await
+                    .context("Failed to create a new check run")?
+                    .without_suite()
+            };
 
-                let plural = |n| if n == 1 { "" } else { "s" };
+            if touches_outside_of_packages {
+                api_client.update_check_run(
+                    repository.owner(),
+                    repository.name(),
+                    check_run.id,
+                    CheckRunConclusion::Failure,
+                    CheckRunOutput {
+                        title: "This PR does too many things",
+                        summary: "A PR should either change packages/, or the rest of the repository, but not both.",
+                        annotations: &[],
+                    },
+                ).await
+                .context("Failed to cancel a check run because the branch does too many things")?;
+                continue;
+            }
 
+            if removed_packages.contains(package) {
+                // The main branch was pulled above: if the package is
+                // there, it was already published.
+                let published = git_repo.package_dir(package).exists();
                 api_client
                     .update_check_run(
                         repository.owner(),
                         repository.name(),
                         check_run.id,
-                        diags.errors().is_empty() && diags.warnings().is_empty(),
+                        if published {
+                            CheckRunConclusion::Failure
+                        } else {
+                            CheckRunConclusion::Neutral
+                        },
                         CheckRunOutput {
-                            title: &if !diags.errors().is_empty() {
-                                if diags.warnings().is_empty() {
-                                    format!(
-                                        "{} error{}",
-                                        diags.errors().len(),
-                                        plural(diags.errors().len())
-                                    )
-                                } else {
-                                    format!(
-                                        "{} error{}, {} warning{}",
-                                        diags.errors().len(),
-                                        plural(diags.errors().len()),
-                                        diags.warnings().len(),
-                                        plural(diags.warnings().len())
-                                    )
-                                }
-                            } else if diags.warnings().is_empty() {
-                                "All good!".to_owned()
+                            title: "This version was removed",
+                            summary: if published {
+                                "This pull request removes a version that was already \
+                                published. Published packages can't be modified or \
+                                removed, as other documents may depend on them."
                             } else {
-                                format!(
-                                    "{} warning{}",
-                                    diags.warnings().len(),
-                                    plural(diags.warnings().len())
-                                )
+                                "This pull request removes this version, \
+                                so there is nothing to check."
                             },
-                            summary: &format!(
-                                "Our bots have automatically run some checks on your packages. \
-                                They found {} error{} and {} warning{}.\n\n\
-                                Warnings are suggestions, your package can still be accepted even \
-                                if you prefer not to fix them.\n\n\
-                                A human being will soon review your package, too.",
-                                diags.errors().len(),
-                                plural(diags.errors().len()),
-                                diags.warnings().len(),
-                                plural(diags.warnings().len()),
-                            ),
-                            annotations: &diags
-                                .errors()
-                                .iter()
-                                .chain(diags.warnings())
-                                .filter_map(|diag| diagnostic_to_annotation(&world, package, diag))
-                                .take(50)
-                                .collect::<Vec<_>>(),
+                            annotations: &[],
                         },
                     )
                     .await
-                    .context("Failed to send report")?;
-
-                tokio::fs::remove_dir_all(checkout_dir).await?;
+                    .context("Failed to report a removed package")?;
+                continue;
             }
 
-            Ok(())
+            to_check.push((package.clone(), check_run));
         }
 
-        if let Err(e) = inner(
-            state,
-            head_sha,
-            api_client,
-            repository,
-            previous_check_run,
-            pr,
-        )
-        .await
-        {
-            warn!("Error in hook handler: {:#}", e)
+        let mut reports = Vec::new();
+        let mut review_suggestions = Vec::new();
+        let mut superseded = false;
+        let mut failed = 0;
+        if !to_check.is_empty() {
+            // All packages are checked from a single checkout of the
+            // pull request.
+            let checkout_dir = format!("checkout-{}", head_sha);
+            git_repo
+                .checkout_commit(&head_sha, &checkout_dir)
+                .await
+                .context("Failed to checkout commit")?;
+
+            let ctx = Arc::new(PackageCheckContext {
+                state,
+                bot_login: bot_login.clone(),
+                api_client: api_client.clone(),
+                repository: repository.clone(),
+                pr: pr.clone(),
+                head_sha: head_sha.clone(),
+                checkout_dir,
+            });
+            let permits = Arc::new(Semaphore::new(check::jobs().min(MAX_CONCURRENT_CHECKS)));
+            let mut tasks = JoinSet::new();
+            for (package, check_run) in to_check {
+                let ctx = ctx.clone();
+                let permits = permits.clone();
+                tasks.spawn(async move {
+                    let _permit = permits.acquire_owned().await?;
+                    let key = ctx.in_flight_key(&package);
+                    if let Some(key) = &key {
+                        ctx.state
+                            .in_flight_checks
+                            .lock()
+                            .insert(key.clone(), ctx.head_sha.clone());
+                    }
+
+                    let res = check_package(&ctx, &package, check_run)
+                        .await
+                        .with_context(|| format!("Failed to check {}", package));
+
+                    if let Some(key) = &key {
+                        let mut in_flight = ctx.state.in_flight_checks.lock();
+                        if in_flight.get(key) == Some(&ctx.head_sha) {
+                            in_flight.remove(key);
+                        }
+                    }
+                    res
+                });
+            }
+
+            // A failure in one package doesn't prevent the other ones
+            // from being reported.
+            while let Some(result) = tasks.join_next().await {
+                match result {
+                    Ok(Ok(Some((report, suggestions)))) => {
+                        reports.push(report);
+                        review_suggestions.extend(suggestions);
+                    }
+                    Ok(Ok(None)) => superseded = true,
+                    Ok(Err(e)) => {
+                        warn!("{:#}", e);
+                        failed += 1;
+                    }
+                    Err(e) => {
+                        warn!("A package check panicked: {}", e);
+                        failed += 1;
+                    }
+                }
+            }
+
+            tokio::fs::remove_dir_all(&ctx.checkout_dir).await?;
         }
-    });
 
-    Ok(())
-}
+        // The other packages are still reported, but the event is not
+        // considered as handled.
+        let outcome = match failed {
+            0 => Ok(()),
+            _ => Err(eyre::eyre!("Failed to check {} package(s)", failed)),
+        };
 
-fn diagnostic_to_annotation(
-    world: &SystemWorld,
-    package: &PackageSpec,
-    diag: &Diagnostic<FileId>,
-) -> Option<Annotation> {
-    let label = diag.labels.first()?;
-    let start_line = world.line_index(label.file_id, label.range.start).ok()?;
-    let end_line = world.line_index(label.file_id, label.range.end).ok()?;
-    let (start_column, end_column) = if start_line == end_line {
-        let start = world
-            .column_number(label.file_id, start_line, label.range.start)
-            .ok();
-        let end = world
-            .column_number(label.file_id, start_line, label.range.end)
-            .ok();
-        (start, end)
-    } else {
-        (None, None)
-    };
-    Some(Annotation {
-        path: Path::new("packages")
-            .join(package.namespace.to_string())
-            .join(package.name.to_string())
-            .join(package.version.to_string())
-            .join(label.file_id.vpath().as_rootless_path())
-            .to_str()?
-            .to_owned(),
-        // Lines are 1-indexed on GitHub but not for codespan
-        start_line: start_line + 1,
-        end_line: end_line + 1,
-        start_column,
-        end_column,
-        annotation_level: if diag.severity == Severity::Warning {
-            AnnotationLevel::Warning
-        } else {
-            AnnotationLevel::Failure
-        },
-        message: diag.message.clone(),
-    })
-}
+        // The pull request will be updated by the checks of the newer
+        // commits, or was already updated by the previous checks of the
+        // same commit.
+        if superseded || already_checked {
+            debug!("Checks of {} were superseded or already done", head_sha);
+            return outcome;
+        }
 
-#[derive(Debug)]
-enum WebError {
-    #[allow(dead_code)]
-    Api(ApiError),
-    UnexpectedEvent,
-}
+        if let Some(pr) = pr.as_ref().filter(|pr| !pr.draft) {
+            if let Err(e) = suggestions::post(
+                &api_client,
+                &repository,
+                pr.number,
+                &head_sha,
+                review_suggestions,
+            )
+            .await
+            {
+                warn!("Failed to post suggestions: {}", e);
+            }
+        }
 
-impl IntoResponse for WebError {
-    fn into_response(self) -> axum::response::Response {
-        debug!("Web error: {:?}", &self);
+        // Update labels, now that we know if there are errors.
+        if let Some(pr) = pr.as_ref().filter(|pr| !pr.draft) {
+            let mut labels = Vec::new();
+            if has_new_packages {
+                labels.push(LABEL_NEW);
+            }
+            if has_updated_packages {
+                labels.push(LABEL_UPDATE);
+            }
+            if reports.iter().any(|r| r.errors > 0) {
+                labels.push(LABEL_HAS_ERRORS);
+            }
 
-        Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::from(format!("{:?}", self)))
-            .expect("Can't build error response")
-    }
-}
+            let has_label = |label: &str| pr.labels.iter().any(|l| l.name == label);
+            let to_add = labels
+                .iter()
+                .filter(|&&label| !has_label(label))
+                .map(|label| label.to_string())
+                .collect::<Vec<_>>();
+            if !to_add.is_empty() {
+                skip_if_forbidden(
+                    api_client
+                        .add_labels(repository.owner(), repository.name(), pr.number, to_add)
+                        .await,
+                    "Failed to add labels",
+                )?;
+            }
 
-impl From<ApiError> for WebError {
+            // Only remove labels managed by this tool.
+            for label in [LABEL_NEW, LABEL_UPDATE, LABEL_HAS_ERRORS] {
+                if has_label(label) && !labels.contains(&label) {
+                    let res = api_client
+                        .remove_label(repository.owner(), repository.name(), pr.number, label)
+                        .await;
+                    // The label may have been removed in the meantime.
+                    if !res.as_ref().is_err_and(|e| e.is_not_found()) {
+                        skip_if_forbidden(res, "Failed to remove label")?;
+                    }
+                }
+            }
+        }
+
+        // Draft pull requests are not ready for review yet, their authors
+        // can already look at the check runs.
+        if let Some(pr) = pr.as_ref().filter(|pr| !pr.draft && !reports.is_empty()) {
+            let body = summary::render_comment(
+                &mut reports,
+                &format!("{}/{}", repository.owner(), repository.name()),
+                &head_sha,
+            );
+            let previous_comment = api_client
+                .list_pr_comments(repository.owner(), repository.name(), pr.number)
+                .await
+                .context("Failed to list pull request comments")?
+                .into_iter()
+                .find(|c| c.user.login == bot_login && c.body.starts_with(summary::MARKER));
+            if let Some(previous_comment) = previous_comment {
+                if previous_comment.body != body {
+                    skip_if_forbidden(
+                        api_client
+                            .update_comment(
+                                repository.owner(),
+                                repository.name(),
+                                previous_comment.id,
+                                body,
+                            )
+                            .await,
+                        "Failed to update summary comment",
+                    )?;
+                }
+            } else {
+                skip_if_forbidden(
+                    api_client
+                        .post_pr_comment(repository.owner(), repository.name(), pr.number, body)
+                        .await,
+                    "Failed to post summary comment",
+                )?;
+            }
+        }
+
+        outcome
+    }
+}
+
+/// How many packages of a single pull request are checked at the same time.
+const MAX_CONCURRENT_CHECKS: usize = 2;
+
+/// Everything needed to check a package of a pull request, shared by all the
+/// packages of that pull request.
+struct PackageCheckContext {
+    state: AppState,
+    bot_login: String,
+    api_client: GitHub<AuthInstallation>,
+    repository: Repository,
+    pr: Option<PullRequest>,
+    /// The commit that is checked.
+    head_sha: String,
+    /// Where the head of the pull request is checked out.
+    checkout_dir: String,
+}
+
+impl PackageCheckContext {
+    /// The key of the checks of a package in [`AppState::in_flight_checks`].
+    fn in_flight_key(&self, package: &PackageSpec) -> Option<(String, usize, PackageSpec)> {
+        let pr = self.pr.as_ref()?;
+        Some((
+            format!("{}/{}", self.repository.owner(), self.repository.name()),
+            pr.number,
+            package.clone(),
+        ))
+    }
+
+    /// Whether new commits were pushed to the pull request since the checks of
+    /// a package started, in which case their results are outdated.
+    async fn is_superseded(&self, package: &PackageSpec) -> bool {
+        let Some(key) = self.in_flight_key(package) else {
+            return false;
+        };
+        if self.state.in_flight_checks.lock().get(&key) != Some(&self.head_sha) {
+            return true;
+        }
+
+        // Another instance of the app may be checking the newer commits.
+        let pr = MinimalPullRequest { number: key.1 };
+        match pr
+            .get_full(
+                &self.api_client,
+                self.repository.owner(),
+                self.repository.name(),
+            )
+            .await
+        {
+            Ok(pr) => pr.head.sha != self.head_sha,
+            Err(e) => {
+                warn!("Failed to fetch the head of the pull request: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Checks a single package and reports the results to its check run.
+async fn check_package(
+    ctx: &PackageCheckContext,
+    package: &PackageSpec,
+    check_run: CheckRun<()>,
+) -> eyre::Result<Option<(summary::PackageReport, Vec<ReviewComment>)>> {
+    let PackageCheckContext {
+        state,
+        bot_login,
+        api_client,
+        repository,
+        pr,
+        checkout_dir,
+        ..
+    } = ctx;
+
+    // Check that the author of this PR is the same as the one of
+    // the previous version.
+    if let Some(current_pr) = pr.as_ref().filter(|pr| !pr.draft) {
+        debug!("There is a current PR");
+        // Listing the versions of the package means reading a directory
+        // with possibly many entries: only do it once.
+        let previous_version = package.previous_version();
+        if let Some((previous_version, previous_commit)) = previous_version
+            .as_ref()
+            .and_then(|previous| Some((previous, check::authors::commit_for_version(previous)?)))
+        {
+            debug!("Found previous commit: {previous_commit}");
+            // GitHub lists every pull request containing this commit, even
+            // the ones that were closed without being merged: only the one
+            // that actually published the previous version is relevant.
+            let previous_pr = async {
+                let default_branch = api_client.default_branch(repository).await?;
+                api_client
+                    .prs_for_commit(
+                        repository.owner(),
+                        repository.name(),
+                        previous_commit.clone(),
+                    )
+                    .await
+                    .map(|prs| publishing_pr(prs, &previous_commit, &default_branch))
+            };
+            if let Ok(Some(previous_pr)) = previous_pr.await {
+                debug!(
+                    "Found previous PR: #{} (author: {})",
+                    previous_pr.number, previous_pr.user.login
+                );
+                // Only notify the previous author once, and not
+                // at all if they already commented.
+                let marker = format!(
+                    "<!-- package-check:author-ping:@{} -->",
+                    previous_pr.user.login
+                );
+                let same_author = previous_pr.user.login == current_pr.user.login;
+                let already_notified = same_author
+                    || api_client
+                        .list_pr_comments(repository.owner(), repository.name(), current_pr.number)
+                        .await
+                        .map(|comments| {
+                            previous_author_was_notified(
+                                &comments,
+                                bot_login,
+                                &previous_pr.user.login,
+                                &marker,
+                            )
+                        })
+                        .unwrap_or(false);
+                if !already_notified {
+                    if let Err(e) = api_client
+                        .post_pr_comment(
+                            repository.owner(),
+                            repository.name(),
+                            current_pr.number,
+                            format!(
+                                "@{} You released {}:{}, so you probably \
+                                want to have a look at this pull request. \
+                                If you want this update to be merged, \
+                                please leave a comment stating so. \
+                                Without your permission, the pull request \
+                                will not be merged.\n\n{}",
+                                previous_pr.user.login,
+                                package.name,
+                                previous_version.version,
+                                marker,
+                            ),
+                        )
+                        .await
+                    {
+                        warn!("Error while posting PR comment: {:?}", e)
+                    }
+                }
+            }
+        }
+    }
+
+    let (world, diags) = match check::all_checks(
+        Some(package),
+        PathBuf::new()
+            .join(&checkout_dir)
+            .join("packages")
+            .join(package.namespace.as_str())
+            .join(package.name.as_str())
+            .join(package.version.to_string()),
+        false,
+        &[],
+        false,
+        check::jobs(),
+    )
+    .await
+    {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("Fatal error while checking {}: {:#}", package, e);
+            // The manifest may not even be readable, the annotation is thus
+            // not created from the world.
+            let diag = check::fatal_error(&e);
+            let annotations = [Annotation {
+                path: format!(
+                    "packages/{}/{}/{}/typst.toml",
+                    package.namespace, package.name, package.version
+                ),
+                start_line: 1,
+                end_line: 1,
+                start_column: None,
+                end_column: None,
+                annotation_level: AnnotationLevel::Failure,
+                message: diag.message,
+            }];
+            api_client
+                .update_check_run(
+                    repository.owner(),
+                    repository.name(),
+                    check_run.id,
+                    CheckRunConclusion::Failure,
+                    CheckRunOutput {
+                        title: "Fatal error",
+                        summary: &format!(
+                            "The checks could not be completed because of the \
+                            following error:\n\n```\n{:?}\n```",
+                            e
+                        ),
+                        annotations: &annotations,
+                    },
+                )
+                .await
+                .context("Failed to report fatal error")?;
+            return Ok(Some((
+                summary::PackageReport::new(package, 1, 0, &annotations),
+                Vec::new(),
+            )));
+        }
+    };
+
+    if ctx.is_superseded(package).await {
+        api_client
+            .update_check_run(
+                repository.owner(),
+                repository.name(),
+                check_run.id,
+                CheckRunConclusion::Cancelled,
+                CheckRunOutput {
+                    title: "Superseded",
+                    summary: "New commits were pushed to this pull request while it was \
+                    being checked. The results are reported on the latest commit.",
+                    annotations: &[],
+                },
+            )
+            .await
+            .context("Failed to report a superseded check")?;
+        return Ok(None);
+    }
+
+    let stats = diags.stats();
+    let plural = |n| if n == 1 { "" } else { "s" };
+
+    let title = if stats.errors > 0 {
+        if stats.warnings == 0 {
+            format!("{} error{}", stats.errors, plural(stats.errors))
+        } else {
+            format!(
+                "{} error{}, {} warning{}",
+                stats.errors,
+                plural(stats.errors),
+                stats.warnings,
+                plural(stats.warnings)
+            )
+        }
+    } else if stats.warnings == 0 {
+        "All good!".to_owned()
+    } else {
+        format!("{} warning{}", stats.warnings, plural(stats.warnings))
+    };
+
+    // Errors come first, so that they are the ones that are
+    // reported if one of the batches fails to be sent.
+    let annotations_by_diag = diags
+        .errors()
+        .iter()
+        .chain(diags.warnings())
+        .chain(diags.notes())
+        .map(|diag| {
+            let kind = diag.code.as_deref().unwrap_or(&diag.message);
+            (kind, diagnostic_to_annotations(&world, package, diag))
+        })
+        .collect::<Vec<_>>();
+    let mut unattached = annotations_by_diag
+        .iter()
+        .filter(|(_, a)| a.is_empty())
+        .count();
+    let report = summary::PackageReport::new(
+        package,
+        stats.errors,
+        stats.warnings,
+        annotations_by_diag.iter().filter_map(|(_, a)| a.first()),
+    );
+    let (annotations, folded) = fold_annotations(annotations_by_diag);
+
+    // GitHub only accepts a limited number of annotations per
+    // request, but appends them to the existing ones on each
+    // update: all batches but the last one are sent while the
+    // check is still in progress.
+    let mut batches = annotations
+        .chunks(MAX_ANNOTATIONS_PER_REQUEST)
+        .collect::<Vec<_>>();
+    let last_batch = batches.pop().unwrap_or_default();
+    for batch in batches {
+        if let Err(e) = api_client
+            .add_check_run_annotations(
+                repository.owner(),
+                repository.name(),
+                check_run.id,
+                CheckRunOutput {
+                    title: &title,
+                    summary: "Checks are being reported…",
+                    annotations: batch,
+                },
+            )
+            .await
+        {
+            warn!("Failed to send a batch of annotations: {:?}", e);
+            unattached += batch.len();
+        }
+    }
+
+    let mut summary = format!(
+        "Our bots have automatically run some checks on your packages. \
+        They found {} error{} and {} warning{}.\n\n\
+        Warnings are suggestions, your package can still be accepted even \
+        if you prefer not to fix them.\n\n\
+        A human being will soon review your package, too.",
+        stats.errors,
+        plural(stats.errors),
+        stats.warnings,
+        plural(stats.warnings),
+    );
+    if stats.notes > 0 {
+        summary += &format!(
+            "\n\nThey also left {} note{} for information, that you don't need to act on.",
+            stats.notes,
+            plural(stats.notes),
+        );
+    }
+    if folded > 0 {
+        summary += &format!(
+            "\n\n{} finding{} {} grouped with similar ones in the annotations.",
+            folded,
+            plural(folded),
+            if folded == 1 { "was" } else { "were" },
+        );
+    }
+    if unattached > 0 {
+        summary += &format!(
+            "\n\n{} of these finding{} could not be attached to your files. \
+            Run `typst-package-check check` locally to see all of them.",
+            unattached,
+            plural(unattached),
+        );
+    }
+    summary += "\n\n";
+    summary += &diags.timings().to_markdown();
+
+    api_client
+        .update_check_run(
+            repository.owner(),
+            repository.name(),
+            check_run.id,
+            conclusion(stats.errors, stats.warnings, state.fail_on_warnings),
+            CheckRunOutput {
+                title: &title,
+                summary: &summary,
+                annotations: last_batch,
+            },
+        )
+        .await
+        .context("Failed to send report")?;
+
+    let suggestions = diags
+        .suggestions()
+        .iter()
+        .filter_map(|suggestion| suggestions::review_comment(&world, package, suggestion))
+        .collect();
+
+    Ok(Some((report, suggestions)))
+}
+
+/// The conclusion of the check run of a package, given the number of errors
+/// and warnings that were found.
+///
+/// Warnings only make the check fail with `fail_on_warnings`.
+fn conclusion(errors: usize, warnings: usize, fail_on_warnings: bool) -> CheckRunConclusion {
+    if errors > 0 || (fail_on_warnings && warnings > 0) {
+        CheckRunConclusion::Failure
+    } else if warnings > 0 {
+        CheckRunConclusion::Neutral
+    } else {
+        CheckRunConclusion::Success
+    }
+}
+
+/// Computes the title a pull request should have, given its current title and
+/// the list of packages it touches.
+///
+/// The list of packages is used as a prefix, but anything the author added
+/// after it (like "(resubmission)") is kept.
+fn pr_title(current: &str, packages: &str) -> String {
+    let normalize = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+    let current_normalized = normalize(current);
+    if let Some(rest) = current_normalized.strip_prefix(&normalize(packages)) {
+        // Make sure the prefix is not the beginning of a longer version
+        // number, or of a longer list of packages.
+        if rest.is_empty() || (rest.starts_with(' ') && !rest.starts_with(" and ")) {
+            return current.to_owned();
+        }
+    }
+
+    const SEPARATORS: [&str; 5] = [" (", " — ", " – ", " - ", " | "];
+    let suffix = SEPARATORS
+        .iter()
+        .filter_map(|sep| current.find(sep))
+        .min()
+        .map(|idx| &current[idx..])
+        .unwrap_or_default();
+    format!("{packages}{suffix}")
+}
+
+/// A warning for pull requests that touch too many packages to be reviewed
+/// properly, if any. This doesn't affect the checks of the packages.
+///
+/// A limit of zero disables the corresponding warning.
+fn pr_size_warning(
+    touched_packages: &HashSet<PackageSpec>,
+    new_packages: usize,
+    max_new_packages: usize,
+    max_versions_per_package: usize,
+) -> Option<String> {
+    let mut warnings = Vec::new();
+    if max_new_packages > 0 && new_packages > max_new_packages {
+        warnings.push(format!(
+            "This pull request adds {} new packages.",
+            new_packages
+        ));
+    }
+
+    let mut versions = HashMap::new();
+    for package in touched_packages {
+        *versions
+            .entry((&package.namespace, &package.name))
+            .or_insert(0usize) += 1;
+    }
+    let mut names = versions
+        .into_iter()
+        .filter(|(_, count)| max_versions_per_package > 0 && *count > max_versions_per_package)
+        .map(|((namespace, name), count)| format!("@{namespace}/{name} ({count} versions)"))
+        .collect::<Vec<_>>();
+    if !names.is_empty() {
+        names.sort();
+        warnings.push(format!(
+            "This pull request adds several versions of the same package: {}.",
+            names.join(", ")
+        ));
+    }
+
+    if warnings.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "{}\n\nLarge pull requests are harder to review carefully. \
+        Please consider splitting it into smaller ones, with one package \
+        (and a single version of it) each. \
+        This doesn't prevent it from being merged.",
+        warnings.join("\n\n")
+    ))
+}
+
+/// Whether the comments of a pull request contain a notification for the
+/// author of a previous version, or a comment of theirs.
+///
+/// Only the notifications of the app count: quoting one doesn't prevent the
+/// author from being notified.
+fn previous_author_was_notified(
+    comments: &[Comment],
+    bot_login: &str,
+    previous_author: &str,
+    marker: &str,
+) -> bool {
+    comments.iter().any(|c| {
+        (c.user.login == bot_login && c.body.contains(marker)) || c.user.login == previous_author
+    })
+}
+
+/// Among the pull requests containing a commit, finds the one that merged it
+/// into the default branch, preferring the one whose merge commit is this
+/// commit (for squashed pull requests).
+fn publishing_pr(prs: Vec<PullRequest>, commit: &str, default_branch: &str) -> Option<PullRequest> {
+    let mut merged = prs
+        .into_iter()
+        .filter(|pr| pr.merged_into(default_branch))
+        .collect::<Vec<_>>();
+    let index = merged
+        .iter()
+        .position(|pr| pr.merge_commit_sha.as_deref() == Some(commit))
+        .unwrap_or(0);
+    (index < merged.len()).then(|| merged.swap_remove(index))
+}
+
+/// Pull request metadata (title, body, labels, comments) only makes the
+/// reviews easier: if the app is not allowed to edit it, the checks are still
+/// reported.
+fn skip_if_forbidden(res: Result<(), ApiError>, message: &'static str) -> eyre::Result<()> {
+    match res {
+        Err(e) if e.is_forbidden() => {
+            warn!("{message}, skipping: {e}");
+            Ok(())
+        }
+        res => res.context(message),
+    }
+}
+
+/// The name of the check run for a given package.
+fn check_run_name(package: &PackageSpec) -> String {
+    format!(
+        "@{}/{}:{}",
+        package.namespace, package.name, package.version
+    )
+}
+
+/// The package corresponding to a check run, as named by [`check_run_name`].
+fn check_run_package(check_run_name: &str) -> Option<PackageSpec> {
+    check_run_name.parse().ok()
+}
+
+/// Whether a comment asks for the checks to run again, by starting with the
+/// command as a whole word.
+fn is_check_command(comment: &str, command: &str) -> bool {
+    comment
+        .trim_start()
+        .strip_prefix(command)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// The path of a file in the repository, as expected by GitHub for
+/// annotations.
+///
+/// Files of the checked package may be referred to either relative to the
+/// package root, or through the package itself (from the template, for
+/// instance). Files from other packages are not part of the pull request and
+/// can't be annotated.
+fn annotation_path(world: &SystemWorld, package: &PackageSpec, file_id: FileId) -> Option<String> {
+    if file_id.package().is_some_and(|p| p != package) {
+        return None;
+    }
+
+    // GitHub rejects the whole request if an annotation is for a file that
+    // isn't in the pull request.
+    let relative_path = file_id.vpath().as_rootless_path();
+    if !world.root().join(relative_path).exists() {
+        warn!(
+            "Not annotating {}, that is not in the package directory",
+            relative_path.display()
+        );
+        return None;
+    }
+
+    // GitHub always expects forward slashes, whatever the platform.
+    let components = relative_path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!(
+        "packages/{}/{}/{}/{}",
+        package.namespace,
+        package.name,
+        package.version,
+        components.join("/")
+    ))
+}
+
+/// Creates an annotation for each label of a diagnostic.
+///
+/// GitHub doesn't support annotations with multiple locations: the other
+/// locations are listed in the message of each annotation.
+fn diagnostic_to_annotations(
+    world: &SystemWorld,
+    package: &PackageSpec,
+    diag: &Diagnostic<FileId>,
+) -> Vec<Annotation> {
+    // Annotations can't have notes, they are appended to the message.
+    // It is truncated if needed when serialized.
+    let message = std::iter::once(&diag.message)
+        .chain(&diag.notes)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let located_labels = diag
+        .labels
+        .iter()
+        .filter_map(|label| Some((label, label_annotation(world, package, diag, label)?)))
+        .collect::<Vec<_>>();
+
+    located_labels
+        .iter()
+        .enumerate()
+        .map(|(i, (label, annotation))| {
+            let mut message = message.clone();
+            if !label.message.is_empty() {
+                message = format!("{}\n\n{}", message, label.message);
+            }
+            let see_also = located_labels
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (other, a))| {
+                    let location = format!("{}:{}", relative_path(a, package), a.start_line);
+                    if other.message.is_empty() {
+                        location
+                    } else {
+                        format!("{} ({})", location, other.message)
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !see_also.is_empty() {
+                message = format!("{}\n\nSee also: {}", message, see_also.join(", "));
+            }
+            if let Some(url) = diag.code.as_deref().and_then(check::explanations::doc_url) {
+                message = format!("{}\n\nMore info: {}", message, url);
+            }
+            Annotation {
+                message,
+                ..annotation.clone()
+            }
+        })
+        .collect()
+}
+
+/// Maximum number of annotations for the same kind of diagnostic in a file.
+const MAX_SIMILAR_ANNOTATIONS: usize = 3;
+
+/// Reduces the noise of the annotations of a package.
+///
+/// Only a few annotations are kept for the diagnostics of the same kind
+/// (their code, or their message if they have none) in a file, the last one
+/// telling how many were left out. Annotations on the same lines are merged.
+///
+/// `annotations` should be sorted from the most to the least important. The
+/// number of annotations that were left out or merged is returned too.
+fn fold_annotations(annotations: Vec<(&str, Vec<Annotation>)>) -> (Vec<Annotation>, usize) {
+    let mut folded = 0;
+
+    // For each file and kind: the index of the last kept annotation, the
+    // number of kept annotations, and the number of left out ones.
+    let mut similar: HashMap<(String, &str), (usize, usize, usize)> = HashMap::new();
+    let mut kept: Vec<Annotation> = Vec::new();
+    for (kind, annotation) in annotations
+        .into_iter()
+        .flat_map(|(kind, annotations)| annotations.into_iter().map(move |a| (kind, a)))
+    {
+        let (last, count, left_out) = similar.entry((annotation.path.clone(), kind)).or_default();
+        if *count >= MAX_SIMILAR_ANNOTATIONS {
+            *left_out += 1;
+            folded += 1;
+        } else {
+            *last = kept.len();
+            *count += 1;
+            kept.push(annotation);
+        }
+    }
+    for (last, _, left_out) in similar.into_values() {
+        if left_out > 0 {
+            kept[last].message += &format!("\n\n(+{} more like this in this file)", left_out);
+        }
+    }
+
+    let mut merged: Vec<Annotation> = Vec::new();
+    for annotation in kept {
+        let same_lines = merged.iter_mut().find(|a| {
+            a.path == annotation.path
+                && a.start_line == annotation.start_line
+                && a.end_line == annotation.end_line
+        });
+        match same_lines {
+            Some(existing) => {
+                existing.message += &format!("\n\n---\n\n{}", annotation.message);
+                if existing.start_column != annotation.start_column
+                    || existing.end_column != annotation.end_column
+                {
+                    existing.start_column = None;
+                    existing.end_column = None;
+                }
+                folded += 1;
+            }
+            None => merged.push(annotation),
+        }
+    }
+
+    (merged, folded)
+}
+
+/// The path of an annotated file, relative to the package directory.
+fn relative_path<'a>(annotation: &'a Annotation, package: &PackageSpec) -> &'a str {
+    let prefix = format!(
+        "packages/{}/{}/{}/",
+        package.namespace, package.name, package.version
+    );
+    annotation
+        .path
+        .strip_prefix(&prefix)
+        .unwrap_or(&annotation.path)
+}
+
+/// An annotation at the location of a label, without any message.
+fn label_annotation(
+    world: &SystemWorld,
+    package: &PackageSpec,
+    diag: &Diagnostic<FileId>,
+    label: &Label<FileId>,
+) -> Option<Annotation> {
+    let start_line = world.line_index(label.file_id, label.range.start).ok()?;
+    let end_line = world.line_index(label.file_id, label.range.end).ok()?;
+    let (start_column, end_column) = if start_line == end_line {
+        let start = world
+            .column_number(label.file_id, start_line, label.range.start)
+            .ok();
+        let end = world
+            .column_number(label.file_id, start_line, label.range.end)
+            .ok();
+        (start, end)
+    } else {
+        (None, None)
+    };
+    Some(Annotation {
+        path: annotation_path(world, package, label.file_id)?,
+        // Lines are 1-indexed on GitHub but not for codespan
+        start_line: start_line + 1,
+        end_line: end_line + 1,
+        start_column,
+        end_column,
+        annotation_level: match diag.severity {
+            Severity::Note | Severity::Help => AnnotationLevel::Notice,
+            Severity::Warning => AnnotationLevel::Warning,
+            Severity::Error | Severity::Bug => AnnotationLevel::Failure,
+        },
+        message: String::new(),
+    })
+}
+
+#[derive(Debug)]
+enum WebError {
+    Api(ApiError),
+    UnexpectedEvent,
+}
+
+impl IntoResponse for WebError {
+    fn into_response(self) -> axum::response::Response {
+        debug!("Web error: {:?}", &self);
+
+        let status = match &self {
+            WebError::Api(e) if e.is_rate_limited() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Response::builder()
+            .status(status)
+            .body(Body::from(format!("{:?}", self)))
+            .expect("Can't build error response")
+    }
+}
+
+impl From<ApiError> for WebError {
     fn from(value: ApiError) -> Self {
         WebError::Api(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use hmac::Mac;
+    use serde_json::json;
+
+    use super::*;
+    use crate::github::api::fake::FakeApi;
+
+    const SECRET: &str = "webhook-secret";
+
+    /// Serves the webhook routes on a random port, using a fake GitHub API,
+    /// and returns their base URL.
+    async fn serve(api: &FakeApi) -> String {
+        let state = AppState {
+            private_key: include_str!("../tests/fixtures/test-private-key.pem").to_owned(),
+            api_url: api.url.clone(),
+            ..AppState::for_tests(SECRET)
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        url
+    }
+
+    /// Lets the app create installation tokens for `typst/packages`.
+    fn grant_installation_tokens(api: &FakeApi) {
+        api.respond(
+            Method::POST,
+            "app/installations/1/access_tokens",
+            StatusCode::CREATED,
+            json!({
+                "token": "installation-token",
+                "expires_at": (chrono::Utc::now() + chrono::TimeDelta::hours(1)).to_rfc3339(),
+                "repositories": [{ "name": "packages" }],
+            }),
+        );
+    }
+
+    /// A comment on pull request #1, asking for its checks to run again.
+    fn check_command_payload(login: &str) -> serde_json::Value {
+        json!({
+            "action": "created",
+            "installation": { "id": 1 },
+            "issue": { "number": 1, "pull_request": {} },
+            "comment": { "id": 10, "body": "/check", "user": { "login": login } },
+            "repository": { "full_name": "typst/packages" },
+        })
+    }
+
+    fn sign(secret: &str, payload: &str) -> String {
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(payload.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hex = digest
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        format!("sha256={hex}")
+    }
+
+    async fn post_hook(
+        url: &str,
+        event: &str,
+        delivery: &str,
+        payload: &str,
+        signature: &str,
+    ) -> reqwest::StatusCode {
+        reqwest::Client::new()
+            .post(format!("{url}/hook"))
+            .header("X-GitHub-Event", event)
+            .header("X-GitHub-Delivery", delivery)
+            .header("X-Hub-Signature-256", signature)
+            .body(payload.to_owned())
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn signed_payloads_are_handled() {
+        let api = FakeApi::start().await;
+        grant_installation_tokens(&api);
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/collaborators/someone/permission",
+            StatusCode::OK,
+            json!({ "permission": "read" }),
+        );
+        let url = serve(&api).await;
+
+        let payload = check_command_payload("someone").to_string();
+        let signature = sign(SECRET, &payload);
+        assert_eq!(
+            post_hook(&url, "issue_comment", "1", &payload, &signature).await,
+            StatusCode::OK
+        );
+        // The app authenticated for the repository of the event, and looked
+        // at who asked for the checks.
+        let paths = api
+            .requests()
+            .into_iter()
+            .map(|r| r.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "app/installations/1/access_tokens",
+                "repos/typst/packages/collaborators/someone/permission"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn events_from_other_repositories_are_ignored() {
+        let api = FakeApi::start().await;
+        grant_installation_tokens(&api);
+        let url = serve(&api).await;
+
+        let mut payload = check_command_payload("someone");
+        payload["repository"]["full_name"] = json!("someone/elsewhere");
+        let payload = payload.to_string();
+        let signature = sign(SECRET, &payload);
+        assert_eq!(
+            post_hook(&url, "issue_comment", "1", &payload, &signature).await,
+            StatusCode::OK
+        );
+        assert!(api.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn payloads_with_a_wrong_signature_are_rejected() {
+        let api = FakeApi::start().await;
+        let url = serve(&api).await;
+        let payload = check_command_payload("someone").to_string();
+        let signature = sign("another-secret", &payload);
+        assert_eq!(
+            post_hook(&url, "issue_comment", "2", &payload, &signature).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert!(api.requests().is_empty());
+    }
+
+    fn pull_request(
+        number: usize,
+        head_sha: &str,
+        draft: bool,
+        labels: &[&str],
+    ) -> serde_json::Value {
+        json!({
+            "number": number,
+            "head": { "sha": head_sha },
+            "title": "Add my package",
+            "body": "I am submitting\n- [ ] a new package\n- [ ] an update for a package",
+            "user": { "login": "author" },
+            "draft": draft,
+            "labels": labels.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+            "base": { "ref": "main" },
+        })
+    }
+
+    /// Answers an event like the webhook route, and returns the checks it
+    /// asks for.
+    async fn checks_for(
+        state: &AppState,
+        api: &FakeApi,
+        payload: HookPayload,
+    ) -> Option<CommitChecks> {
+        checks_for_event(state.clone(), api.client(), DeliveryId(None), payload)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_maintainers_can_run_the_checks_again() {
+        let api = FakeApi::start().await;
+        for (login, permission) in [("someone", "read"), ("maintainer", "write")] {
+            api.respond(
+                Method::GET,
+                &format!("repos/typst/packages/collaborators/{login}/permission"),
+                StatusCode::OK,
+                json!({ "permission": permission }),
+            );
+        }
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/pulls/1",
+            StatusCode::OK,
+            pull_request(1, "abcdef", false, &[]),
+        );
+        let state = AppState::for_tests(SECRET);
+        let reactions = "repos/typst/packages/issues/comments/10/reactions";
+
+        let payload = serde_json::from_value(check_command_payload("someone")).unwrap();
+        let checks = checks_for(&state, &api, HookPayload::IssueComment(payload)).await;
+        assert!(checks.is_none());
+        assert!(api.requests_to(Method::POST, reactions).is_empty());
+        assert!(api
+            .requests_to(Method::GET, "repos/typst/packages/pulls/1")
+            .is_empty());
+
+        let payload = serde_json::from_value(check_command_payload("maintainer")).unwrap();
+        let checks = checks_for(&state, &api, HookPayload::IssueComment(payload))
+            .await
+            .unwrap();
+        assert!(checks.rerun);
+        assert_eq!(checks.head_sha, "abcdef");
+        assert_eq!(checks.pr.map(|pr| pr.number), Some(1));
+        assert_eq!(
+            api.requests_to(Method::POST, reactions),
+            [json!({ "content": "+1" })]
+        );
+    }
+
+    /// A copy of `typst/packages`, and the clone of it in which the app
+    /// checks pull requests.
+    struct PackagesRepo {
+        origin: PathBuf,
+        clone: PathBuf,
+    }
+
+    impl PackagesRepo {
+        /// Creates the repository, with some files on its main branch.
+        fn new(name: &str, files: &[(String, String)]) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "typst-package-check-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            let origin = dir.join("origin");
+            std::fs::create_dir_all(&origin).unwrap();
+            git(&origin, &["init", "--initial-branch=main"]);
+
+            let repo = PackagesRepo {
+                origin,
+                clone: dir.join("clone"),
+            };
+            // Commits are checked out in a directory named after them: they
+            // must be different in each test.
+            let readme = ("README.md".to_owned(), name.to_owned());
+            repo.commit(&[vec![readme], files.to_vec()].concat(), &[]);
+            git(&dir, &["clone", "origin", "clone"]);
+            repo
+        }
+
+        /// Commits changes to a new branch, and returns the hash of the
+        /// commit. The main branch is left as it is.
+        fn pull_request(
+            &self,
+            branch: &str,
+            files: &[(String, String)],
+            deleted: &[&str],
+        ) -> String {
+            git(&self.origin, &["checkout", "-b", branch]);
+            let sha = self.commit(files, deleted);
+            git(&self.origin, &["checkout", "main"]);
+            sha
+        }
+
+        fn commit(&self, files: &[(String, String)], deleted: &[&str]) -> String {
+            for (path, contents) in files {
+                let path = self.origin.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, contents).unwrap();
+            }
+            for path in deleted {
+                std::fs::remove_file(self.origin.join(path)).unwrap();
+            }
+            git(&self.origin, &["add", "--all"]);
+            git(
+                &self.origin,
+                &[
+                    "-c",
+                    "user.name=Jane Doe",
+                    "-c",
+                    "user.email=jane@example.com",
+                    "commit",
+                    "--message=Update packages",
+                ],
+            );
+            git(&self.origin, &["rev-parse", "HEAD"]).trim().to_owned()
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// A package without errors.
+    const LIB: &str = "#let greet(name) = [Hello, #name!]\n";
+
+    /// The files of a package in `@preview`.
+    fn package_files(name: &str, version: &str, lib: &str) -> Vec<(String, String)> {
+        let dir = format!("packages/preview/{name}/{version}");
+        let manifest = format!(
+            "[package]\n\
+            name = \"{name}\"\n\
+            version = \"{version}\"\n\
+            entrypoint = \"lib.typ\"\n\
+            authors = [\"Jane Doe\"]\n\
+            license = \"MIT\"\n\
+            description = \"Greet people.\"\n"
+        );
+        vec![
+            (format!("{dir}/typst.toml"), manifest),
+            (format!("{dir}/LICENSE"), "MIT License\n".to_owned()),
+            (format!("{dir}/README.md"), format!("# {name}\n")),
+            (format!("{dir}/lib.typ"), lib.to_owned()),
+        ]
+    }
+
+    /// A fake API that creates check runs, and knows about a pull request.
+    async fn fake_api(pr: &serde_json::Value) -> FakeApi {
+        let api = FakeApi::start().await;
+        let ids = std::sync::atomic::AtomicU64::new(0);
+        api.respond_with(
+            Method::POST,
+            "repos/typst/packages/check-runs",
+            move |body| {
+                let id = ids.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                let check_run =
+                    json!({ "id": id, "name": body["name"], "check_suite": { "id": 1 } });
+                (StatusCode::CREATED, check_run)
+            },
+        );
+        api.respond(
+            Method::GET,
+            &format!("repos/typst/packages/pulls/{}", pr["number"]),
+            StatusCode::OK,
+            pr.clone(),
+        );
+        api
+    }
+
+    fn pull_request_event(action: &str, pr: &serde_json::Value) -> HookPayload {
+        HookPayload::PullRequest(
+            serde_json::from_value(json!({
+                "action": action,
+                "installation": { "id": 1 },
+                "pull_request": pr,
+                "repository": { "full_name": "typst/packages" },
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn test_state(repo: &PackagesRepo) -> AppState {
+        AppState {
+            git_dir: repo.clone.display().to_string(),
+            ..AppState::for_tests(SECRET)
+        }
+    }
+
+    /// Answers an event and waits for the checks it asks for. Returns whether
+    /// there were checks to run.
+    async fn run_checks_for(state: &AppState, api: &FakeApi, payload: HookPayload) -> bool {
+        crate::http::set_offline();
+        let Some(checks) = checks_for(state, api, payload).await else {
+            return false;
+        };
+        checks.run().await.unwrap();
+        true
+    }
+
+    /// The updates of each check run, by name.
+    ///
+    /// Check runs are created one after the other, and get increasing IDs
+    /// from [`fake_api`].
+    fn check_runs(api: &FakeApi) -> HashMap<String, Vec<serde_json::Value>> {
+        let mut names = Vec::new();
+        let mut runs = HashMap::new();
+        for request in api.requests() {
+            if request.method == Method::POST && request.path == "repos/typst/packages/check-runs" {
+                let name = request.body["name"].as_str().unwrap().to_owned();
+                names.push(name.clone());
+                runs.insert(name, Vec::new());
+            } else if let Some(id) = request
+                .path
+                .strip_prefix("repos/typst/packages/check-runs/")
+                .filter(|_| request.method == Method::PATCH)
+            {
+                let name = &names[id.parse::<usize>().unwrap() - 1];
+                runs.get_mut(name).unwrap().push(request.body);
+            }
+        }
+        runs
+    }
+
+    /// The final conclusion of a check run, if it was completed.
+    fn conclusion_of(updates: &[serde_json::Value]) -> Option<&str> {
+        updates
+            .last()
+            .filter(|update| update["status"] == "completed")?["conclusion"]
+            .as_str()
+    }
+
+    /// The changes made to the pull requests (their title, body, labels and
+    /// comments), check runs aside.
+    fn pr_changes(api: &FakeApi) -> Vec<api::fake::Request> {
+        api.requests()
+            .into_iter()
+            .filter(|r| r.method != Method::GET && !r.path.contains("/check-runs"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn drafts_are_checked_but_not_modified_until_they_are_ready() {
+        let repo = PackagesRepo::new("draft", &[]);
+        let sha = repo.pull_request("draft", &package_files("greet", "0.1.0", LIB), &[]);
+        let state = test_state(&repo);
+        let draft = pull_request(1, &sha, true, &[]);
+        let api = fake_api(&draft).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &draft)).await);
+        let runs = check_runs(&api);
+        assert!(conclusion_of(&runs["@preview/greet:0.1.0"]).is_some());
+        assert!(pr_changes(&api).is_empty());
+
+        let ready = pull_request(1, &sha, false, &[]);
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/pulls/1",
+            StatusCode::OK,
+            ready.clone(),
+        );
+        assert!(run_checks_for(&state, &api, pull_request_event("ready_for_review", &ready)).await);
+        let updates = api.requests_to(Method::PATCH, "repos/typst/packages/issues/1");
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["title"], "greet:0.1.0");
+        assert!(updates[0]["body"]
+            .as_str()
+            .unwrap()
+            .contains("- [x] a new package"));
+        let labels = api.requests_to(Method::POST, "repos/typst/packages/issues/1/labels");
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0]["labels"][0], LABEL_NEW);
+        let comments = api.requests_to(Method::POST, "repos/typst/packages/issues/1/comments");
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0]["body"]
+            .as_str()
+            .unwrap()
+            .starts_with(summary::MARKER));
+    }
+
+    #[tokio::test]
+    async fn every_package_of_a_pull_request_is_reported() {
+        let repo = PackagesRepo::new("several-packages", &[]);
+        let files = ["first", "second", "third"]
+            .into_iter()
+            .flat_map(|name| package_files(name, "0.1.0", LIB))
+            .collect::<Vec<_>>();
+        let sha = repo.pull_request("several", &files, &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let runs = check_runs(&api);
+        assert_eq!(runs.len(), 3);
+        for (name, updates) in &runs {
+            assert!(conclusion_of(updates).is_some(), "{name} was not completed");
+        }
+        // There is a single summary for all of them.
+        let comments = api.requests_to(Method::POST, "repos/typst/packages/issues/1/comments");
+        assert_eq!(comments.len(), 1);
+        for name in runs.keys() {
+            assert!(comments[0]["body"]
+                .as_str()
+                .unwrap()
+                .contains(name.as_str()));
+        }
+    }
+
+    #[tokio::test]
+    async fn fatal_errors_only_stop_the_checks_of_their_package() {
+        let repo = PackagesRepo::new("fatal-error", &[]);
+        let mut files = package_files("broken", "0.1.0", LIB);
+        files[0].1 = "[package\nname = \"broken\"\n".to_owned();
+        files.extend(package_files("fine", "0.1.0", LIB));
+        let sha = repo.pull_request("fatal", &files, &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let runs = check_runs(&api);
+        let broken = &runs["@preview/broken:0.1.0"];
+        assert_eq!(conclusion_of(broken), Some("failure"));
+        assert_eq!(broken.last().unwrap()["output"]["title"], "Fatal error");
+        let annotations = &broken.last().unwrap()["output"]["annotations"];
+        assert_eq!(
+            annotations[0]["path"],
+            "packages/preview/broken/0.1.0/typst.toml"
+        );
+        let fine = &runs["@preview/fine:0.1.0"];
+        assert!(conclusion_of(fine).is_some());
+        assert_ne!(fine.last().unwrap()["output"]["title"], "Fatal error");
+    }
+
+    /// A package with an error in its manifest, and more than
+    /// [`MAX_ANNOTATIONS_PER_REQUEST`] warnings.
+    fn noisy_package() -> Vec<(String, String)> {
+        let mut lib = String::new();
+        let mut files = package_files("noisy", "0.1.0", "");
+        // The description is missing.
+        files[0].1 = files[0].1.replace("description = \"Greet people.\"\n", "");
+        // Three warnings in each file, that are not folded.
+        for i in 0..20 {
+            lib += &format!("#import \"greet-{i}.typ\": *\n");
+            files.push((
+                format!("packages/preview/noisy/0.1.0/greet-{i}.typ"),
+                format!("#let greetPerson{i}(\n  firstName,\n  lastName,\n) = [Hello]\n"),
+            ));
+        }
+        files[3].1 = lib;
+        files
+    }
+
+    #[tokio::test]
+    async fn annotations_are_sent_in_batches() {
+        let repo = PackagesRepo::new("annotation-batches", &[]);
+        let sha = repo.pull_request("noisy", &noisy_package(), &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let updates = &check_runs(&api)["@preview/noisy:0.1.0"];
+        assert!(updates.len() >= 2);
+        let (last, batches) = updates.split_last().unwrap();
+        // Only the last update completes the check run.
+        for batch in batches {
+            assert_eq!(batch["status"], "in_progress");
+            assert!(batch.get("conclusion").is_none());
+            let annotations = batch["output"]["annotations"].as_array().unwrap();
+            assert_eq!(annotations.len(), MAX_ANNOTATIONS_PER_REQUEST);
+        }
+        assert_eq!(conclusion_of(updates), Some("failure"));
+        let last_annotations = last["output"]["annotations"].as_array().unwrap();
+        assert!(!last_annotations.is_empty());
+        assert!(last_annotations.len() <= MAX_ANNOTATIONS_PER_REQUEST);
+        // Errors are sent first.
+        let first = &batches[0]["output"]["annotations"][0];
+        assert_eq!(first["annotation_level"], "failure");
+        assert_eq!(first["path"], "packages/preview/noisy/0.1.0/typst.toml");
+        // Errors of the manifest link to its documentation.
+        let url = check::explanations::doc_url("manifest/description/missing").unwrap();
+        assert!(first["message"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("More info: {url}")));
+    }
+
+    #[tokio::test]
+    async fn annotations_that_could_not_be_sent_are_counted() {
+        let repo = PackagesRepo::new("failed-annotations", &[]);
+        let sha = repo.pull_request("noisy", &noisy_package(), &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+        // The first check run is the one of the package.
+        api.respond_with(Method::PATCH, "repos/typst/packages/check-runs/1", |body| {
+            if body["status"] == "in_progress" {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "message": "Oops" }),
+                )
+            } else {
+                (StatusCode::OK, json!({}))
+            }
+        });
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let updates = &check_runs(&api)["@preview/noisy:0.1.0"];
+        assert_eq!(conclusion_of(updates), Some("failure"));
+        let failed = (updates.len() - 1) * MAX_ANNOTATIONS_PER_REQUEST;
+        let summary = updates.last().unwrap()["output"]["summary"]
+            .as_str()
+            .unwrap();
+        assert!(failed > 0);
+        // Findings without a location can't be attached either.
+        let unattached = summary
+            .split("\n\n")
+            .find_map(|paragraph| paragraph.split_once(" of these findings could not be attached"))
+            .and_then(|(count, _)| count.parse::<usize>().ok());
+        assert!(unattached.is_some_and(|count| count >= failed), "{summary}");
+    }
+
+    /// The labels that were added to pull request #1, and the ones that were
+    /// removed.
+    fn label_changes(api: &FakeApi) -> (Vec<String>, Vec<String>) {
+        let added = api
+            .requests_to(Method::POST, "repos/typst/packages/issues/1/labels")
+            .iter()
+            .flat_map(|body| body["labels"].as_array().unwrap().clone())
+            .map(|label| label.as_str().unwrap().to_owned())
+            .collect();
+        let removed = api
+            .requests()
+            .into_iter()
+            .filter(|r| r.method == Method::DELETE)
+            .filter_map(|r| {
+                let label = r
+                    .path
+                    .strip_prefix("repos/typst/packages/issues/1/labels/")?;
+                Some(label.to_owned())
+            })
+            .collect();
+        (added, removed)
+    }
+
+    #[tokio::test]
+    async fn labels_are_updated() {
+        let repo = PackagesRepo::new("labels", &[]);
+        let sha = repo.pull_request("labels", &package_files("greet", "0.1.0", LIB), &[]);
+        let cases: [(&[&str], &[&str], &[&str]); 3] = [
+            // current labels, added labels, removed labels
+            (&[], &[LABEL_NEW], &[]),
+            (&[LABEL_NEW, "other"], &[], &[]),
+            (
+                &[LABEL_UPDATE, LABEL_HAS_ERRORS, "other"],
+                &[LABEL_NEW],
+                &[LABEL_UPDATE, LABEL_HAS_ERRORS],
+            ),
+        ];
+        let owned = |labels: &[&str]| labels.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        for (current, added, removed) in cases {
+            let state = test_state(&repo);
+            let pr = pull_request(1, &sha, false, current);
+            let api = fake_api(&pr).await;
+            assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+            assert_eq!(
+                label_changes(&api),
+                (owned(added), owned(removed)),
+                "{current:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn pull_requests_with_errors_are_labelled() {
+        let repo = PackagesRepo::new("error-label", &[]);
+        let mut files = package_files("greet", "0.1.0", LIB);
+        files[0].1 = files[0].1.replace("description = \"Greet people.\"\n", "");
+        let sha = repo.pull_request("errors", &files, &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[LABEL_NEW]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        assert_eq!(
+            label_changes(&api),
+            (vec![LABEL_HAS_ERRORS.to_owned()], vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn removed_versions_are_not_checked() {
+        let published = package_files("greet", "0.1.0", LIB);
+        let repo = PackagesRepo::new("removed-version", &published);
+        let deleted = published
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>();
+        let sha = repo.pull_request("removal", &[], &deleted);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let updates = &check_runs(&api)["@preview/greet:0.1.0"];
+        assert_eq!(updates.len(), 1);
+        assert_eq!(conclusion_of(updates), Some("failure"));
+        let output = &updates[0]["output"];
+        assert_eq!(output["title"], "This version was removed");
+        assert!(output["summary"]
+            .as_str()
+            .unwrap()
+            .contains("already published"));
+    }
+
+    #[tokio::test]
+    async fn partially_removed_versions_are_checked() {
+        let published = package_files("greet", "0.1.0", LIB);
+        let repo = PackagesRepo::new("partially-removed-version", &published);
+        let lib = vec![(
+            "packages/preview/greet/0.1.0/lib.typ".to_owned(),
+            "#let greet(name) = [Hi, #name!]\n".to_owned(),
+        )];
+        let sha = repo.pull_request(
+            "partial-removal",
+            &lib,
+            &["packages/preview/greet/0.1.0/README.md"],
+        );
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let updates = &check_runs(&api)["@preview/greet:0.1.0"];
+        assert!(conclusion_of(updates).is_some());
+        let title = &updates.last().unwrap()["output"]["title"];
+        assert_ne!(title, "This version was removed");
+        assert_ne!(title, "Fatal error");
+    }
+
+    fn check_suite_event(head_sha: &str) -> HookPayload {
+        HookPayload::CheckSuite(
+            serde_json::from_value(json!({
+                "action": "requested",
+                "installation": { "id": 1 },
+                "check_suite": { "head_sha": head_sha, "pull_requests": [] },
+                "repository": { "full_name": "typst/packages" },
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn pull_requests_are_updated_after_a_check_suite_without_them() {
+        let repo = PackagesRepo::new("suite-first", &[]);
+        let sha = repo.pull_request("suite-first", &package_files("greet", "0.1.0", LIB), &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+
+        // Check suites of pull requests from forks don't say which pull
+        // request they are for.
+        assert!(run_checks_for(&state, &api, check_suite_event(&sha)).await);
+        assert!(pr_changes(&api).is_empty());
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        let updates = api.requests_to(Method::PATCH, "repos/typst/packages/issues/1");
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["title"], "greet:0.1.0");
+        let comments = api.requests_to(Method::POST, "repos/typst/packages/issues/1/comments");
+        assert_eq!(comments.len(), 1);
+
+        // Once the pull request is updated, the same commit is not checked
+        // again.
+        let runs = api.requests_to(Method::POST, "repos/typst/packages/check-runs");
+        assert!(run_checks_for(&state, &api, check_suite_event(&sha)).await);
+        assert!(run_checks_for(&state, &api, pull_request_event("synchronize", &pr)).await);
+        assert_eq!(
+            api.requests_to(Method::POST, "repos/typst/packages/check-runs"),
+            runs
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_checks_are_run_again_when_delivered_again() {
+        crate::http::set_offline();
+        let repo = PackagesRepo::new("redelivered", &[]);
+        let sha = repo.pull_request("redelivered", &package_files("greet", "0.1.0", LIB), &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+        let deliver = || {
+            checks_for_event(
+                state.clone(),
+                api.client(),
+                DeliveryId(Some("1".to_owned())),
+                pull_request_event("opened", &pr),
+            )
+        };
+
+        // The check run of the package can't be completed.
+        api.respond(
+            Method::PATCH,
+            "repos/typst/packages/check-runs/1",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "message": "Oops" }),
+        );
+        let checks = deliver().await.unwrap().unwrap();
+        assert_eq!(checks.delivery.as_deref(), Some("1"));
+        assert!(checks.run().await.is_err());
+
+        // A new check run is created, and can be completed.
+        let checks = deliver().await.unwrap().unwrap();
+        checks.run().await.unwrap();
+        assert!(conclusion_of(&check_runs(&api)["@preview/greet:0.1.0"]).is_some());
+
+        // Now that the delivery was handled, it is ignored.
+        assert!(deliver().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn events_that_could_not_be_answered_are_handled_when_delivered_again() {
+        let api = FakeApi::start().await;
+        let permission = "repos/typst/packages/collaborators/maintainer/permission";
+        api.respond(
+            Method::GET,
+            permission,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "message": "Oops" }),
+        );
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/pulls/1",
+            StatusCode::OK,
+            pull_request(1, "abcdef", false, &[]),
+        );
+        let state = AppState::for_tests(SECRET);
+        let deliver = || {
+            let payload = serde_json::from_value(check_command_payload("maintainer")).unwrap();
+            checks_for_event(
+                state.clone(),
+                api.client(),
+                DeliveryId(Some("1".to_owned())),
+                HookPayload::IssueComment(payload),
+            )
+        };
+
+        assert!(deliver().await.is_err());
+        api.respond(
+            Method::GET,
+            permission,
+            StatusCode::OK,
+            json!({ "permission": "write" }),
+        );
+        assert!(deliver().await.unwrap().is_some());
+        assert!(deliver().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn only_summaries_of_the_app_are_updated() {
+        let repo = PackagesRepo::new("copied-summary", &[]);
+        let sha = repo.pull_request("copied-summary", &package_files("greet", "0.1.0", LIB), &[]);
+        let state = test_state(&repo);
+        let pr = pull_request(1, &sha, false, &[]);
+        let api = fake_api(&pr).await;
+        // Someone quoted the summary before the app posted an updated one.
+        let outdated = format!("{}\nOutdated summary", summary::MARKER);
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/issues/1/comments",
+            StatusCode::OK,
+            json!([
+                { "id": 1, "body": outdated, "user": { "login": "someone" } },
+                { "id": 2, "body": outdated, "user": { "login": config::BOT_LOGIN } },
+            ]),
+        );
+
+        assert!(run_checks_for(&state, &api, pull_request_event("opened", &pr)).await);
+        assert!(api
+            .requests_to(Method::PATCH, "repos/typst/packages/issues/comments/1")
+            .is_empty());
+        assert_eq!(
+            api.requests_to(Method::PATCH, "repos/typst/packages/issues/comments/2")
+                .len(),
+            1
+        );
+        assert!(api
+            .requests_to(Method::POST, "repos/typst/packages/issues/1/comments")
+            .is_empty());
+    }
+
+    #[test]
+    fn check_run_names_round_trip() {
+        for spec in ["@preview/example:0.1.0", "@preview/my-package:10.2.33"] {
+            let package: PackageSpec = spec.parse().unwrap();
+            let name = check_run_name(&package);
+            assert_eq!(name, spec);
+            assert_eq!(check_run_package(&name), Some(package));
+        }
+    }
+
+    #[test]
+    fn other_check_runs_have_no_package() {
+        assert_eq!(check_run_package("Package check"), None);
+        assert_eq!(check_run_package("@preview/example"), None);
+        assert_eq!(check_run_package("preview/example:0.1.0"), None);
+    }
+
+    #[test]
+    fn check_commands() {
+        assert!(is_check_command("/check", "/check"));
+        assert!(is_check_command("  /check\n", "/check"));
+        assert!(is_check_command("/check please", "/check"));
+        assert!(!is_check_command("/checkout the docs", "/check"));
+        assert!(!is_check_command("/check-later", "/check"));
+        assert!(!is_check_command("Please /check", "/check"));
+    }
+
+    fn merged_pr(number: usize, base: &str, merge_commit: &str) -> PullRequest {
+        serde_json::from_value(serde_json::json!({
+            "number": number,
+            "head": { "sha": "head" },
+            "title": "foo:0.1.0",
+            "body": "",
+            "user": { "login": "someone" },
+            "state": "closed",
+            "merged_at": "2024-01-01T00:00:00Z",
+            "merge_commit_sha": merge_commit,
+            "base": { "ref": base },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn publishing_pr_is_merged_into_the_default_branch() {
+        let prs = vec![
+            merged_pr(1, "main", "abc"),
+            merged_pr(2, "trunk", "def"),
+            merged_pr(3, "trunk", "abc"),
+        ];
+        let pr = publishing_pr(prs.clone(), "abc", "trunk").unwrap();
+        assert_eq!(pr.number, 3);
+        let pr = publishing_pr(prs.clone(), "123", "trunk").unwrap();
+        assert_eq!(pr.number, 2);
+        assert!(publishing_pr(prs, "abc", "release").is_none());
+    }
+
+    #[test]
+    fn previous_authors_are_notified_once() {
+        let marker = "<!-- package-check:author-ping:@previous -->";
+        let comment = |login: &str, body: &str| Comment {
+            id: 1,
+            body: body.to_owned(),
+            user: api::user::User {
+                login: login.to_owned(),
+            },
+        };
+        let notified = |comments: &[Comment]| {
+            previous_author_was_notified(comments, config::BOT_LOGIN, "previous", marker)
+        };
+
+        assert!(!notified(&[]));
+        assert!(!notified(&[comment("someone", "Hello")]));
+        assert!(notified(&[comment(
+            config::BOT_LOGIN,
+            &format!("@previous\n\n{marker}")
+        )]));
+        assert!(notified(&[comment("previous", "I agree")]));
+        // Someone quoting the notification.
+        assert!(!notified(&[comment("someone", &format!("> {marker}"))]));
+    }
+
+    #[test]
+    fn conclusions() {
+        use CheckRunConclusion::*;
+        let cases = [
+            // errors, warnings, fail_on_warnings, expected conclusion
+            (0, 0, false, Success),
+            (0, 0, true, Success),
+            (0, 2, false, Neutral),
+            (0, 2, true, Failure),
+            (1, 0, false, Failure),
+            (1, 3, false, Failure),
+            (1, 3, true, Failure),
+        ];
+        for (errors, warnings, fail_on_warnings, expected) in cases {
+            assert_eq!(
+                conclusion(errors, warnings, fail_on_warnings),
+                expected,
+                "{errors} errors, {warnings} warnings, fail_on_warnings: {fail_on_warnings}"
+            );
+        }
+    }
+
+    #[test]
+    fn pr_titles() {
+        let cases = [
+            // current title, packages, expected title
+            ("foo:0.1.0", "foo:0.1.0", "foo:0.1.0"),
+            ("Add foo", "foo:0.1.0", "foo:0.1.0"),
+            (
+                "foo:0.1.0 (resubmission)",
+                "foo:0.1.0",
+                "foo:0.1.0 (resubmission)",
+            ),
+            (
+                "foo:0.1.0  (resubmission)",
+                "foo:0.1.0",
+                "foo:0.1.0  (resubmission)",
+            ),
+            (
+                "foo:0.1.0 (resubmission)",
+                "foo:0.1.1",
+                "foo:0.1.1 (resubmission)",
+            ),
+            (
+                "foo:0.1.0 — fixes #123",
+                "foo:0.2.0",
+                "foo:0.2.0 — fixes #123",
+            ),
+            ("foo 0.1.0 | docs", "foo:0.1.0", "foo:0.1.0 | docs"),
+            ("foo:0.1.01", "foo:0.1.0", "foo:0.1.0"),
+            ("foo:0.1.0 and bar:0.2.0", "foo:0.1.0", "foo:0.1.0"),
+            ("foo:0.1.0, bar:0.2.0", "foo:0.1.0", "foo:0.1.0"),
+            (
+                "bar:0.2.0 and foo:0.1.0",
+                "bar:0.2.0 and foo:0.1.0",
+                "bar:0.2.0 and foo:0.1.0",
+            ),
+        ];
+        for (current, packages, expected) in cases {
+            assert_eq!(pr_title(current, packages), expected, "{current:?}");
+        }
+    }
+
+    fn packages(specs: &[&str]) -> HashSet<PackageSpec> {
+        specs.iter().map(|spec| spec.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn small_prs_have_no_size_warning() {
+        let touched = packages(&["@preview/foo:0.1.0", "@preview/bar:0.2.0"]);
+        assert_eq!(pr_size_warning(&touched, 2, 2, 1), None);
+    }
+
+    #[test]
+    fn prs_with_many_new_packages_are_reported() {
+        let touched = packages(&["@preview/a:0.1.0", "@preview/b:0.1.0", "@preview/c:0.1.0"]);
+        let warning = pr_size_warning(&touched, 3, 2, 1).unwrap();
+        assert!(warning.starts_with("This pull request adds 3 new packages.\n\n"));
+        assert!(!warning.contains("several versions"));
+    }
+
+    #[test]
+    fn prs_with_many_versions_of_a_package_are_reported() {
+        let touched = packages(&[
+            "@preview/foo:0.1.0",
+            "@preview/foo:0.2.0",
+            "@preview/foo:0.3.0",
+            "@preview/bar:0.1.0",
+            "@preview/bar:0.2.0",
+            "@preview/baz:0.1.0",
+        ]);
+        let warning = pr_size_warning(&touched, 0, 2, 1).unwrap();
+        assert!(warning.starts_with(
+            "This pull request adds several versions of the same package: \
+            @preview/bar (2 versions), @preview/foo (3 versions).\n\n"
+        ));
+        assert!(!warning.contains("new packages"));
+    }
+
+    #[test]
+    fn both_size_warnings_are_combined() {
+        let touched = packages(&["@preview/a:0.1.0", "@preview/a:0.2.0", "@preview/b:0.1.0"]);
+        let warning = pr_size_warning(&touched, 2, 1, 1).unwrap();
+        assert!(warning.starts_with(
+            "This pull request adds 2 new packages.\n\n\
+            This pull request adds several versions of the same package: \
+            @preview/a (2 versions).\n\n"
+        ));
+    }
+
+    #[test]
+    fn size_limits_of_zero_are_disabled() {
+        let touched = packages(&["@preview/a:0.1.0", "@preview/a:0.2.0", "@preview/b:0.1.0"]);
+        assert_eq!(pr_size_warning(&touched, 2, 0, 0), None);
+        assert!(pr_size_warning(&touched, 2, 0, 1)
+            .unwrap()
+            .contains("@preview/a (2 versions)"));
+        assert!(pr_size_warning(&touched, 2, 1, 0)
+            .unwrap()
+            .starts_with("This pull request adds 2 new packages."));
+    }
+
+    fn annotation(path: &str, line: usize, column: usize, message: &str) -> Annotation {
+        Annotation {
+            path: path.to_owned(),
+            start_line: line,
+            end_line: line,
+            start_column: Some(column),
+            end_column: Some(column + 1),
+            annotation_level: AnnotationLevel::Warning,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn only_a_few_similar_annotations_are_kept() {
+        let annotations = (1..=5)
+            .map(|line| annotation("lib.typ", line, 1, &format!("kebab {line}")))
+            .collect();
+        let (kept, folded) = fold_annotations(vec![("kebab-case", annotations)]);
+        assert_eq!(folded, 2);
+        let messages = kept.iter().map(|a| a.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "kebab 1",
+                "kebab 2",
+                "kebab 3\n\n(+2 more like this in this file)"
+            ]
+        );
+    }
+
+    #[test]
+    fn similar_annotations_are_counted_by_file_and_kind() {
+        let (kept, folded) = fold_annotations(vec![
+            (
+                "kebab-case",
+                (1..=4)
+                    .map(|line| annotation("lib.typ", line, 1, "a"))
+                    .collect(),
+            ),
+            ("kebab-case", vec![annotation("utils.typ", 1, 1, "b")]),
+            ("unused-import", vec![annotation("lib.typ", 10, 1, "c")]),
+        ]);
+        assert_eq!(folded, 1);
+        let kept = kept
+            .iter()
+            .map(|a| (a.path.as_str(), a.start_line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kept,
+            [
+                ("lib.typ", 1),
+                ("lib.typ", 2),
+                ("lib.typ", 3),
+                ("utils.typ", 1),
+                ("lib.typ", 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn the_most_important_annotations_are_kept() {
+        // Errors come first, and are kept over the warnings of the same kind.
+        let mut error = annotation("lib.typ", 20, 1, "error");
+        error.annotation_level = AnnotationLevel::Failure;
+        let (kept, _) = fold_annotations(vec![
+            ("code", vec![error]),
+            (
+                "code",
+                (1..=3)
+                    .map(|line| annotation("lib.typ", line, 1, "warning"))
+                    .collect(),
+            ),
+        ]);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].annotation_level, AnnotationLevel::Failure);
+        assert_eq!(kept[0].start_line, 20);
+        assert!(kept[2]
+            .message
+            .ends_with("(+1 more like this in this file)"));
+    }
+
+    #[test]
+    fn annotations_on_the_same_lines_are_merged() {
+        let (kept, folded) = fold_annotations(vec![
+            ("first", vec![annotation("lib.typ", 1, 1, "first")]),
+            ("second", vec![annotation("lib.typ", 1, 1, "second")]),
+            ("third", vec![annotation("lib.typ", 2, 1, "third")]),
+            ("fourth", vec![annotation("lib.typ", 2, 5, "fourth")]),
+            ("fifth", vec![annotation("other.typ", 2, 5, "fifth")]),
+        ]);
+        assert_eq!(folded, 2);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].message, "first\n\n---\n\nsecond");
+        assert_eq!(kept[0].start_column, Some(1));
+        // Columns can't be kept when they differ.
+        assert_eq!(kept[1].message, "third\n\n---\n\nfourth");
+        assert_eq!(kept[1].start_column, None);
+        assert_eq!(kept[1].end_column, None);
+        assert_eq!(kept[2].path, "other.typ");
+    }
+
+    #[tokio::test]
+    async fn health_check() {
+        let url = serve(&FakeApi::start().await).await;
+        let response = reqwest::get(format!("{url}/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -1,34 +1,78 @@
 //! Interact with the GitHub REST API.
 
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 use axum::{extract::FromRequestParts, http::request::Parts};
 use check::MinimalCheckSuite;
+use chrono::{DateTime, TimeDelta, Utc};
 use eyre::Error;
 use jwt_simple::{
     algorithms::{RS256KeyPair, RSAKeyPairLike},
     claims::Claims,
     reexports::coarsetime::Duration,
 };
-use reqwest::{RequestBuilder, Response, StatusCode};
-use serde::Deserialize;
+use parking_lot::Mutex;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use tracing::{debug, warn};
 
-use self::check::{CheckRun, CheckRunId, CheckRunOutput};
+use self::check::{CheckRun, CheckRunConclusion, CheckRunId, CheckRunOutput};
 
 use super::AppState;
+use crate::http::http_client;
 
 pub mod check;
+#[cfg(test)]
+pub mod fake;
 pub mod hook;
 pub mod pr;
-mod user;
+pub mod user;
 
 #[derive(Debug)]
 pub enum ApiError {
     #[allow(dead_code)]
     Reqwest(reqwest::Error),
     Json(serde_json::Error),
-    UnexpectedResponse(String),
+    /// GitHub answered with an error status.
+    Response(Box<ResponseError>),
+    /// GitHub answered successfully, but with content that doesn't make sense.
+    InvalidResponse(String),
+}
+
+impl ApiError {
+    /// The status code of the response, if GitHub answered with an error.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ApiError::Response(e) => Some(e.status),
+            _ => None,
+        }
+    }
+
+    /// Whether the request was rejected because of a (primary or secondary)
+    /// rate limit, in which case it can be retried later.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            ApiError::Response(e) => {
+                e.status == StatusCode::TOO_MANY_REQUESTS
+                    || (e.status == StatusCode::FORBIDDEN
+                        && e.body
+                            .as_ref()
+                            .is_some_and(|body| body.message.to_lowercase().contains("rate limit")))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the requested resource doesn't exist (or isn't visible with
+    /// the current permissions).
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// Whether the app is not allowed to do this request.
+    pub fn is_forbidden(&self) -> bool {
+        self.status() == Some(StatusCode::FORBIDDEN) && !self.is_rate_limited()
+    }
 }
 
 impl std::error::Error for ApiError {
@@ -36,7 +80,7 @@ impl std::error::Error for ApiError {
         match self {
             ApiError::Reqwest(e) => Some(e),
             ApiError::Json(e) => Some(e),
-            ApiError::UnexpectedResponse(_) => None,
+            ApiError::Response(_) | ApiError::InvalidResponse(_) => None,
         }
     }
 }
@@ -46,11 +90,58 @@ impl Display for ApiError {
         match self {
             ApiError::Reqwest(e) => write!(f, "Network error: {:?}", e),
             ApiError::Json(e) => write!(f, "JSON ser/de error: {:?}", e),
-            ApiError::UnexpectedResponse(e) => write!(f, "Unexpected response: {:?}", e),
+            ApiError::Response(e) => e.fmt(f),
+            ApiError::InvalidResponse(e) => write!(f, "Invalid response: {:?}", e),
         }
     }
 }
 
+/// An error status returned by the GitHub API, with the request that caused
+/// it.
+#[derive(Debug)]
+pub struct ResponseError {
+    pub method: Method,
+    /// The path of the endpoint, without the query.
+    pub path: String,
+    pub status: StatusCode,
+    /// The error, as described by GitHub. `None` if the body of the response
+    /// is not in the usual format.
+    pub body: Option<GitHubErrorBody>,
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} failed with {}",
+            self.method, self.path, self.status
+        )?;
+        if let Some(body) = &self.body {
+            write!(f, ": {}", body.message)?;
+            for error in &body.errors {
+                write!(f, "\n  - {}", error)?;
+            }
+            if let Some(url) = &body.documentation_url {
+                write!(f, "\n(see {})", url)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The body of error responses of the GitHub API.
+///
+/// See <https://docs.github.com/en/rest/using-the-rest-api/troubleshooting-the-rest-api>.
+#[derive(Debug, Deserialize)]
+pub struct GitHubErrorBody {
+    pub message: String,
+    /// Details about validation errors, in various formats depending on the
+    /// endpoint.
+    #[serde(default)]
+    pub errors: Vec<serde_json::Value>,
+    pub documentation_url: Option<String>,
+}
+
 impl From<reqwest::Error> for ApiError {
     fn from(value: reqwest::Error) -> Self {
         ApiError::Reqwest(value)
@@ -66,63 +157,208 @@ impl From<serde_json::Error> for ApiError {
 type ApiResult<T> = Result<T, ApiError>;
 
 /// Authentication for the GitHub API using a JWT token.
-pub struct AuthJwt(String);
+pub struct AuthJwt {
+    token: String,
+    /// Installation tokens that were previously created with this app.
+    installation_tokens: InstallationTokenCache,
+}
 
 impl Display for AuthJwt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
+        self.token.fmt(f)
     }
 }
 
+/// Installation tokens, indexed by installation ID and repository name.
+///
+/// Tokens are valid for an hour, they can be reused for many webhook
+/// deliveries instead of creating a new one every time.
+pub type InstallationTokenCache = Arc<Mutex<HashMap<(u64, String), InstallationToken>>>;
+
+/// The permissions requested for installation tokens.
+const INSTALLATION_PERMISSIONS: [(&str, &str); 4] = [
+    ("metadata", "read"),
+    ("issues", "write"),
+    ("pull_requests", "write"),
+    ("checks", "write"),
+];
+
 /// Authentication for the GitHub API using an installation token, that
 /// is scoped to a specific organization or set of repositories, but that
 /// can do more than a [`AuthJwt`] token.
-pub struct AuthInstallation(String);
+#[derive(Clone)]
+pub struct AuthInstallation {
+    /// The current token, shared by all the clones of the client so that
+    /// they all use the new one once it is renewed.
+    token: Arc<Mutex<String>>,
+    /// What is needed to create a new token, if GitHub rejects this one.
+    renewal: Option<Arc<Renewal>>,
+}
 
 impl Display for AuthInstallation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
+        self.token.lock().fmt(f)
+    }
+}
+
+/// What is needed to replace the installation token of a client.
+struct Renewal {
+    app: GitHub<AuthJwt>,
+    installation_id: u64,
+    repository: Repository,
+}
+
+/// Credentials for the GitHub API.
+pub trait Authentication: Display {
+    /// Replaces the credentials after GitHub rejected them, and returns
+    /// whether the request should be sent again.
+    async fn renew(&self) -> ApiResult<bool> {
+        Ok(false)
+    }
+}
+
+impl Authentication for AuthJwt {}
+
+impl Authentication for AuthInstallation {
+    /// Installation tokens can be revoked before they expire, for instance
+    /// when the permissions of the app change. The rejected token is removed
+    /// from the cache, and a new one is created.
+    async fn renew(&self) -> ApiResult<bool> {
+        let Some(renewal) = &self.renewal else {
+            return Ok(false);
+        };
+
+        let rejected = self.token.lock().clone();
+        let cache_key = (renewal.installation_id, renewal.repository.name().0);
+        {
+            let mut cache = renewal.app.auth.installation_tokens.lock();
+            // Another request may have renewed it already.
+            if cache.get(&cache_key).is_some_and(|t| t.token == rejected) {
+                cache.remove(&cache_key);
+            }
+        }
+
+        let token = renewal
+            .app
+            .installation_token(renewal.installation_id, &renewal.repository)
+            .await?;
+        *self.token.lock() = token;
+        Ok(true)
     }
 }
 
 /// A GitHub API client
+#[derive(Clone)]
 pub struct GitHub<A = AuthJwt> {
     auth: A,
     req: reqwest::Client,
+    /// The root of the API, that is somewhere else for GitHub Enterprise
+    /// Server.
+    api_url: String,
 }
 
-impl<A: ToString> GitHub<A> {
-    fn get(&self, url: impl AsRef<str>) -> RequestBuilder {
-        self.with_headers(self.req.get(Self::url(url)))
+impl<A: Authentication> GitHub<A> {
+    fn get(&self, url: impl AsRef<str>) -> ApiRequest<A> {
+        self.request(self.req.get(self.url(url)))
     }
 
-    fn patch(&self, url: impl AsRef<str>) -> RequestBuilder {
-        self.with_headers(self.req.patch(Self::url(url)))
+    fn patch(&self, url: impl AsRef<str>) -> ApiRequest<A> {
+        self.request(self.req.patch(self.url(url)))
     }
 
-    fn post(&self, url: impl AsRef<str>) -> RequestBuilder {
-        self.with_headers(self.req.post(Self::url(url)))
+    fn post(&self, url: impl AsRef<str>) -> ApiRequest<A> {
+        self.request(self.req.post(self.url(url)))
     }
 
-    fn with_headers(&self, req: RequestBuilder) -> RequestBuilder {
-        req.bearer_auth(self.auth.to_string())
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .header("User-Agent", "Typst package check")
+    fn delete(&self, url: impl AsRef<str>) -> ApiRequest<A> {
+        self.request(self.req.delete(self.url(url)))
     }
 
-    fn url<S: AsRef<str>>(path: S) -> String {
-        let u = format!("https://api.github.com/{}", path.as_ref());
+    fn request(&self, req: RequestBuilder) -> ApiRequest<A> {
+        ApiRequest {
+            api: self,
+            builder: req
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28"),
+        }
+    }
+
+    fn url<S: AsRef<str>>(&self, path: S) -> String {
+        let u = format!("{}/{}", self.api_url.trim_end_matches('/'), path.as_ref());
         debug!("API URL: {}", u);
         u
     }
 }
 
+/// A request to the API, that is authenticated when it is sent.
+pub struct ApiRequest<'a, A> {
+    api: &'a GitHub<A>,
+    builder: RequestBuilder,
+}
+
+impl<A: Authentication> ApiRequest<'_, A> {
+    fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        ApiRequest {
+            builder: self.builder.json(json),
+            ..self
+        }
+    }
+
+    fn body(self, body: impl Into<reqwest::Body>) -> Self {
+        ApiRequest {
+            builder: self.builder.body(body),
+            ..self
+        }
+    }
+
+    /// Sends the request, and turns error statuses into
+    /// [`ApiError::Response`].
+    ///
+    /// If the credentials are rejected and can be renewed, the request is
+    /// sent a second time with the new ones.
+    async fn send_checked(self) -> ApiResult<Response> {
+        let retry = self.builder.try_clone();
+        let res = self
+            .builder
+            .bearer_auth(self.api.auth.to_string())
+            .send_checked()
+            .await;
+
+        let Some(retry) = retry else {
+            return res;
+        };
+        match res {
+            Err(e) if e.status() == Some(StatusCode::UNAUTHORIZED) => {
+                if !self.api.auth.renew().await? {
+                    return Err(e);
+                }
+                debug!("Sending the request again with new credentials");
+                retry
+                    .bearer_auth(self.api.auth.to_string())
+                    .send_checked()
+                    .await
+            }
+            res => res,
+        }
+    }
+}
+
 pub trait GitHubAuth {
+    /// Authenticates as an installation of the app, with access to a single
+    /// repository.
     async fn auth_installation(
         self,
         installation: &impl AsInstallation,
+        repository: &Repository,
     ) -> ApiResult<GitHub<AuthInstallation>>;
+
+    /// The login of the bot user of the app, that writes its comments.
+    async fn bot_login(&self) -> ApiResult<String>;
+}
+
+#[derive(Deserialize)]
+struct App {
+    slug: String,
 }
 
 impl GitHubAuth for GitHub<AuthJwt> {
@@ -130,28 +366,92 @@ impl GitHubAuth for GitHub<AuthJwt> {
     async fn auth_installation(
         self,
         installation: &impl AsInstallation,
+        repository: &Repository,
     ) -> ApiResult<GitHub<AuthInstallation>> {
         let installation_id = installation.id();
-        let installation_token: InstallationToken = self
+        let token = self.installation_token(installation_id, repository).await?;
+        Ok(GitHub {
+            req: self.req.clone(),
+            api_url: self.api_url.clone(),
+            auth: AuthInstallation {
+                token: Arc::new(Mutex::new(token)),
+                renewal: Some(Arc::new(Renewal {
+                    app: self,
+                    installation_id,
+                    repository: repository.clone(),
+                })),
+            },
+        })
+    }
+
+    async fn bot_login(&self) -> ApiResult<String> {
+        let app: App = self.get("app").send_checked().await?.parse_json().await?;
+        Ok(format!("{}[bot]", app.slug))
+    }
+}
+
+impl GitHub<AuthJwt> {
+    /// A token for an installation of the app, with access to a single
+    /// repository. Tokens are reused while they remain valid.
+    async fn installation_token(
+        &self,
+        installation_id: u64,
+        repository: &Repository,
+    ) -> ApiResult<String> {
+        let repository_name = repository.name().0;
+        let cache_key = (installation_id, repository_name.clone());
+
+        // Refresh tokens a bit before they expire, so that they remain valid
+        // until the end of the checks.
+        let cached = self
+            .auth
+            .installation_tokens
+            .lock()
+            .get(&cache_key)
+            .filter(|t| t.expires_at - Utc::now() > TimeDelta::minutes(5))
+            .map(|t| t.token.clone());
+        if let Some(token) = cached {
+            debug!("Reusing installation token");
+            return Ok(token);
+        }
+
+        let installation_token: RawInstallationToken = self
             .post(format!("app/installations/{installation_id}/access_tokens"))
             .json(&serde_json::json!({
-                "repositories": ["packages"],
-                "permissions": {
-                    "metadata": "read",
-                    "issues": "write",
-                    "pull_requests": "write",
-                    "checks": "write",
-                }
+                "repositories": [repository_name],
+                "permissions": HashMap::from(INSTALLATION_PERMISSIONS),
             }))
-            .send()
+            .send_checked()
             .await?
             .parse_json()
             .await?;
+        // GitHub ignores the repositories the app is not installed on,
+        // instead of failing.
+        if !installation_token
+            .repositories
+            .iter()
+            .any(|r| r.name == repository_name)
+        {
+            return Err(ApiError::InvalidResponse(format!(
+                "The installation token doesn't give access to {}, \
+                is the app installed on this repository?",
+                repository.full_name()
+            )));
+        }
 
-        Ok(GitHub {
-            req: self.req,
-            auth: AuthInstallation(installation_token.token),
-        })
+        let expires_at = DateTime::parse_from_rfc3339(&installation_token.expires_at)
+            .map_err(|_| ApiError::InvalidResponse(installation_token.expires_at.clone()))?
+            .with_timezone(&Utc);
+
+        self.auth.installation_tokens.lock().insert(
+            cache_key,
+            InstallationToken {
+                token: installation_token.token.clone(),
+                expires_at,
+            },
+        );
+
+        Ok(installation_token.token)
     }
 }
 
@@ -159,9 +459,20 @@ impl GitHubAuth for GitHub<AuthInstallation> {
     async fn auth_installation(
         self,
         _installation: &impl AsInstallation,
+        _repository: &Repository,
     ) -> ApiResult<GitHub<AuthInstallation>> {
         Ok(self)
     }
+
+    async fn bot_login(&self) -> ApiResult<String> {
+        // Only the app itself can be asked about, not its installations.
+        match &self.auth.renewal {
+            Some(renewal) => renewal.app.bot_login().await,
+            None => Err(ApiError::InvalidResponse(
+                "The app of this installation is unknown".to_owned(),
+            )),
+        }
+    }
 }
 
 impl GitHub<AuthInstallation> {
@@ -173,22 +484,18 @@ impl GitHub<AuthInstallation> {
         check_run_name: String,
         head_sha: &str,
     ) -> ApiResult<CheckRun<MinimalCheckSuite>> {
-        let response = self
+        let check_run = self
             .post(format!("repos/{owner}/{repo}/check-runs"))
             .body(serde_json::to_string(&serde_json::json!({
                 "name": check_run_name,
                 "head_sha": head_sha,
                 "status": "in_progress",
             }))?)
-            .send()
+            .send_checked()
+            .await?
+            .parse_json()
             .await?;
-
-        if response.status() != StatusCode::CREATED {
-            return Err(ApiError::UnexpectedResponse(response.text().await?));
-        }
-
-        let result = serde_json::from_str(&response.text().await?)?;
-        Ok(result)
+        Ok(check_run)
     }
 
     #[tracing::instrument(skip(self, output))]
@@ -197,23 +504,72 @@ impl GitHub<AuthInstallation> {
         owner: OwnerId,
         repo: RepoId,
         check_run: CheckRunId,
-        success: bool,
+        conclusion: CheckRunConclusion,
         output: CheckRunOutput<'a>,
     ) -> ApiResult<()> {
         let res = self
             .patch(format!("repos/{owner}/{repo}/check-runs/{check_run}"))
             .body(serde_json::to_string(&serde_json::json!({
                 "status": "completed",
-                "conclusion": if success { "success" } else { "failure" },
+                "conclusion": conclusion,
                 "output": output,
             }))?)
-            .send()
+            .send_checked()
             .await?
             .text()
             .await?;
         debug!("GitHub said: {}", res);
         Ok(())
     }
+
+    /// The branch of a repository to which pull requests are merged by
+    /// default, fetched only if the repository didn't come with it.
+    pub async fn default_branch(&self, repository: &Repository) -> ApiResult<String> {
+        if let Some(branch) = &repository.default_branch {
+            return Ok(branch.clone());
+        }
+
+        #[derive(Deserialize)]
+        struct RepositoryInfo {
+            default_branch: String,
+        }
+
+        let info: RepositoryInfo = self
+            .get(format!(
+                "repos/{}/{}",
+                repository.owner(),
+                repository.name()
+            ))
+            .send_checked()
+            .await?
+            .parse_json()
+            .await?;
+        Ok(info.default_branch)
+    }
+
+    /// Adds annotations to a check run without completing it.
+    ///
+    /// GitHub appends annotations to the existing ones on each update, this
+    /// can thus be used to send more than [`check::MAX_ANNOTATIONS_PER_REQUEST`]
+    /// annotations.
+    #[tracing::instrument(skip(self, output))]
+    pub async fn add_check_run_annotations<'a>(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        check_run: CheckRunId,
+        output: CheckRunOutput<'a>,
+    ) -> ApiResult<()> {
+        self.patch(format!("repos/{owner}/{repo}/check-runs/{check_run}"))
+            .body(serde_json::to_string(&serde_json::json!({
+                "status": "in_progress",
+                "output": output,
+            }))?)
+            .send_checked()
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -224,20 +580,24 @@ impl FromRequestParts<AppState> for GitHub {
         _parts: &'a mut Parts,
         state: &'s AppState,
     ) -> Result<Self, Self::Rejection> {
-        let Ok(private_key) = RS256KeyPair::from_pem(&state.private_key) else {
-            warn!("The private key in the .env file cannot be parsed as PEM.");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        };
+        let private_key = RS256KeyPair::from_pem(&state.private_key).map_err(|e| {
+            warn!("GITHUB_PRIVATE_KEY cannot be parsed as PEM: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
         let claims = Claims::create(Duration::from_mins(10)).with_issuer(&state.app_id);
-        let Ok(token) = private_key.sign(claims) else {
-            warn!("Couldn't sign JWT claims.");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        };
+        let token = private_key.sign(claims).map_err(|e| {
+            warn!("Couldn't sign JWT claims: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
         Ok(Self {
-            auth: AuthJwt(token),
-            req: reqwest::Client::new(),
+            auth: AuthJwt {
+                token,
+                installation_tokens: state.installation_tokens.clone(),
+            },
+            req: http_client().clone(),
+            api_url: state.api_url.clone(),
         })
     }
 }
@@ -260,9 +620,12 @@ impl Display for RepoId {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Repository {
     full_name: String,
+    /// Only known for repositories that come from a webhook payload.
+    #[serde(default)]
+    default_branch: Option<String>,
 }
 
 impl Repository {
@@ -273,9 +636,15 @@ impl Repository {
 
         Ok(Self {
             full_name: name.to_owned(),
+            default_branch: None,
         })
     }
 
+    /// The name of the repository, including its owner.
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
     pub fn owner(&self) -> OwnerId {
         OwnerId(
             self.full_name
@@ -312,9 +681,59 @@ impl AsInstallation for Installation {
     }
 }
 
+/// An installation token, as returned by the API.
+#[derive(Deserialize)]
+struct RawInstallationToken {
+    token: String,
+    expires_at: String,
+    /// The repositories the token gives access to.
+    #[serde(default)]
+    repositories: Vec<RepositoryName>,
+}
+
 #[derive(Deserialize)]
-struct InstallationToken {
+struct RepositoryName {
+    name: String,
+}
+
+pub struct InstallationToken {
     token: String,
+    expires_at: DateTime<Utc>,
+}
+
+trait RequestExt {
+    /// Sends the request, and turns error statuses into [`ApiError::Response`].
+    async fn send_checked(self) -> Result<Response, ApiError>;
+}
+
+impl RequestExt for RequestBuilder {
+    async fn send_checked(self) -> Result<Response, ApiError> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let path = request.url().path().to_owned();
+
+        let response = client.execute(request).await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let bytes = response.bytes().await?;
+        debug!(
+            "{} {} failed with {}: {}",
+            method,
+            path,
+            status,
+            std::str::from_utf8(&bytes).unwrap_or("[INVALID UTF8]")
+        );
+        Err(ApiError::Response(Box::new(ResponseError {
+            method,
+            path,
+            status,
+            body: serde_json::from_slice(&bytes).ok(),
+        })))
+    }
 }
 
 trait JsonExt {
@@ -333,3 +752,284 @@ impl JsonExt for Response {
         Ok(serde_json::from_slice(&bytes)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{
+        extract::State,
+        http::HeaderMap,
+        routing::{get, post},
+        Json, Router,
+    };
+
+    use super::*;
+
+    /// How many times each endpoint of the fake API was called.
+    #[derive(Default)]
+    struct Calls {
+        tokens: AtomicUsize,
+        permissions: AtomicUsize,
+        /// The bodies of the requests for new tokens.
+        token_requests: Mutex<Vec<serde_json::Value>>,
+    }
+
+    async fn create_token(
+        State(calls): State<Arc<Calls>>,
+        Json(body): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        calls.token_requests.lock().push(body);
+        let n = calls.tokens.fetch_add(1, Ordering::SeqCst) + 1;
+        Json(serde_json::json!({
+            "token": format!("fresh-{n}"),
+            "expires_at": (Utc::now() + TimeDelta::hours(1)).to_rfc3339(),
+            "repositories": [{ "name": "packages" }],
+        }))
+    }
+
+    /// Serves a fake API that rejects the `revoked` token, and creates new
+    /// tokens that are rejected too if `reject_all` is set.
+    ///
+    /// Returns the URL of the API.
+    async fn fake_api(reject_all: bool) -> (String, Arc<Calls>) {
+        let calls = Arc::new(Calls::default());
+        let app = Router::new()
+            .route("/app/installations/1/access_tokens", post(create_token))
+            .route(
+                "/app",
+                get(|| async { Json(serde_json::json!({ "slug": "typst-package-check" })) }),
+            )
+            .route(
+                "/repos/typst/packages/collaborators/someone/permission",
+                get(
+                    move |State(calls): State<Arc<Calls>>, headers: HeaderMap| async move {
+                        calls.permissions.fetch_add(1, Ordering::SeqCst);
+                        let auth = headers.get("Authorization").unwrap().to_str().unwrap();
+                        if reject_all || auth == "Bearer revoked" || auth == "Bearer expiring" {
+                            Err(StatusCode::UNAUTHORIZED)
+                        } else {
+                            Ok(Json(serde_json::json!({ "permission": "write" })))
+                        }
+                    },
+                ),
+            )
+            .with_state(calls.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, calls)
+    }
+
+    /// An app client, with a token for the repository in its cache.
+    fn app_with_cached_token(
+        api_url: String,
+        token: &str,
+        expires_in: TimeDelta,
+    ) -> (GitHub<AuthJwt>, InstallationTokenCache) {
+        let cache = InstallationTokenCache::default();
+        cache.lock().insert(
+            (1, "packages".to_owned()),
+            InstallationToken {
+                token: token.to_owned(),
+                expires_at: Utc::now() + expires_in,
+            },
+        );
+        let app = GitHub {
+            auth: AuthJwt {
+                token: "jwt".to_owned(),
+                installation_tokens: cache.clone(),
+            },
+            req: reqwest::Client::new(),
+            api_url,
+        };
+        (app, cache)
+    }
+
+    async fn user_permission(app: GitHub<AuthJwt>) -> ApiResult<user::Permission> {
+        let repository = Repository::new("typst/packages").unwrap();
+        let api = app
+            .auth_installation(&Installation { id: 1 }, &repository)
+            .await?;
+        api.user_permission(repository.owner(), repository.name(), "someone")
+            .await
+    }
+
+    #[tokio::test]
+    async fn revoked_tokens_are_renewed_once() {
+        let (url, calls) = fake_api(false).await;
+        let (app, cache) = app_with_cached_token(url, "revoked", TimeDelta::hours(1));
+        let permission = user_permission(app).await.unwrap();
+        assert!(permission.can_write());
+        assert_eq!(calls.tokens.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.permissions.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.lock()[&(1, "packages".to_owned())].token, "fresh-1");
+    }
+
+    #[tokio::test]
+    async fn renewed_tokens_can_be_rejected_too() {
+        let (url, calls) = fake_api(true).await;
+        let (app, _) = app_with_cached_token(url, "revoked", TimeDelta::hours(1));
+        let error = user_permission(app).await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(calls.tokens.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.permissions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn valid_tokens_are_reused() {
+        let (url, calls) = fake_api(false).await;
+        let (app, _) = app_with_cached_token(url, "cached", TimeDelta::hours(1));
+        assert!(user_permission(app).await.unwrap().can_write());
+        assert_eq!(calls.tokens.load(Ordering::SeqCst), 0);
+        assert_eq!(calls.permissions.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn tokens_about_to_expire_are_refreshed() {
+        let (url, calls) = fake_api(false).await;
+        let (app, cache) = app_with_cached_token(url, "expiring", TimeDelta::minutes(4));
+        assert!(user_permission(app).await.unwrap().can_write());
+        // The new token is used directly, the expiring one is never sent.
+        assert_eq!(calls.tokens.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.permissions.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.lock()[&(1, "packages".to_owned())].token, "fresh-1");
+    }
+
+    #[tokio::test]
+    async fn tokens_are_requested_for_the_repository_of_the_event() {
+        let (url, calls) = fake_api(false).await;
+        let (app, _) = app_with_cached_token(url, "expiring", TimeDelta::minutes(1));
+        app.installation_token(1, &Repository::new("typst/packages").unwrap())
+            .await
+            .unwrap();
+        // The cached token is only for the `packages` repository.
+        let error = app
+            .installation_token(1, &Repository::new("someone/fork").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ApiError::InvalidResponse(_)));
+
+        let requests = calls.token_requests.lock();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["repositories"], serde_json::json!(["packages"]));
+        assert_eq!(requests[1]["repositories"], serde_json::json!(["fork"]));
+        for (permission, access) in INSTALLATION_PERMISSIONS {
+            assert_eq!(requests[0]["permissions"][permission], access);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_bot_is_named_after_the_app() {
+        let (url, _) = fake_api(false).await;
+        let (app, _) = app_with_cached_token(url, "cached", TimeDelta::hours(1));
+        assert_eq!(app.bot_login().await.unwrap(), "typst-package-check[bot]");
+        let api = app
+            .auth_installation(
+                &Installation { id: 1 },
+                &Repository::new("typst/packages").unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(api.bot_login().await.unwrap(), "typst-package-check[bot]");
+    }
+
+    /// The error returned for a response from GitHub.
+    async fn error_for(status: StatusCode, body: &'static str) -> ApiError {
+        let app = Router::new().route(
+            "/error",
+            get(move || async move { (status, [("Content-Type", "application/json")], body) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/error", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        reqwest::Client::new()
+            .get(url)
+            .send_checked()
+            .await
+            .unwrap_err()
+    }
+
+    // The bodies below were returned by the GitHub API.
+
+    #[tokio::test]
+    async fn primary_rate_limits() {
+        let error = error_for(
+            StatusCode::FORBIDDEN,
+            r#"{"message":"API rate limit exceeded for installation ID 12345678.","documentation_url":"https://docs.github.com/rest/overview/rate-limits-for-the-rest-api"}"#,
+        )
+        .await;
+        assert!(error.is_rate_limited());
+        assert!(!error.is_forbidden());
+        assert!(!error.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn secondary_rate_limits() {
+        let error = error_for(
+            StatusCode::FORBIDDEN,
+            r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again. If you reach out to GitHub Support for help, please include the request ID 0000:1111:2222:3333:44444444.","documentation_url":"https://docs.github.com/free-pro-team@latest/rest/overview/rate-limits-for-the-rest-api#about-secondary-rate-limits"}"#,
+        )
+        .await;
+        assert!(error.is_rate_limited());
+        assert!(!error.is_forbidden());
+
+        let error = error_for(
+            StatusCode::TOO_MANY_REQUESTS,
+            r#"{"message":"You have exceeded a secondary rate limit and have been temporarily blocked from content creation. Please retry your request again later.","documentation_url":"https://docs.github.com/rest/overview/rate-limits-for-the-rest-api#about-secondary-rate-limits"}"#,
+        )
+        .await;
+        assert!(error.is_rate_limited());
+    }
+
+    #[tokio::test]
+    async fn missing_permissions() {
+        let error = error_for(
+            StatusCode::FORBIDDEN,
+            r#"{"message":"Resource not accessible by integration","documentation_url":"https://docs.github.com/rest/issues/labels#add-labels-to-an-issue","status":"403"}"#,
+        )
+        .await;
+        assert!(error.is_forbidden());
+        assert!(!error.is_rate_limited());
+        assert!(!error.is_not_found());
+        let ApiError::Response(response) = &error else {
+            panic!("expected an error response");
+        };
+        assert_eq!(
+            response.body.as_ref().unwrap().message,
+            "Resource not accessible by integration"
+        );
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let error = error_for(
+            StatusCode::NOT_FOUND,
+            r#"{"message":"Not Found","documentation_url":"https://docs.github.com/rest/issues/labels#remove-a-label-from-an-issue","status":"404"}"#,
+        )
+        .await;
+        assert!(error.is_not_found());
+        assert!(!error.is_forbidden());
+        assert!(!error.is_rate_limited());
+    }
+
+    #[tokio::test]
+    async fn unusual_error_bodies() {
+        let error = error_for(StatusCode::FORBIDDEN, "<html>Forbidden</html>").await;
+        assert!(error.is_forbidden());
+        let ApiError::Response(response) = &error else {
+            panic!("expected an error response");
+        };
+        assert!(response.body.is_none());
+
+        let error = error_for(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            r#"{"message":"Validation Failed","errors":[{"resource":"Label","code":"invalid","field":"name"}],"documentation_url":"https://docs.github.com/rest/issues/labels#add-labels-to-an-issue","status":"422"}"#,
+        )
+        .await;
+        assert_eq!(error.status(), Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!error.is_forbidden() && !error.is_not_found() && !error.is_rate_limited());
+        assert!(error.to_string().contains("Validation Failed"));
+    }
+}
@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
 use super::pr::AnyPullRequest;
 
@@ -67,14 +67,38 @@ impl<S> CheckRun<S> {
     }
 }
 
+/// The final result of a check run.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckRunConclusion {
+    /// No issues were found.
+    Success,
+    /// Only warnings were found.
+    Neutral,
+    /// Errors were found, or the checks could not run.
+    Failure,
+    /// The results are outdated, as newer commits were pushed.
+    Cancelled,
+}
+
+/// The maximum number of annotations GitHub accepts in a single check run
+/// update.
+pub const MAX_ANNOTATIONS_PER_REQUEST: usize = 50;
+
+/// The output of a check run.
+///
+/// Texts that are longer than what GitHub accepts are truncated when
+/// serialized, otherwise the whole update would be rejected.
 #[derive(Debug, Serialize)]
 pub struct CheckRunOutput<'a> {
+    #[serde(serialize_with = "truncate_title")]
     pub title: &'a str,
+    #[serde(serialize_with = "truncate_summary")]
     pub summary: &'a str,
     pub annotations: &'a [Annotation],
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Annotation {
     pub path: String,
     pub start_line: usize,
@@ -84,12 +108,100 @@ pub struct Annotation {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end_column: Option<usize>,
     pub annotation_level: AnnotationLevel,
+    #[serde(serialize_with = "truncate_message")]
     pub message: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AnnotationLevel {
+    Notice,
     Warning,
     Failure,
 }
+
+/// Maximum length of a check run title.
+const MAX_TITLE_LEN: usize = 255;
+/// Maximum length of a check run summary.
+const MAX_SUMMARY_LEN: usize = 65535;
+/// Maximum length of an annotation message.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+fn truncate_title<S: Serializer>(title: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&truncate(title, MAX_TITLE_LEN))
+}
+
+fn truncate_summary<S: Serializer>(summary: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&truncate(summary, MAX_SUMMARY_LEN))
+}
+
+fn truncate_message<S: Serializer>(message: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&truncate(message, MAX_MESSAGE_LEN))
+}
+
+/// Shortens a text to at most `max_len` bytes, indicating that it was
+/// truncated if needed.
+///
+/// Lengths are counted in bytes, which is always at least the number of
+/// characters GitHub counts.
+fn truncate(text: &str, max_len: usize) -> Cow<str> {
+    const ELLIPSIS: &str = "… (truncated)";
+
+    if text.len() <= max_len {
+        return Cow::Borrowed(text);
+    }
+
+    let mut end = max_len - ELLIPSIS.len();
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}{ELLIPSIS}", &text[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_texts_are_kept() {
+        assert_eq!(truncate("All good", MAX_TITLE_LEN), "All good");
+        let exact = "a".repeat(MAX_TITLE_LEN);
+        assert_eq!(truncate(&exact, MAX_TITLE_LEN), exact);
+    }
+
+    #[test]
+    fn long_texts_are_truncated_on_char_boundaries() {
+        let text = "é".repeat(MAX_TITLE_LEN);
+        let truncated = truncate(&text, MAX_TITLE_LEN);
+        assert!(truncated.len() <= MAX_TITLE_LEN);
+        assert!(truncated.ends_with("… (truncated)"));
+        assert!(truncated.starts_with("éé"));
+    }
+
+    #[test]
+    fn serialized_outputs_respect_the_limits() {
+        let title = "t".repeat(MAX_TITLE_LEN + 1);
+        let summary = "s".repeat(MAX_SUMMARY_LEN * 2);
+        let annotations = [Annotation {
+            path: "packages/preview/foo/0.1.0/lib.typ".to_owned(),
+            start_line: 1,
+            end_line: 1,
+            start_column: None,
+            end_column: None,
+            annotation_level: AnnotationLevel::Failure,
+            message: "m".repeat(MAX_MESSAGE_LEN + 10),
+        }];
+        let output = CheckRunOutput {
+            title: &title,
+            summary: &summary,
+            annotations: &annotations,
+        };
+
+        let json = serde_json::to_value(&output).unwrap();
+        let len = |value: &serde_json::Value| value.as_str().unwrap().len();
+        assert!(len(&json["title"]) <= MAX_TITLE_LEN);
+        assert!(len(&json["summary"]) <= MAX_SUMMARY_LEN);
+        assert!(len(&json["annotations"][0]["message"]) <= MAX_MESSAGE_LEN);
+        assert!(json["summary"].as_str().unwrap().ends_with("… (truncated)"));
+    }
+}
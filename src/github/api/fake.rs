@@ -0,0 +1,123 @@
+//! A fake GitHub API for the tests, that records the requests it receives.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{Method, StatusCode, Uri},
+    Json, Router,
+};
+use parking_lot::Mutex;
+use serde_json::Value;
+
+use super::{AuthInstallation, GitHub};
+
+/// A request received by the fake API.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    /// The path of the endpoint, without the leading slash nor the query.
+    pub path: String,
+    /// The JSON body of the request, `null` if there is none.
+    pub body: Value,
+}
+
+type Responder = Box<dyn Fn(&Value) -> (StatusCode, Value) + Send + Sync>;
+
+#[derive(Default)]
+struct Endpoints {
+    requests: Mutex<Vec<Request>>,
+    responses: Mutex<HashMap<(Method, String), Responder>>,
+}
+
+/// A fake GitHub API, served on a random port.
+///
+/// Endpoints without a configured response answer successfully, with an
+/// empty list to `GET` requests (so that listing anything finds nothing) and
+/// with an empty object otherwise.
+#[derive(Clone)]
+pub struct FakeApi {
+    pub url: String,
+    endpoints: Arc<Endpoints>,
+}
+
+impl FakeApi {
+    pub async fn start() -> Self {
+        let endpoints = Arc::new(Endpoints::default());
+        let app = Router::new().fallback(handle).with_state(endpoints.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        FakeApi { url, endpoints }
+    }
+
+    /// Answers the requests to an endpoint with a fixed response.
+    pub fn respond(&self, method: Method, path: &str, status: StatusCode, body: Value) {
+        self.respond_with(method, path, move |_| (status, body.clone()));
+    }
+
+    /// Answers the requests to an endpoint depending on their body.
+    pub fn respond_with(
+        &self,
+        method: Method,
+        path: &str,
+        responder: impl Fn(&Value) -> (StatusCode, Value) + Send + Sync + 'static,
+    ) {
+        self.endpoints
+            .responses
+            .lock()
+            .insert((method, path.to_owned()), Box::new(responder));
+    }
+
+    /// All the requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.endpoints.requests.lock().clone()
+    }
+
+    /// The bodies of the requests received by an endpoint, in order.
+    pub fn requests_to(&self, method: Method, path: &str) -> Vec<Value> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.method == method && r.path == path)
+            .map(|r| r.body)
+            .collect()
+    }
+
+    /// A client for this API, authenticated as an installation with a token
+    /// that is never renewed.
+    pub fn client(&self) -> GitHub<AuthInstallation> {
+        GitHub {
+            auth: AuthInstallation {
+                token: Arc::new(Mutex::new("installation-token".to_owned())),
+                renewal: None,
+            },
+            req: reqwest::Client::new(),
+            api_url: self.url.clone(),
+        }
+    }
+}
+
+async fn handle(
+    State(endpoints): State<Arc<Endpoints>>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    let path = uri.path().trim_start_matches('/').to_owned();
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let (status, response) = match endpoints
+        .responses
+        .lock()
+        .get(&(method.clone(), path.clone()))
+    {
+        Some(responder) => responder(&body),
+        None if method == Method::GET => (StatusCode::OK, Value::Array(Vec::new())),
+        None => (StatusCode::OK, Value::Object(Default::default())),
+    };
+    endpoints
+        .requests
+        .lock()
+        .push(Request { method, path, body });
+    (status, Json(response))
+}
@@ -1,15 +1,20 @@
-use axum::extract::{FromRequest, Request};
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::request::Parts,
+};
 use hmac::Mac;
 use reqwest::StatusCode;
-use serde::Deserialize;
+use serde::{de::IgnoredAny, Deserialize};
 use tracing::{debug, trace, warn};
 
 use crate::github::AppState;
 
 use super::{
     check::{CheckRun, CheckRunAction, CheckSuite, CheckSuiteAction},
-    pr::PullRequest,
-    AsInstallation, Installation,
+    pr::{Comment, PullRequest},
+    AsInstallation, Installation, Repository,
 };
 
 #[derive(Debug)]
@@ -18,6 +23,7 @@ pub enum HookPayload {
     CheckSuite(CheckSuitePayload),
     CheckRun(CheckRunPayload),
     PullRequest(PullRequestPayload),
+    IssueComment(IssueCommentPayload),
 }
 
 impl HookPayload {
@@ -27,6 +33,18 @@ impl HookPayload {
             HookPayload::Installation(i) => &i.installation,
             HookPayload::CheckRun(cr) => &cr.installation,
             HookPayload::PullRequest(pr) => &pr.installation,
+            HookPayload::IssueComment(ic) => &ic.installation,
+        }
+    }
+
+    /// The repository in which the event happened, if any.
+    pub fn repository(&self) -> Option<&Repository> {
+        match self {
+            HookPayload::CheckSuite(cs) => Some(&cs.repository),
+            HookPayload::Installation(_) => None,
+            HookPayload::CheckRun(cr) => Some(&cr.repository),
+            HookPayload::PullRequest(pr) => Some(&pr.repository),
+            HookPayload::IssueComment(ic) => Some(&ic.repository),
         }
     }
 }
@@ -37,6 +55,25 @@ impl AsInstallation for HookPayload {
     }
 }
 
+/// The unique identifier of a webhook delivery, read from the
+/// `X-GitHub-Delivery` header.
+pub struct DeliveryId(pub Option<String>);
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for DeliveryId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(DeliveryId(
+            parts
+                .headers
+                .get("X-GitHub-Delivery")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_owned()),
+        ))
+    }
+}
+
 /// Request extractor that reads and check a GitHub hook payload.
 #[async_trait::async_trait]
 impl FromRequest<AppState> for HookPayload {
@@ -50,8 +87,14 @@ impl FromRequest<AppState> for HookPayload {
             .map(|v| v.as_bytes().to_owned());
         debug!("Event type is {:?}", event_type);
 
-        let Some(their_signature_header) = req.headers().get("X-Hub-Signature") else {
-            return Err((StatusCode::UNAUTHORIZED, "X-Hub-Signature is missing"));
+        // Prefer the SHA-256 signature, but accept the legacy SHA-1 one if
+        // it is the only one that was provided.
+        let Some(their_signature_header) = req
+            .headers()
+            .get("X-Hub-Signature-256")
+            .or_else(|| req.headers().get("X-Hub-Signature"))
+        else {
+            return Err((StatusCode::UNAUTHORIZED, "X-Hub-Signature-256 is missing"));
         };
         let their_signature_header = their_signature_header
             .to_str()
@@ -62,7 +105,7 @@ impl FromRequest<AppState> for HookPayload {
             return Err((StatusCode::BAD_REQUEST, "Malformed signature header"));
         };
 
-        if method != "sha1" {
+        if method != "sha256" && method != "sha1" {
             warn!(
                 "A hook with a {} signature was received, and rejected",
                 method
@@ -79,28 +122,38 @@ impl FromRequest<AppState> for HookPayload {
 
         trace!("Webhook payload was: {}", raw_payload);
 
-        let our_digest = {
-            let Ok(mut mac) = hmac::Hmac::<sha1::Sha1>::new_from_slice(&state.webhook_secret)
-            else {
-                warn!("Webhook secret is invalid.");
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Server is not correctly configured.",
-                ));
-            };
-            mac.update(raw_payload.as_bytes());
-            mac
-        };
         // GitHub provides their hash as a hexadecimal string.
         let parsed_digest: Vec<_> = (0..their_digest.len() / 2)
             .filter_map(|idx| {
-                let slice = &their_digest[idx * 2..idx * 2 + 2];
+                let slice = their_digest.get(idx * 2..idx * 2 + 2)?;
                 u8::from_str_radix(slice, 16).ok()
             })
             .collect();
-        if our_digest.verify_slice(&parsed_digest).is_err() {
-            debug!("Invalid hook signature");
-            return Err((StatusCode::UNAUTHORIZED, "Invalid hook signature"));
+        // `verify_slice` compares digests in constant time.
+        let verified = if method == "sha256" {
+            hmac::Hmac::<sha2::Sha256>::new_from_slice(&state.webhook_secret).map(|mut mac| {
+                mac.update(raw_payload.as_bytes());
+                mac.verify_slice(&parsed_digest).is_ok()
+            })
+        } else {
+            hmac::Hmac::<sha1::Sha1>::new_from_slice(&state.webhook_secret).map(|mut mac| {
+                mac.update(raw_payload.as_bytes());
+                mac.verify_slice(&parsed_digest).is_ok()
+            })
+        };
+        match verified {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("Invalid hook signature");
+                return Err((StatusCode::UNAUTHORIZED, "Invalid hook signature"));
+            }
+            Err(_) => {
+                warn!("Webhook secret is invalid.");
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Server is not correctly configured.",
+                ));
+            }
         }
 
         macro_rules! try_deser {
@@ -117,6 +170,7 @@ impl FromRequest<AppState> for HookPayload {
             Some(b"check_suite") => try_deser!(CheckSuite, &raw_payload),
             Some(b"check_run") => try_deser!(CheckRun, &raw_payload),
             Some(b"pull_request") => try_deser!(PullRequest, &raw_payload),
+            Some(b"issue_comment") => try_deser!(IssueComment, &raw_payload),
             Some(x) => {
                 debug!(
                     "Uknown event type: {}",
@@ -140,6 +194,7 @@ pub struct CheckSuitePayload {
     pub action: CheckSuiteAction,
     pub installation: Installation,
     pub check_suite: CheckSuite,
+    pub repository: Repository,
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,6 +202,7 @@ pub struct CheckRunPayload {
     pub installation: Installation,
     pub action: CheckRunAction,
     pub check_run: CheckRun,
+    pub repository: Repository,
 }
 
 #[derive(Debug, Deserialize)]
@@ -154,11 +210,109 @@ pub struct PullRequestPayload {
     pub installation: Installation,
     pub action: PullRequestAction,
     pub pull_request: PullRequest,
+    pub repository: Repository,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PullRequestAction {
+    /// A pull request was created
     Opened,
+    /// New commits were pushed to the head branch of the pull request
     Synchronize,
+    /// A closed pull request was reopened
+    Reopened,
+    /// A draft pull request was marked as ready for review
+    ReadyForReview,
+    /// Any other action, that doesn't require checks to run again
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueCommentPayload {
+    pub installation: Installation,
+    pub action: IssueCommentAction,
+    pub issue: Issue,
+    pub comment: Comment,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCommentAction {
+    Created,
+    Edited,
+    Deleted,
+}
+
+/// An issue, or a pull request, as GitHub considers them to be issues too.
+#[derive(Debug, Deserialize)]
+pub struct Issue {
+    pub number: usize,
+    /// Only present if this issue is a pull request.
+    pub pull_request: Option<IgnoredAny>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    /// The secret of the example in GitHub's documentation.
+    const SECRET: &str = "It's a Secret to Everybody";
+    const PAYLOAD: &str = r#"{"installation":{"id":1}}"#;
+    const SHA256: &str = "sha256=07499767a4ab2803db3657a29cd805343bc047913f55500668fe9161fd92c1bb";
+    const SHA1: &str = "sha1=59d08c0b57a218fb846fd34df6532f93c2039190";
+
+    async fn extract(
+        headers: &[(&str, &str)],
+        payload: &str,
+    ) -> Result<HookPayload, (StatusCode, &'static str)> {
+        let mut req = axum::http::Request::builder().header("X-GitHub-Event", "installation");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let req = req.body(Body::from(payload.to_owned())).unwrap();
+        HookPayload::from_request(req, &AppState::for_tests(SECRET)).await
+    }
+
+    #[tokio::test]
+    async fn sha256_signatures_are_verified() {
+        let payload = extract(&[("X-Hub-Signature-256", SHA256)], PAYLOAD).await;
+        assert!(matches!(payload, Ok(HookPayload::Installation(_))));
+    }
+
+    #[tokio::test]
+    async fn sha1_signatures_are_verified() {
+        let payload = extract(&[("X-Hub-Signature", SHA1)], PAYLOAD).await;
+        assert!(matches!(payload, Ok(HookPayload::Installation(_))));
+    }
+
+    #[tokio::test]
+    async fn sha256_signatures_are_preferred() {
+        let wrong_sha1 = "sha1=0000000000000000000000000000000000000000";
+        let headers = [
+            ("X-Hub-Signature-256", SHA256),
+            ("X-Hub-Signature", wrong_sha1),
+        ];
+        let payload = extract(&headers, PAYLOAD).await;
+        assert!(matches!(payload, Ok(HookPayload::Installation(_))));
+    }
+
+    #[tokio::test]
+    async fn tampered_payloads_are_rejected() {
+        let tampered = r#"{"installation":{"id":2}}"#;
+        for header in [("X-Hub-Signature-256", SHA256), ("X-Hub-Signature", SHA1)] {
+            let payload = extract(&[header], tampered).await;
+            assert_eq!(payload.unwrap_err().0, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn unsigned_payloads_are_rejected() {
+        let payload = extract(&[], PAYLOAD).await;
+        assert_eq!(payload.unwrap_err().0, StatusCode::UNAUTHORIZED);
+    }
 }
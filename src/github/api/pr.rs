@@ -20,7 +20,7 @@ impl MinimalPullRequest {
             repo = repo,
             pull_number = self.number
         ))
-        .send()
+        .send_checked()
         .await?
         .parse_json()
         .await
@@ -34,6 +34,47 @@ pub struct PullRequest {
     pub title: String,
     pub body: String,
     pub user: User,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub state: PullRequestState,
+    /// `None` if the pull request was not merged (yet).
+    #[serde(default)]
+    pub merged_at: Option<String>,
+    #[serde(default)]
+    pub merge_commit_sha: Option<String>,
+    /// The branch the pull request is (or was) to be merged into.
+    pub base: Option<Branch>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PullRequestState {
+    #[default]
+    Open,
+    Closed,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Branch {
+    #[serde(rename = "ref")]
+    pub name: String,
+}
+
+impl PullRequest {
+    /// Whether this pull request was merged into a given branch.
+    pub fn merged_into(&self, branch: &str) -> bool {
+        self.state == PullRequestState::Closed
+            && self.merged_at.is_some()
+            && self.base.as_ref().is_some_and(|base| base.name == branch)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Label {
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -62,10 +103,34 @@ pub struct Commit {
     pub sha: String,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    pub body: String,
+    pub user: User,
+}
+
+/// A file changed by a pull request.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PullRequestFile {
+    pub filename: String,
+    /// The diff of the file. Missing for binary or very large files.
+    pub patch: Option<String>,
+}
+
+/// A comment on a line of the diff of a pull request.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReviewComment {
+    pub path: String,
+    /// `None` if the line is not part of the diff anymore.
+    pub line: Option<usize>,
+    pub body: String,
+}
+
 #[derive(Serialize)]
 pub struct PullRequestUpdate {
-    pub title: String,
-    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub body: Option<String>,
 }
@@ -80,7 +145,7 @@ impl GitHub<AuthInstallation> {
     ) -> Result<(), ApiError> {
         self.patch(format!("repos/{}/{}/issues/{}", owner, repo, pr))
             .json(&update)
-            .send()
+            .send_checked()
             .await?
             .parse_json::<serde_json::Value>()
             .await?;
@@ -95,13 +160,119 @@ impl GitHub<AuthInstallation> {
         commit: String,
     ) -> Result<Vec<PullRequest>, ApiError> {
         self.get(format!("repos/{owner}/{repo}/commits/{commit}/pulls"))
-            .send()
+            .send_checked()
             .await?
             .parse_json()
             .await
             .map_err(ApiError::from)
     }
 
+    /// Adds a reaction (for instance `+1`) to a comment.
+    pub async fn react_to_comment(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        comment: u64,
+        reaction: &str,
+    ) -> Result<(), ApiError> {
+        self.post(format!(
+            "repos/{owner}/{repo}/issues/comments/{comment}/reactions"
+        ))
+        .json(&serde_json::json!({
+            "content": reaction
+        }))
+        .send_checked()
+        .await?
+        .parse_json::<serde_json::Value>()
+        .await?;
+
+        Ok(())
+    }
+
+    /// Adds labels to a pull request, keeping the existing ones.
+    pub async fn add_labels(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+        labels: Vec<String>,
+    ) -> Result<(), ApiError> {
+        self.post(format!("repos/{owner}/{repo}/issues/{pr}/labels"))
+            .json(&serde_json::json!({
+                "labels": labels
+            }))
+            .send_checked()
+            .await?
+            .parse_json::<serde_json::Value>()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_label(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+        label: &str,
+    ) -> Result<(), ApiError> {
+        self.delete(format!("repos/{owner}/{repo}/issues/{pr}/labels/{label}"))
+            .send_checked()
+            .await?
+            .parse_json::<serde_json::Value>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists all the comments of a pull request, oldest first.
+    pub async fn list_pr_comments(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+    ) -> Result<Vec<Comment>, ApiError> {
+        const PER_PAGE: usize = 100;
+
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let page: Vec<Comment> = self
+                .get(format!(
+                    "repos/{owner}/{repo}/issues/{pr}/comments?per_page={PER_PAGE}&page={page}"
+                ))
+                .send_checked()
+                .await?
+                .parse_json()
+                .await?;
+            let last_page = page.len() < PER_PAGE;
+            comments.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(comments)
+    }
+
+    pub async fn update_comment(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        comment: u64,
+        message: String,
+    ) -> Result<(), ApiError> {
+        self.patch(format!("repos/{owner}/{repo}/issues/comments/{comment}"))
+            .json(&serde_json::json!({
+                "body": message
+            }))
+            .send_checked()
+            .await?
+            .parse_json::<serde_json::Value>()
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn post_pr_comment(
         &self,
         owner: OwnerId,
@@ -113,7 +284,93 @@ impl GitHub<AuthInstallation> {
             .json(&serde_json::json!({
                 "body": message
             }))
-            .send()
+            .send_checked()
+            .await?
+            .parse_json::<serde_json::Value>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists the files changed by a pull request.
+    ///
+    /// GitHub doesn't list more than 3000 files.
+    pub async fn list_pr_files(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+    ) -> Result<Vec<PullRequestFile>, ApiError> {
+        const PER_PAGE: usize = 100;
+
+        let mut files = Vec::new();
+        for page in 1..=30 {
+            let page: Vec<PullRequestFile> = self
+                .get(format!(
+                    "repos/{owner}/{repo}/pulls/{pr}/files?per_page={PER_PAGE}&page={page}"
+                ))
+                .send_checked()
+                .await?
+                .parse_json()
+                .await?;
+            let last_page = page.len() < PER_PAGE;
+            files.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Lists the review comments of a pull request, oldest first.
+    pub async fn list_review_comments(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+    ) -> Result<Vec<ReviewComment>, ApiError> {
+        const PER_PAGE: usize = 100;
+
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let page: Vec<ReviewComment> = self
+                .get(format!(
+                    "repos/{owner}/{repo}/pulls/{pr}/comments?per_page={PER_PAGE}&page={page}"
+                ))
+                .send_checked()
+                .await?
+                .parse_json()
+                .await?;
+            let last_page = page.len() < PER_PAGE;
+            comments.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(comments)
+    }
+
+    /// Comments on a line of the new version of a file, in the diff of a
+    /// pull request.
+    pub async fn create_review_comment(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        pr: usize,
+        commit_sha: &str,
+        comment: &ReviewComment,
+    ) -> Result<(), ApiError> {
+        self.post(format!("repos/{owner}/{repo}/pulls/{pr}/comments"))
+            .json(&serde_json::json!({
+                "body": comment.body,
+                "commit_id": commit_sha,
+                "path": comment.path,
+                "line": comment.line,
+                "side": "RIGHT",
+            }))
+            .send_checked()
             .await?
             .parse_json::<serde_json::Value>()
             .await?;
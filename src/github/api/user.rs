@@ -1,6 +1,49 @@
 use serde::Deserialize;
 
+use super::{ApiError, AuthInstallation, GitHub, JsonExt, OwnerId, RepoId};
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct User {
     pub login: String,
 }
+
+/// The permission of a user on a repository.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Admin,
+    Write,
+    Read,
+    None,
+}
+
+impl Permission {
+    /// Whether this permission allows to push to the repository.
+    pub fn can_write(&self) -> bool {
+        matches!(self, Permission::Admin | Permission::Write)
+    }
+}
+
+#[derive(Deserialize)]
+struct CollaboratorPermission {
+    permission: Permission,
+}
+
+impl GitHub<AuthInstallation> {
+    pub async fn user_permission(
+        &self,
+        owner: OwnerId,
+        repo: RepoId,
+        user: &str,
+    ) -> Result<Permission, ApiError> {
+        let permission: CollaboratorPermission = self
+            .get(format!(
+                "repos/{owner}/{repo}/collaborators/{user}/permission"
+            ))
+            .send_checked()
+            .await?
+            .parse_json()
+            .await?;
+        Ok(permission.permission)
+    }
+}
@@ -0,0 +1,204 @@
+//! Configuration of the server, read from the environment.
+
+use std::fmt::Display;
+
+use jwt_simple::algorithms::RS256KeyPair;
+
+use super::AppState;
+
+/// A problem with the configuration of the server.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A required environment variable is not set.
+    Missing(&'static str),
+    /// An environment variable is set, but to an invalid value.
+    Invalid { var: &'static str, reason: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Missing(var) => write!(f, "{var} is not set."),
+            ConfigError::Invalid { var, reason } => write!(f, "{var} is set but {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The login of the bot of the app in tests, that don't ask GitHub for it.
+#[cfg(test)]
+pub const BOT_LOGIN: &str = "typst-package-check[bot]";
+
+impl AppState {
+    /// Reads the configuration from the environment.
+    ///
+    /// All the problems are reported at once, instead of just the first one.
+    pub fn try_read() -> Result<Self, Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut required = |var: &'static str| match std::env::var(var) {
+            Ok(value) if !value.trim().is_empty() => value,
+            _ => {
+                errors.push(ConfigError::Missing(var));
+                String::new()
+            }
+        };
+
+        let webhook_secret = required("GITHUB_WEBHOOK_SECRET");
+        let private_key = required("GITHUB_PRIVATE_KEY");
+        let app_id = required("GITHUB_APP_IDENTIFIER");
+        let git_dir = required("PACKAGES_DIR");
+
+        let private_key = if private_key.is_empty() {
+            private_key
+        } else {
+            normalize_private_key(&private_key).unwrap_or_else(|reason| {
+                errors.push(ConfigError::Invalid {
+                    var: "GITHUB_PRIVATE_KEY",
+                    reason,
+                });
+                String::new()
+            })
+        };
+
+        if !app_id.is_empty() && app_id.parse::<u64>().is_err() {
+            errors.push(ConfigError::Invalid {
+                var: "GITHUB_APP_IDENTIFIER",
+                reason: format!(
+                    "is not a number: {app_id:?}. This should be the App ID, not its client ID."
+                ),
+            });
+        }
+
+        let target_repos = std::env::var("GITHUB_TARGET_REPOS")
+            .unwrap_or("typst/packages".to_owned())
+            .split(',')
+            .map(|repo| repo.trim().to_owned())
+            .filter(|repo| !repo.is_empty())
+            .collect::<Vec<_>>();
+        if target_repos.is_empty() {
+            errors.push(ConfigError::Invalid {
+                var: "GITHUB_TARGET_REPOS",
+                reason: "doesn't contain any repository.".to_owned(),
+            });
+        } else if let Some(repo) = target_repos.iter().find(|repo| !repo.contains('/')) {
+            errors.push(ConfigError::Invalid {
+                var: "GITHUB_TARGET_REPOS",
+                reason: format!("{repo:?} is not of the form `owner/name`."),
+            });
+        }
+
+        let mut limit = |var: &'static str, default: usize| match std::env::var(var) {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                errors.push(ConfigError::Invalid {
+                    var,
+                    reason: format!("is not a number: {value:?}."),
+                });
+                default
+            }),
+            Err(_) => default,
+        };
+        let max_new_packages = limit("MAX_NEW_PACKAGES_PER_PR", 3);
+        let max_versions_per_package = limit("MAX_VERSIONS_PER_PR", 2);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(AppState {
+            webhook_secret: webhook_secret.into_bytes(),
+            private_key,
+            app_id,
+            git_dir,
+            // The API is somewhere else for GitHub Enterprise Server.
+            api_url: std::env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_owned()),
+            check_command: std::env::var("CHECK_COMMAND").unwrap_or("/check".to_owned()),
+            installation_tokens: Default::default(),
+            bot_login: Default::default(),
+            fail_on_warnings: std::env::var("FAIL_ON_WARNINGS").is_ok_and(|v| v == "true"),
+            in_flight_checks: Default::default(),
+            target_repos,
+            recent_deliveries: Default::default(),
+            recent_checks: Default::default(),
+            max_new_packages,
+            max_versions_per_package,
+        })
+    }
+
+    /// The default configuration, for tests that don't need to authenticate
+    /// with GitHub.
+    #[cfg(test)]
+    pub fn for_tests(webhook_secret: &str) -> Self {
+        AppState {
+            webhook_secret: webhook_secret.as_bytes().to_owned(),
+            private_key: String::new(),
+            app_id: "1".to_owned(),
+            git_dir: std::env::temp_dir().display().to_string(),
+            api_url: "https://api.github.com".to_owned(),
+            check_command: "/check".to_owned(),
+            installation_tokens: Default::default(),
+            bot_login: std::sync::Arc::new(tokio::sync::OnceCell::new_with(Some(
+                BOT_LOGIN.to_owned(),
+            ))),
+            fail_on_warnings: false,
+            in_flight_checks: Default::default(),
+            target_repos: vec!["typst/packages".to_owned()],
+            recent_deliveries: Default::default(),
+            recent_checks: Default::default(),
+            max_new_packages: 3,
+            max_versions_per_package: 2,
+        }
+    }
+}
+
+/// Some platforms don't support multi-line environment variables: line breaks
+/// in the private key can be replaced with `&`.
+///
+/// The key is parsed to make sure it is valid, as this is easy to get wrong.
+fn normalize_private_key(raw: &str) -> Result<String, String> {
+    let key = raw.trim().replace('&', "\n");
+    if !key.starts_with("-----BEGIN") {
+        return Err("is not valid PEM: it should start with `-----BEGIN`. \
+            Make sure it contains the key itself, not the path to the key file."
+            .to_owned());
+    }
+    if !key.contains('\n') {
+        return Err("is not valid PEM: it is on a single line. \
+            Line breaks can be replaced with `&`."
+            .to_owned());
+    }
+
+    RS256KeyPair::from_pem(&key).map_err(|e| format!("is not valid PEM: {e}"))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A key that was generated for these tests only.
+    const KEY: &str = include_str!("../../tests/fixtures/test-private-key.pem");
+
+    #[test]
+    fn keys_can_be_on_a_single_line() {
+        let munged = KEY.trim().replace('\n', "&");
+        assert!(!munged.contains('\n'));
+        assert_eq!(normalize_private_key(&munged).unwrap(), KEY.trim());
+        assert_eq!(normalize_private_key(KEY).unwrap(), KEY.trim());
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        let error = normalize_private_key("/secrets/private-key.pem").unwrap_err();
+        assert!(error.contains("`-----BEGIN`"));
+
+        let single_line = KEY.trim().replace('\n', "");
+        let error = normalize_private_key(&single_line).unwrap_err();
+        assert!(error.contains("single line"));
+
+        // Truncated keys can't be parsed.
+        let truncated = KEY.lines().take(3).collect::<Vec<_>>().join("&");
+        let error = normalize_private_key(&truncated).unwrap_err();
+        assert!(error.starts_with("is not valid PEM: "));
+    }
+}
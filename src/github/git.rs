@@ -16,6 +16,27 @@ pub fn repo_dir() -> PathBuf {
     PathBuf::from(repo_path)
 }
 
+/// The package a file of the repository belongs to, if any.
+pub fn package_of(path: &Path) -> Option<PackageSpec> {
+    let mut components = path.components();
+    if components.next()?.as_os_str() != "packages" {
+        return None;
+    }
+
+    Some(PackageSpec {
+        namespace: components.next()?.as_os_str().to_str()?.into(),
+        name: components.next()?.as_os_str().to_str()?.into(),
+        version: components.next()?.as_os_str().to_str()?.parse().ok()?,
+    })
+}
+
+/// A file that was modified by a commit.
+pub struct TouchedFile {
+    pub path: PathBuf,
+    /// Whether the file was deleted by the commit.
+    pub deleted: bool,
+}
+
 pub struct GitRepo<'a> {
     dir: &'a Path,
 }
@@ -98,7 +119,7 @@ impl<'a> GitRepo<'a> {
         Ok(())
     }
 
-    pub async fn files_touched_by(&self, sha: impl AsRef<str>) -> eyre::Result<Vec<PathBuf>> {
+    pub async fn files_touched_by(&self, sha: impl AsRef<str>) -> eyre::Result<Vec<TouchedFile>> {
         debug!("Listing files touched by {}", sha.as_ref());
         let command_output = String::from_utf8(
             Command::new("git")
@@ -107,7 +128,7 @@ impl<'a> GitRepo<'a> {
                     self.dir()?,
                     "diff-tree",
                     "--no-commit-id",
-                    "--name-only",
+                    "--name-status",
                     "-r",
                     "--merge-base",
                     "main",
@@ -120,12 +141,80 @@ impl<'a> GitRepo<'a> {
 
         debug!("Done");
 
+        // Each line is a status letter, followed by the path, separated by a
+        // tab.
         Ok(command_output
             .lines()
-            .map(|l| Path::new(l).to_owned())
+            .filter_map(|l| {
+                let (status, path) = l.split_once('\t')?;
+                Some(TouchedFile {
+                    path: Path::new(path).to_owned(),
+                    deleted: status.starts_with('D'),
+                })
+            })
             .collect())
     }
 
+    /// Lists the files that differ between the working tree (including
+    /// untracked files) and the merge base of `base` and `HEAD`.
+    ///
+    /// Only the files in the directory of this repository object are listed,
+    /// and their paths are relative to it. This directory doesn't need to be
+    /// the root of the repository.
+    pub async fn files_changed_since(&self, base: &str) -> eyre::Result<Vec<TouchedFile>> {
+        debug!("Listing files changed since {base}");
+        let output = traced_git([
+            "-C",
+            self.dir()?,
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            "--merge-base",
+            base,
+        ])
+        .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "git diff failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        // Each line is a status letter, followed by the path, separated by a
+        // tab.
+        let mut files: Vec<_> = String::from_utf8(output.stdout)?
+            .lines()
+            .filter_map(|l| {
+                let (status, path) = l.split_once('\t')?;
+                Some(TouchedFile {
+                    path: Path::new(path).to_owned(),
+                    deleted: status.starts_with('D'),
+                })
+            })
+            .collect();
+
+        let untracked = traced_git([
+            "-C",
+            self.dir()?,
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+        ])
+        .await?;
+        files.extend(
+            String::from_utf8(untracked.stdout)?
+                .lines()
+                .map(|path| TouchedFile {
+                    path: Path::new(path).to_owned(),
+                    deleted: false,
+                }),
+        );
+
+        debug!("Done");
+        Ok(files)
+    }
+
     pub fn authors_of(&self, file: &Path) -> Option<HashSet<String>> {
         use std::process::Command;
 
@@ -195,6 +284,15 @@ impl<'a> GitRepo<'a> {
             .context("Directory name is not valid unicode")
     }
 
+    /// The directory of a package in this repository.
+    pub fn package_dir(&self, package: &PackageSpec) -> PathBuf {
+        self.dir
+            .join("packages")
+            .join(package.namespace.as_str())
+            .join(package.name.as_str())
+            .join(package.version.to_string())
+    }
+
     pub async fn has_previous_version(&self, package: &PackageSpec) -> eyre::Result<bool> {
         let package_dir = PathBuf::from(self.dir()?)
             .join("packages")
@@ -0,0 +1,232 @@
+//! Review comments suggesting fixes, that authors can apply in one click.
+
+use std::collections::{HashMap, HashSet};
+
+use typst::syntax::package::PackageSpec;
+
+use crate::{check::Suggestion, world::SystemWorld};
+
+use super::api::{
+    pr::{PullRequestFile, ReviewComment},
+    ApiError, AuthInstallation, GitHub, Repository,
+};
+
+/// Hidden marker used to recognize the suggestions that were already posted.
+const MARKER: &str = "<!-- typst-package-check:suggestion -->";
+
+/// Maximum number of suggestions posted on a single pull request, to avoid
+/// flooding it.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Turns a suggestion into a review comment, that replaces the whole line.
+///
+/// Only suggestions that fit on a single line are supported.
+pub fn review_comment(
+    world: &SystemWorld,
+    package: &PackageSpec,
+    suggestion: &Suggestion,
+) -> Option<ReviewComment> {
+    let source = world.lookup(suggestion.file_id).ok()?;
+    let line = source.byte_to_line(suggestion.range.start)?;
+    if source.byte_to_line(suggestion.range.end)? != line {
+        return None;
+    }
+
+    let text = source.text();
+    let line_range = source.line_to_range(line)?;
+    let line_end = line_range.start
+        + text[line_range.clone()]
+            .trim_end_matches(['\r', '\n'])
+            .len();
+    let new_line = format!(
+        "{}{}{}",
+        text.get(line_range.start..suggestion.range.start)?,
+        suggestion.replacement,
+        text.get(suggestion.range.end..line_end)?,
+    );
+
+    Some(ReviewComment {
+        path: super::annotation_path(world, package, suggestion.file_id)?,
+        line: Some(line + 1),
+        body: format!(
+            "{}\n\n```suggestion\n{}\n```\n{}",
+            suggestion.message, new_line, MARKER
+        ),
+    })
+}
+
+/// Posts the suggestions that are on lines of the diff of the pull request,
+/// as GitHub doesn't accept review comments on other lines.
+pub async fn post(
+    api_client: &GitHub<AuthInstallation>,
+    repository: &Repository,
+    pr: usize,
+    head_sha: &str,
+    suggestions: Vec<ReviewComment>,
+) -> Result<(), ApiError> {
+    if suggestions.is_empty() {
+        return Ok(());
+    }
+
+    let files = api_client
+        .list_pr_files(repository.owner(), repository.name(), pr)
+        .await?;
+    let diff_lines = lines_in_diff(&files);
+    let existing = api_client
+        .list_review_comments(repository.owner(), repository.name(), pr)
+        .await?;
+
+    let mut posted = existing.iter().filter(|c| c.body.contains(MARKER)).count();
+    for suggestion in suggestions {
+        if posted >= MAX_SUGGESTIONS {
+            break;
+        }
+
+        let in_diff = suggestion.line.is_some_and(|line| {
+            diff_lines
+                .get(suggestion.path.as_str())
+                .is_some_and(|lines| lines.contains(&line))
+        });
+        let already_posted = existing
+            .iter()
+            .any(|c| c.path == suggestion.path && c.body == suggestion.body);
+        if !in_diff || already_posted {
+            continue;
+        }
+
+        api_client
+            .create_review_comment(
+                repository.owner(),
+                repository.name(),
+                pr,
+                head_sha,
+                &suggestion,
+            )
+            .await?;
+        posted += 1;
+    }
+
+    Ok(())
+}
+
+/// The lines of the new version of each file that appear in the diff.
+fn lines_in_diff(files: &[PullRequestFile]) -> HashMap<&str, HashSet<usize>> {
+    files
+        .iter()
+        .filter_map(|file| Some((file.filename.as_str(), lines_in_patch(file.patch.as_ref()?))))
+        .collect()
+}
+
+/// The lines of the new version of a file that appear in a patch, be they
+/// added or only there for context.
+fn lines_in_patch(patch: &str) -> HashSet<usize> {
+    let mut lines = HashSet::new();
+    let mut next_line = None;
+    for line in patch.lines() {
+        // Hunk headers look like `@@ -old_start,old_len +new_start,new_len @@`.
+        if let Some(header) = line.strip_prefix("@@ ") {
+            next_line = header
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next()?.parse::<usize>().ok());
+            continue;
+        }
+
+        let Some(next_line) = next_line.as_mut() else {
+            continue;
+        };
+        // Removed lines only exist in the old version, and `\` introduces
+        // "No newline at end of file".
+        if line.starts_with('-') || line.starts_with('\\') {
+            continue;
+        }
+        lines.insert(*next_line);
+        *next_line += 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+
+    use super::*;
+    use crate::github::api::fake::FakeApi;
+
+    #[test]
+    fn lines_of_several_hunks() {
+        let patch = "\
+@@ -1,3 +1,4 @@
+ #let a = 1
+-#let b = 2
++#let b = 3
++#let c = 4
+ #let d = 5
+@@ -10,2 +11,3 @@ #let e = 0
+ #let f = 6
++#let g = 7
+ #let h = 8
+\\ No newline at end of file";
+        assert_eq!(
+            lines_in_patch(patch),
+            HashSet::from([1, 2, 3, 4, 11, 12, 13])
+        );
+    }
+
+    #[test]
+    fn removed_lines_are_not_in_the_new_version() {
+        assert_eq!(
+            lines_in_patch("@@ -5,2 +4,0 @@\n-#let a = 1\n-#let b = 2"),
+            HashSet::new()
+        );
+
+        let patch = "\
+@@ -1 +1,2 @@
+-#let a = 1
+\\ No newline at end of file
++#let a = 2
++#let b = 3";
+        assert_eq!(lines_in_patch(patch), HashSet::from([1, 2]));
+    }
+
+    fn suggestion(path: &str, line: usize) -> ReviewComment {
+        ReviewComment {
+            path: path.to_owned(),
+            line: Some(line),
+            body: format!("Line {line}\n\n```suggestion\n#let a = 1\n```\n{MARKER}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn suggestions_outside_of_the_diff_are_not_posted() {
+        let api = FakeApi::start().await;
+        api.respond(
+            Method::GET,
+            "repos/typst/packages/pulls/1/files",
+            StatusCode::OK,
+            serde_json::json!([{
+                "filename": "packages/preview/foo/0.1.0/lib.typ",
+                "patch": "@@ -0,0 +1,2 @@\n+#let a=1\n+#let b=2",
+            }, {
+                "filename": "packages/preview/foo/0.1.0/logo.png",
+            }]),
+        );
+
+        let repository = Repository::new("typst/packages").unwrap();
+        let suggestions = vec![
+            suggestion("packages/preview/foo/0.1.0/lib.typ", 10),
+            suggestion("packages/preview/foo/0.1.0/lib.typ", 2),
+            suggestion("packages/preview/foo/0.1.0/logo.png", 1),
+            suggestion("packages/preview/foo/0.1.0/other.typ", 1),
+        ];
+        post(&api.client(), &repository, 1, "abcdef", suggestions)
+            .await
+            .unwrap();
+
+        let posted = api.requests_to(Method::POST, "repos/typst/packages/pulls/1/comments");
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0]["path"], "packages/preview/foo/0.1.0/lib.typ");
+        assert_eq!(posted[0]["line"], 2);
+        assert_eq!(posted[0]["commit_id"], "abcdef");
+    }
+}
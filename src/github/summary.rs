@@ -0,0 +1,121 @@
+//! Summary of the checks, posted as a comment on pull requests.
+
+use typst::syntax::package::PackageSpec;
+
+use super::api::check::Annotation;
+
+/// Hidden marker used to find the comment that was previously posted on a
+/// pull request, to update it instead of posting a new one.
+pub const MARKER: &str = "<!-- typst-package-check -->";
+
+/// Maximum number of findings listed for each package.
+const MAX_FINDINGS: usize = 3;
+
+/// Comments longer than that are rejected by GitHub. Some margin is kept for
+/// the last line of the comment.
+const MAX_COMMENT_LEN: usize = 65536 - 1024;
+
+/// The results of the checks for a single package.
+pub struct PackageReport {
+    pub package: PackageSpec,
+    pub errors: usize,
+    pub warnings: usize,
+    /// The most important findings, errors first.
+    pub top_findings: Vec<Finding>,
+}
+
+pub struct Finding {
+    pub message: String,
+    pub path: String,
+    pub line: usize,
+}
+
+impl PackageReport {
+    pub fn new<'a>(
+        package: &PackageSpec,
+        errors: usize,
+        warnings: usize,
+        annotations: impl IntoIterator<Item = &'a Annotation>,
+    ) -> Self {
+        Self {
+            package: package.clone(),
+            errors,
+            warnings,
+            top_findings: annotations
+                .into_iter()
+                .take(MAX_FINDINGS)
+                .map(|a| Finding {
+                    message: a.message.clone(),
+                    path: a.path.clone(),
+                    line: a.start_line,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Renders the summary comment.
+///
+/// `repository` is the full name of the repository (`owner/name`), and `sha`
+/// the commit that was checked, to link to the files in which issues were
+/// found.
+pub fn render_comment(reports: &mut [PackageReport], repository: &str, sha: &str) -> String {
+    // Always use the same order, to avoid updating the comment when nothing
+    // changed.
+    reports.sort_by(|a, b| {
+        (&a.package.namespace, &a.package.name, &a.package.version).cmp(&(
+            &b.package.namespace,
+            &b.package.name,
+            &b.package.version,
+        ))
+    });
+
+    let mut comment = format!(
+        "{MARKER}\n\
+        ### Automated checks\n\n\
+        | Package | Errors | Warnings | Main findings |\n\
+        |---------|--------|----------|---------------|\n"
+    );
+
+    for (i, report) in reports.iter().enumerate() {
+        let findings = report
+            .top_findings
+            .iter()
+            .map(|f| {
+                format!(
+                    "[{path}:{line}](https://github.com/{repository}/blob/{sha}/{path}#L{line}): {message}",
+                    path = f.path,
+                    line = f.line,
+                    message = escape_cell(&f.message),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("<br>");
+        let row = format!(
+            "| `@{}/{}:{}` | {} | {} | {} |\n",
+            report.package.namespace,
+            report.package.name,
+            report.package.version,
+            report.errors,
+            report.warnings,
+            findings,
+        );
+
+        if comment.len() + row.len() > MAX_COMMENT_LEN {
+            comment += &format!(
+                "\n…and {} more package(s), see the checks tab for details.\n",
+                reports.len() - i
+            );
+            return comment;
+        }
+        comment += &row;
+    }
+
+    comment += "\nThis comment is updated automatically when new commits are pushed.\n";
+    comment
+}
+
+/// Makes a message fit in a single cell of a Markdown table.
+fn escape_cell(message: &str) -> String {
+    message.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,45 @@
+//! The HTTP client shared by the GitHub API and by the checks.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Prevents the checks from making network requests, for `--offline`.
+pub fn set_offline() {
+    OFFLINE.store(true, Ordering::Relaxed)
+}
+
+/// Whether the checks should avoid network requests.
+///
+/// This is the case with `--offline`, or when the `PACKAGE_CHECK_OFFLINE`
+/// environment variable is set to anything else than `0`.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+        || std::env::var_os("PACKAGE_CHECK_OFFLINE")
+            .is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The HTTP client to use for all requests.
+///
+/// A single client is shared so that connections are pooled. Proxies set with
+/// the usual environment variables (`HTTPS_PROXY`, `HTTP_PROXY`, `NO_PROXY`…)
+/// are used.
+pub fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("typst-package-check/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .pool_max_idle_per_host(8)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .build()
+            .expect("Can't create the HTTP client")
+    })
+}
@@ -1,13 +1,18 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use codespan_reporting::diagnostic::Severity;
 use tracing_subscriber::EnvFilter;
 
 mod check;
 mod cli;
+mod completions;
 mod github;
+mod http;
 mod package;
 mod world;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     dotenvy::dotenv().ok();
 
     if std::env::var("LOG_STYLE").as_deref().unwrap_or("human") == "json" {
@@ -24,23 +29,332 @@ async fn main() {
     let mut args = std::env::args();
     let cmd = args.next();
     let subcommand = args.next();
-    if Some("server") == subcommand.as_deref() {
-        github::hook_server().await;
+    if matches!(subcommand.as_deref(), Some("serve" | "server")) {
+        let mut port = 7878;
+        while let Some(arg) = args.next() {
+            if arg == "--port" {
+                let Some(p) = args.next().and_then(|p| p.parse().ok()) else {
+                    eprintln!("--port should be followed by a valid port number.");
+                    std::process::exit(1);
+                };
+                port = p;
+            }
+        }
+        github::hook_server(port).await
     } else if Some("check") == subcommand.as_deref() {
-        cli::main(args.next().unwrap_or_default()).await;
+        let mut packages = Vec::new();
+        let mut specs_from = None;
+        let mut changed = false;
+        let mut all = false;
+        let mut namespace = None;
+        let mut since = None;
+        let mut options = cli::CheckOptions::default();
+        let mut as_spec = None;
+        let mut version = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--changed" => changed = true,
+                "--all" => all = true,
+                "--version" => {
+                    let Some(v) = args.next().and_then(|v| v.parse().ok()) else {
+                        eprintln!("--version should be followed by a version, like 1.0.0.");
+                        std::process::exit(1);
+                    };
+                    version = Some(v);
+                }
+                "--namespace" => {
+                    let Some(ns) = args.next() else {
+                        eprintln!("--namespace should be followed by a namespace, like preview.");
+                        std::process::exit(1);
+                    };
+                    namespace = Some(ns);
+                }
+                "--timings" => options.timings = true,
+                "--json" => options.json = true,
+                "--format" => match args.next().as_deref() {
+                    Some("text") => {}
+                    Some("json") => options.json = true,
+                    Some("junit") => options.junit = true,
+                    _ => {
+                        eprintln!("--format should be followed by text, json or junit.");
+                        std::process::exit(1);
+                    }
+                },
+                "--report-file" => {
+                    let Some(path) = args.next() else {
+                        eprintln!("--report-file should be followed by a path.");
+                        std::process::exit(1);
+                    };
+                    options.report_file = Some(PathBuf::from(path));
+                }
+                "--ignore-warnings" => options.ignore_warnings = true,
+                "--fix" => options.fix = true,
+                "--fail-fast" => options.fail_fast = true,
+                "--color" => {
+                    options.color = match args.next().as_deref() {
+                        Some("auto") => cli::Color::Auto,
+                        Some("always") => cli::Color::Always,
+                        Some("never") => cli::Color::Never,
+                        _ => {
+                            eprintln!("--color should be followed by auto, always or never.");
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--verbose-diagnostics" => options.verbose_diagnostics = true,
+                "--offline" => http::set_offline(),
+                "--allow" | "--warn" | "--deny" => {
+                    let Some(code) = args.next() else {
+                        eprintln!("{arg} should be followed by a diagnostic code.");
+                        std::process::exit(1);
+                    };
+                    if check::explanations::find(&code).is_none() {
+                        eprintln!("Unknown diagnostic code: {code}. Valid codes are:");
+                        for explanation in check::explanations::EXPLANATIONS {
+                            eprintln!("  {}", explanation.code);
+                        }
+                        std::process::exit(1);
+                    }
+                    let severity = match arg.as_str() {
+                        "--allow" => None,
+                        "--warn" => Some(Severity::Warning),
+                        _ => Some(Severity::Error),
+                    };
+                    options.severities.push((code, severity));
+                }
+                "--as" => {
+                    let Some(spec) = args.next().and_then(|spec| spec.parse().ok()) else {
+                        eprintln!(
+                            "--as should be followed by a package specification, like @preview/name:1.0.0."
+                        );
+                        std::process::exit(1);
+                    };
+                    as_spec = Some(spec);
+                }
+                "--jobs" => {
+                    let Some(jobs) = args
+                        .next()
+                        .and_then(|jobs| jobs.parse().ok())
+                        .filter(|jobs| *jobs > 0)
+                    else {
+                        eprintln!("--jobs should be followed by a number of packages, at least 1.");
+                        std::process::exit(1);
+                    };
+                    options.jobs = Some(jobs);
+                }
+                "--changed-since" => {
+                    let Some(base) = args.next() else {
+                        eprintln!("--changed-since should be followed by a Git reference.");
+                        std::process::exit(1);
+                    };
+                    options.changed_since = Some(base);
+                }
+                "--max-warnings" => {
+                    let Some(max_warnings) = args.next().and_then(|max| max.parse().ok()) else {
+                        eprintln!("--max-warnings should be followed by a number of warnings.");
+                        std::process::exit(1);
+                    };
+                    options.max_warnings = Some(max_warnings);
+                }
+                "--font-path" => {
+                    let Some(path) = args.next() else {
+                        eprintln!("--font-path should be followed by a directory.");
+                        std::process::exit(1);
+                    };
+                    options.font_paths.push(PathBuf::from(path));
+                }
+                "--specs-from" => {
+                    let Some(path) = args.next() else {
+                        eprintln!("--specs-from should be followed by a path, or - for stdin.");
+                        std::process::exit(1);
+                    };
+                    specs_from = Some(path);
+                }
+                "--since" => {
+                    let Some(base) = args.next() else {
+                        eprintln!("--since should be followed by a Git reference.");
+                        std::process::exit(1);
+                    };
+                    since = Some(base);
+                }
+                _ => packages.push(arg),
+            }
+        }
+        if options.json && options.junit {
+            eprintln!("Only one of the JSON and JUnit formats can be used.");
+            std::process::exit(1);
+        }
+        if changed {
+            cli::check_changed(since, options).await
+        } else if all {
+            cli::check_all(namespace, since, options).await
+        } else if since.is_some() {
+            eprintln!("--since can only be used with --changed or --all.");
+            std::process::exit(1);
+        } else if namespace.is_some() {
+            eprintln!("--namespace can only be used with --all.");
+            std::process::exit(1);
+        } else if as_spec.is_some() && (specs_from.is_some() || packages.len() > 1) {
+            eprintln!("--as can only be used to check a single package.");
+            std::process::exit(1);
+        } else if version.is_some() && (specs_from.is_some() || packages.len() > 1) {
+            eprintln!("--version can only be used to check a single package.");
+            std::process::exit(1);
+        } else if specs_from.is_some() || packages.len() > 1 {
+            cli::check_list(packages, specs_from, options).await
+        } else {
+            cli::main(
+                packages.pop().unwrap_or_default(),
+                options,
+                as_spec,
+                version,
+            )
+            .await
+        }
+    } else if Some("bundle") == subcommand.as_deref() {
+        let mut package = String::new();
+        let mut output = PathBuf::from("package.tar.gz");
+        let mut force = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--force" => force = true,
+                "--output" => {
+                    let Some(o) = args.next() else {
+                        eprintln!("--output should be followed by a path.");
+                        std::process::exit(1);
+                    };
+                    output = PathBuf::from(o);
+                }
+                _ => package = arg,
+            }
+        }
+        cli::bundle(package, output, force).await
+    } else if Some("show-excluded") == subcommand.as_deref() {
+        let mut package = String::new();
+        let mut json = false;
+        for arg in args {
+            match arg.as_str() {
+                "--json" => json = true,
+                _ => package = arg,
+            }
+        }
+        cli::show_excluded(package, json).await
+    } else if Some("deps") == subcommand.as_deref() {
+        let mut package = String::new();
+        let mut json = false;
+        for arg in args {
+            match arg.as_str() {
+                "--json" => json = true,
+                _ => package = arg,
+            }
+        }
+        cli::deps(package, json).await
+    } else if Some("explain") == subcommand.as_deref() {
+        cli::explain(args.next().filter(|arg| arg != "--list"))
+    } else if Some("list-checks") == subcommand.as_deref() {
+        cli::list_checks(args.any(|arg| arg == "--json"))
+    } else if Some("typst-version") == subcommand.as_deref() {
+        cli::typst_version(args.any(|arg| arg == "--json"))
+    } else if Some("completions") == subcommand.as_deref() {
+        completions::main(args.next())
     } else {
         show_help(&cmd.unwrap_or("typst-package-check".to_owned()));
+        ExitCode::SUCCESS
     }
 }
 
 fn show_help(program: &str) {
     println!("Usage :");
-    println!("  {program} server");
+    println!("  {program} serve [--port PORT]");
     println!("    Start a server to handle GitHub webhooks and report checks in pull requests.");
+    println!("    The server listens on port 7878 by default.");
     println!("  {program} check @preview/PACKAGE:VERSION");
     println!(
         "    Check a local package at the specified version. To be run in typst/packages/packages."
     );
+    println!("  {program} check @preview/PACKAGE [--version VERSION]");
+    println!("    Same as above, with the latest version of the package, or VERSION.");
+    println!("  {program} check packages/preview/PACKAGE/VERSION");
+    println!("    Same as above, with the path of the package in typst/packages.");
+    println!("  {program} check --changed [--since REF]");
+    println!(
+        "    Check the packages changed since REF (the main branch by default), including uncommitted files."
+    );
+    println!("    To be run in typst/packages/packages, like the pull request checks.");
+    println!("  {program} check --all [--namespace NAMESPACE] [--since REF]");
+    println!("    Check all the packages of typst/packages, or the ones in NAMESPACE, or the ones");
+    println!("    changed since REF, and print a table of the results.");
     println!("  {program} check");
     println!("    Check the package in the current directory.");
+    println!("  {program} check [DIRECTORY] --as @preview/PACKAGE:VERSION");
+    println!(
+        "    Check a package in any directory, as if it was submitted with this name and version."
+    );
+    println!("  {program} check ARCHIVE.tar.gz [--as @preview/PACKAGE:VERSION]");
+    println!("    Check the package in an archive, like the ones created by the bundle command.");
+    println!("  {program} check [PACKAGE...] --specs-from FILE");
+    println!("    Check several packages, listed one per line in FILE (or stdin if FILE is -).");
+    println!(
+        "    With several packages, --jobs N checks N of them at the same time (JOBS by default)."
+    );
+    println!(
+        "  All the check commands accept --timings, to show how long each step of the checks took,"
+    );
+    println!("  and --json, to print one JSON object per diagnostic and per line instead of text.");
+    println!("  With both, the timings are printed as a last JSON object for each package.");
+    println!("  --format junit prints a JUnit XML report instead, with a test suite per package");
+    println!(
+        "  and a test case per diagnostic code. With --report-file PATH, it is written to PATH"
+    );
+    println!(
+        "  and the text output is kept. Without --format junit, --report-file PATH writes a JSON"
+    );
+    println!(
+        "  summary of the run to PATH: counts by severity and code, and durations, per package."
+    );
+    println!("  With --ignore-warnings, they exit successfully if only warnings were found.");
+    println!("  --allow CODE, --warn CODE and --deny CODE hide the diagnostics with this code,");
+    println!("  or report them as warnings or errors. They can be repeated.");
+    println!(
+        "  --font-path DIR adds a directory of fonts for the compilation. It can be repeated."
+    );
+    println!(
+        "  --changed-since REF only reports the diagnostics about files that changed since REF,"
+    );
+    println!("  or that are not about a specific file. This applies to --json too.");
+    println!("  --verbose-diagnostics adds the explanation of their code to the diagnostics.");
+    println!("  --color auto|always|never sets when diagnostics are colored. By default, they are");
+    println!("  only colored in a terminal, and if the NO_COLOR environment variable is not set.");
+    println!("  --fail-fast stops the checks of a package at the first error.");
+    println!("  --offline skips the checks that need the network (PACKAGE_CHECK_OFFLINE=1 too).");
+    println!("  --fix applies the fixes that are safe (renames, exclusions, import versions),");
+    println!("  and checks the package again.");
+    println!("  {program} bundle [@preview/PACKAGE:VERSION] [--output PATH] [--force]");
+    println!(
+        "    Create the archive that would be published for a package (package.tar.gz by default),"
+    );
+    println!(
+        "    and list the files it contains. Packages with errors are only bundled with --force."
+    );
+    println!("  {program} show-excluded [@preview/PACKAGE:VERSION] [--json]");
+    println!(
+        "    List the files of a package, and which glob of the `exclude` field matches them."
+    );
+    println!("  {program} deps [@preview/PACKAGE:VERSION] [--json]");
+    println!("    List the packages a package depends on, and whether newer versions exist.");
+    println!("  {program} explain [CODE | --list]");
+    println!("    Explain a diagnostic code in detail, or list the documented codes.");
+    println!("  {program} list-checks [--json]");
+    println!("    List the diagnostic codes of the checks, with their default severity.");
+    println!("  {program} typst-version [--json]");
+    println!("    Print the version of Typst used to compile packages.");
+    println!("  {program} completions bash|zsh|fish|powershell");
+    println!("    Print the completion script for a shell. For instance, in ~/.bashrc:");
+    println!("      eval \"$({program} completions bash)\"");
+    println!();
+    println!(
+        "The check command exits with code 1 if errors were found, 2 if only warnings were found,"
+    );
+    println!("and 3 if the checks could not be run. With --max-warnings N, more than N warnings");
+    println!("are treated like errors.");
 }
@@ -60,10 +60,28 @@ impl PackageExt for PackageSpec {
 
 pub trait VersionlessPackageExt {
     fn directory(&self) -> PathBuf;
+
+    /// The most recent version of this package in the local copy of
+    /// `typst/packages`.
+    fn latest_version(&self) -> Option<PackageVersion>;
 }
 
 impl VersionlessPackageExt for VersionlessPackageSpec {
     fn directory(&self) -> PathBuf {
         dir().join(self.namespace.as_str()).join(self.name.as_str())
     }
+
+    fn latest_version(&self) -> Option<PackageVersion> {
+        latest_version_in(&self.directory())
+    }
+}
+
+/// The most recent version in a directory containing a subdirectory for
+/// each version of a package.
+pub fn latest_version_in(versions_dir: &Path) -> Option<PackageVersion> {
+    std::fs::read_dir(versions_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|version_dir| version_dir.file_name().to_str()?.parse().ok())
+        .max()
 }
@@ -5,7 +5,8 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::OnceLock,
+    sync::{Arc, OnceLock},
+    time::SystemTime,
 };
 
 use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
@@ -34,12 +35,15 @@ pub struct SystemWorld {
     main: FileId,
     /// Typst's standard library.
     library: LazyHash<Library>,
-    /// Metadata about discovered fonts.
-    book: LazyHash<FontBook>,
-    /// Locations of and storage for lazily loaded fonts.
-    fonts: Vec<FontSlot>,
+    /// Directories in which fonts are searched, before the system fonts.
+    font_paths: Vec<PathBuf>,
+    /// Metadata about discovered fonts, and locations of and storage for
+    /// lazily loaded fonts.
+    fonts: OnceLock<Arc<Fonts>>,
     /// Maps file ids to source files and buffers.
     slots: Mutex<HashMap<FileId, FileSlot>>,
+    /// Raw contents of the files, that may be shared with other worlds.
+    store: FileStore,
     /// The current datetime if requested. This is stored here to ensure it is
     /// always the same within one compilation.
     /// Reset between compilations if not [`Now::Fixed`].
@@ -48,6 +52,15 @@ pub struct SystemWorld {
     package_override: Option<(PackageSpec, PathBuf)>,
     /// Files that are considered excluded and should not be read from.
     excluded: Override,
+    /// Whether this world simulates a project created with `typst init`, in
+    /// which case files outside of the root can't be accessed.
+    template_mode: bool,
+    /// Canonical versions of the paths that were already resolved, as
+    /// canonicalization can be slow on some file systems.
+    canonical_paths: Mutex<HashMap<PathBuf, PathBuf>>,
+    /// Files that were requested during the current compilation, but that
+    /// are outside of the root.
+    outside_root: Mutex<Vec<FileId>>,
 }
 
 impl SystemWorld {
@@ -60,20 +73,21 @@ impl SystemWorld {
 
         let library = Library::default();
 
-        let mut searcher = FontSearcher::new();
-        searcher.search(&[]);
-
         Ok(Self {
             workdir: std::env::current_dir().ok(),
             root,
             main,
             library: LazyHash::new(library),
-            book: LazyHash::new(searcher.book),
-            fonts: searcher.fonts,
+            font_paths: Vec::new(),
+            fonts: OnceLock::new(),
             slots: Mutex::new(HashMap::new()),
+            store: FileStore::default(),
             now: OnceLock::new(),
             package_override: None,
             excluded: Override::empty(),
+            template_mode: false,
+            canonical_paths: Mutex::new(HashMap::new()),
+            outside_root: Mutex::new(Vec::new()),
         })
     }
 
@@ -82,6 +96,36 @@ impl SystemWorld {
         self
     }
 
+    /// Read files through the given store, to share their contents with
+    /// other worlds using it.
+    pub fn with_file_store(mut self, store: FileStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Search fonts in these directories too, before the system fonts.
+    pub fn with_font_paths(mut self, font_paths: &[PathBuf]) -> Self {
+        self.font_paths = font_paths.to_owned();
+        self
+    }
+
+    /// The store from which this world reads files.
+    pub fn file_store(&self) -> &FileStore {
+        &self.store
+    }
+
+    /// Only allow non-package files to be read if they are in the template
+    /// directory, as other files will not be copied by `typst init`.
+    pub fn with_template_mode(mut self) -> Self {
+        self.template_mode = true;
+        self
+    }
+
+    /// Whether this world is the one of a template.
+    pub fn is_template(&self) -> bool {
+        self.template_mode
+    }
+
     /// The root relative to which absolute paths are resolved.
     pub fn root(&self) -> &Path {
         &self.root
@@ -106,6 +150,40 @@ impl SystemWorld {
         let mut slots = self.slots.lock();
         slots.clear();
     }
+
+    /// Reset the compilation state in preparation of a new compilation.
+    ///
+    /// Files are not evicted from the cache, but they will be looked up
+    /// again in the file store and compared to their previous fingerprint on
+    /// next access. Paths are canonicalized again too, as symlinks may have
+    /// changed in the meantime.
+    pub fn reset(&mut self) {
+        for slot in self.slots.get_mut().values_mut() {
+            slot.reset();
+        }
+        self.now.take();
+        self.canonical_paths.get_mut().clear();
+        self.outside_root.get_mut().clear();
+    }
+
+    /// Packages from which files were requested, since the cache was last
+    /// cleared.
+    pub fn packages_used(&self) -> Vec<PackageSpec> {
+        let mut packages: Vec<PackageSpec> = self
+            .slots
+            .lock()
+            .keys()
+            .filter_map(|id| id.package().cloned())
+            .collect();
+        packages.sort_by_key(|spec| spec.to_string());
+        packages.dedup();
+        packages
+    }
+
+    /// Files outside of the root that were requested since the last reset.
+    pub fn files_outside_root(&self) -> Vec<FileId> {
+        self.outside_root.lock().clone()
+    }
 }
 
 impl World for SystemWorld {
@@ -114,7 +192,7 @@ impl World for SystemWorld {
     }
 
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.book
+        &self.fonts().book
     }
 
     fn main(&self) -> FileId {
@@ -124,19 +202,15 @@ impl World for SystemWorld {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.slot(id, |slot| {
-            slot.source(&self.root, &self.package_override, &self.excluded)
-        })
+        self.slot(id, |slot| slot.source(self))
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.slot(id, |slot| {
-            slot.file(&self.root, &self.package_override, &self.excluded)
-        })
+        self.slot(id, |slot| slot.file(self))
     }
 
     fn font(&self, index: usize) -> Option<Font> {
-        self.fonts[index].get()
+        self.fonts().slots.get(index)?.get()
     }
 
     fn today(&self, offset: Option<i64>) -> Option<Datetime> {
@@ -160,6 +234,54 @@ impl World for SystemWorld {
 }
 
 impl SystemWorld {
+    /// Search for fonts if it was not done yet.
+    ///
+    /// Fonts are only searched for the first time they are needed, as a lot
+    /// of checks don't need to compile anything. The result is shared by all
+    /// the worlds with the same font paths, as the fonts of the system don't
+    /// change while checking several packages.
+    fn fonts(&self) -> &Fonts {
+        static SEARCHED: Mutex<Vec<(Vec<PathBuf>, Arc<Fonts>)>> = Mutex::new(Vec::new());
+
+        self.fonts.get_or_init(|| {
+            let mut searched = SEARCHED.lock();
+            if let Some((_, fonts)) = searched
+                .iter()
+                .find(|(font_paths, _)| *font_paths == self.font_paths)
+            {
+                return fonts.clone();
+            }
+
+            let mut searcher = FontSearcher::new();
+            searcher.search(&self.font_paths);
+            let fonts = Arc::new(Fonts {
+                book: LazyHash::new(searcher.book),
+                slots: searcher.fonts,
+            });
+            searched.push((self.font_paths.clone(), fonts.clone()));
+            fonts
+        })
+    }
+
+    /// Whether fonts were already searched for this world.
+    #[cfg(test)]
+    pub fn searched_fonts(&self) -> bool {
+        self.fonts.get().is_some()
+    }
+
+    /// Canonicalize a path, reusing the result of previous calls for the same
+    /// path.
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+        let mut cache = self.canonical_paths.lock();
+        if let Some(canonical) = cache.get(path) {
+            return Some(canonical.clone());
+        }
+
+        let canonical = path.canonicalize().ok()?;
+        cache.insert(path.to_owned(), canonical.clone());
+        Some(canonical)
+    }
+
     /// Access the canonical slot for the given file id.
     fn slot<F, T>(&self, id: FileId, f: F) -> T
     where
@@ -170,6 +292,58 @@ impl SystemWorld {
     }
 }
 
+/// Raw contents of files read from disk, indexed by their path.
+///
+/// It can be shared by multiple worlds, for instance the one of a package and
+/// the one of its template, that will then read the same files only once and
+/// observe the same contents. Files are read again if they changed on disk
+/// since, so that long-lived worlds don't check stale contents. Exclusions are
+/// not handled here, each world decides on its own if a file can be read
+/// before looking it up.
+#[derive(Clone, Default)]
+pub struct FileStore(Arc<Mutex<HashMap<PathBuf, (Option<FileVersion>, FileResult<Bytes>)>>>);
+
+impl FileStore {
+    /// Read a file, or reuse its contents if it was already read and didn't
+    /// change since then.
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        let version = FileVersion::of(path);
+        if let Some((read_version, contents)) = self.0.lock().get(path) {
+            if *read_version == version {
+                debug!("Reusing the contents of {}", path.display());
+                return contents.clone();
+            }
+        }
+
+        // The lock is not held while reading, so that other files can be
+        // read at the same time.
+        let contents = read_from_disk(path).map(Bytes::from);
+        self.0
+            .lock()
+            .insert(path.to_owned(), (version, contents.clone()));
+        contents
+    }
+}
+
+/// What tells that a file changed on disk, without reading it.
+#[derive(PartialEq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    /// Timestamps can be too coarse to see quick successive changes, but
+    /// these changes often affect the size of the file too.
+    len: u64,
+}
+
+impl FileVersion {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileVersion {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
 /// Holds the processed data for a file ID.
 ///
 /// Both fields can be populated if the file is both imported and read().
@@ -192,15 +366,17 @@ impl FileSlot {
         }
     }
 
+    /// Marks the file as not yet accessed in preparation of the next
+    /// compilation.
+    fn reset(&mut self) {
+        self.source.reset();
+        self.file.reset();
+    }
+
     /// Retrieve the source for this file.
-    fn source(
-        &mut self,
-        project_root: &Path,
-        package_override: &Option<(PackageSpec, PathBuf)>,
-        excluded: &Override,
-    ) -> FileResult<Source> {
+    fn source(&mut self, world: &SystemWorld) -> FileResult<Source> {
         self.source.get_or_init(
-            || read(self.id, project_root, package_override, excluded),
+            || read(self.id, world),
             |data, prev| {
                 let text = decode_utf8(&data)?;
                 if let Some(mut prev) = prev {
@@ -214,16 +390,9 @@ impl FileSlot {
     }
 
     /// Retrieve the file's bytes.
-    fn file(
-        &mut self,
-        project_root: &Path,
-        package_override: &Option<(PackageSpec, PathBuf)>,
-        excluded: &Override,
-    ) -> FileResult<Bytes> {
-        self.file.get_or_init(
-            || read(self.id, project_root, package_override, excluded),
-            |data, _| Ok(data.into()),
-        )
+    fn file(&mut self, world: &SystemWorld) -> FileResult<Bytes> {
+        self.file
+            .get_or_init(|| read(self.id, world), |data, _| Ok(data))
     }
 }
 
@@ -247,11 +416,17 @@ impl<T: Clone> SlotCell<T> {
         }
     }
 
+    /// Marks the cell as not yet accessed in preparation of the next
+    /// compilation.
+    fn reset(&mut self) {
+        self.accessed = false;
+    }
+
     /// Gets the contents of the cell or initialize them.
     fn get_or_init(
         &mut self,
-        load: impl FnOnce() -> FileResult<Vec<u8>>,
-        f: impl FnOnce(Vec<u8>, Option<T>) -> FileResult<T>,
+        load: impl FnOnce() -> FileResult<Bytes>,
+        f: impl FnOnce(Bytes, Option<T>) -> FileResult<T>,
     ) -> FileResult<T> {
         // If we accessed the file already in this compilation, retrieve it.
         if std::mem::replace(&mut self.accessed, true) {
@@ -281,23 +456,33 @@ impl<T: Clone> SlotCell<T> {
 
 /// Resolves the path of a file id on the system, downloading a package if
 /// necessary.
-fn system_path(
-    package_override: &Option<(PackageSpec, PathBuf)>,
-    project_root: &Path,
-    excluded: &Override,
-    id: FileId,
-) -> FileResult<PathBuf> {
+fn system_path(world: &SystemWorld, id: FileId) -> FileResult<PathBuf> {
     let _ = span!(Level::DEBUG, "Path resolution").enter();
     debug!("File ID = {:?}", id);
+    let project_root = world.root();
+    let package_override = &world.package_override;
+    let excluded = &world.excluded;
     let exclude = |file: FileResult<PathBuf>| match file {
         Ok(f) => {
-            if let Ok(canonical_path) = f.canonicalize() {
-                if excluded.matched(canonical_path, false).is_ignore() {
-                    debug!("This file is excluded");
-                    return Err(FileError::Other(Some(
-                        "This file exists but is excluded from your package.".into(),
-                    )));
-                }
+            // The exclusion globs are relative to the (canonical) package
+            // root: if the file was resolved relative to the same directory,
+            // its virtual path can be matched directly, without having to
+            // canonicalize it.
+            let matched = if id.package().is_none()
+                && world.canonicalize(project_root).as_deref() == Some(excluded.path())
+            {
+                Some(excluded.matched(id.vpath().as_rootless_path(), false))
+            } else {
+                world
+                    .canonicalize(&f)
+                    .map(|canonical_path| excluded.matched(canonical_path, false))
+            };
+
+            if matched.is_some_and(|m| m.is_ignore()) {
+                debug!("This file is excluded");
+                return Err(FileError::Other(Some(
+                    "This file exists but is excluded from your package.".into(),
+                )));
             }
 
             debug!("Resolved to {}", f.display());
@@ -332,9 +517,47 @@ fn system_path(
         .unwrap_or_else(|| prepare_package(spec))
         .map_err(FileError::Package)?
     } else {
+        if world.template_mode {
+            // Paths that can't be resolved are reported below.
+            let path = id.vpath().resolve(project_root);
+            if path.is_some_and(|path| !stays_in_root(world, &path)) {
+                debug!("This file is outside of the template directory");
+                return Err(FileError::Other(Some(
+                    format!(
+                        "{} is outside of the template directory. \
+                        It will not be copied by `typst init`, \
+                        so it won't exist in projects created from this template.",
+                        id.vpath().as_rootless_path().display()
+                    )
+                    .into(),
+                )));
+            }
+        }
+
         project_root.to_owned()
     };
-    exclude(id.vpath().resolve(&root).ok_or(FileError::AccessDenied))
+    let resolved = id.vpath().resolve(&root);
+    if resolved.is_none() && id.package().is_none() {
+        debug!("This file is outside of the root");
+        let mut outside_root = world.outside_root.lock();
+        if !outside_root.contains(&id) {
+            outside_root.push(id);
+        }
+    }
+    exclude(resolved.ok_or(FileError::AccessDenied))
+}
+
+/// Checks that a path is still in a given root directory once symbolic links
+/// have been resolved.
+///
+/// Paths that can't be canonicalized (generally because they don't exist) are
+/// considered to be in the root, to let the reading code report a more precise
+/// error.
+fn stays_in_root(world: &SystemWorld, path: &Path) -> bool {
+    match (world.canonicalize(path), world.canonicalize(world.root())) {
+        (Some(path), Some(root)) => path.starts_with(root),
+        _ => true,
+    }
 }
 
 // Goes up in a file system hierarchy while the parent folder matches the expected name
@@ -360,15 +583,10 @@ fn expect_parents<'a>(dir: &'a Path, parents: &'a [&'a str]) -> Option<PathBuf>
 
 /// Reads a file from a `FileId`.
 ///
-/// If the ID represents stdin it will read from standard input,
-/// otherwise it gets the file path of the ID and reads the file from disk.
-fn read(
-    id: FileId,
-    project_root: &Path,
-    package_override: &Option<(PackageSpec, PathBuf)>,
-    excluded: &Override,
-) -> FileResult<Vec<u8>> {
-    read_from_disk(&system_path(package_override, project_root, excluded, id)?)
+/// It gets the file path of the ID and reads the file through the store of
+/// the world.
+fn read(id: FileId, world: &SystemWorld) -> FileResult<Bytes> {
+    world.store.read(&system_path(world, id)?)
 }
 
 /// Read a file from disk.
@@ -405,6 +623,14 @@ impl std::fmt::Display for WorldCreationError {
     }
 }
 
+/// Fonts that are available to a [`SystemWorld`].
+struct Fonts {
+    /// Metadata about discovered fonts.
+    book: LazyHash<FontBook>,
+    /// Locations of and storage for lazily loaded fonts.
+    slots: Vec<FontSlot>,
+}
+
 /// Searches for fonts.
 pub struct FontSearcher {
     /// Metadata about all discovered fonts.
@@ -524,6 +750,12 @@ pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
             return Ok(dir);
         }
 
+        if crate::http::is_offline() {
+            return Err(PackageError::Other(Some(
+                "All packages are supposed to be present in the `packages` repository, or in the local cache. \
+                Packages are never downloaded offline.".into(),
+            )));
+        }
         return Err(PackageError::NetworkFailed(Some(
             "All packages are supposed to be present in the `packages` repository, or in the local cache.".into(),
         )));
@@ -531,3 +763,122 @@ pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
 
     Err(PackageError::NotFound(spec.clone()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty directory for a test, with a `template` subfolder
+    /// that is used as the root of the world, and an `assets` folder next
+    /// to it.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "typst-package-check-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("template")).unwrap();
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("template/main.typ"), "").unwrap();
+        std::fs::write(dir.join("assets/x.png"), "").unwrap();
+        dir
+    }
+
+    fn template_world(dir: &Path) -> SystemWorld {
+        let root = dir.join("template");
+        SystemWorld::new(root.join("main.typ"), root)
+            .unwrap()
+            .with_template_mode()
+    }
+
+    #[test]
+    fn files_in_the_root_stay_in_it() {
+        let dir = test_dir("stays-in-root");
+        let world = template_world(&dir);
+        assert!(stays_in_root(&world, &dir.join("template/main.typ")));
+        // Files that don't exist are reported when they are read.
+        assert!(stays_in_root(&world, &dir.join("template/missing.typ")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parent_directories_leave_the_root() {
+        let dir = test_dir("parent-dir");
+        let world = template_world(&dir);
+        assert!(!stays_in_root(
+            &world,
+            &dir.join("template/../assets/x.png")
+        ));
+        assert!(stays_in_root(
+            &world,
+            &dir.join("template/../template/main.typ")
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn changes_of_size_are_seen_with_the_same_timestamp() {
+        let dir = test_dir("same-timestamp");
+        let path = dir.join("template/main.typ");
+        let store = FileStore::default();
+        std::fs::write(&path, "= Title").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(&*store.read(&path).unwrap(), b"= Title");
+
+        std::fs::write(&path, "= Longer title").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(&*store.read(&path).unwrap(), b"= Longer title");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reset_reloads_changed_files() {
+        let dir = test_dir("reset");
+        let mut world = template_world(&dir);
+        let main = world.main();
+        assert_eq!(world.source(main).unwrap().text(), "");
+
+        std::fs::write(dir.join("template/main.typ"), "= Changed").unwrap();
+        // Within a compilation, files are only read once.
+        assert_eq!(world.source(main).unwrap().text(), "");
+        world.reset();
+        assert_eq!(world.source(main).unwrap().text(), "= Changed");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reset_resolves_symlinks_again() {
+        let dir = test_dir("reset-symlinks");
+        std::os::unix::fs::symlink(dir.join("template/main.typ"), dir.join("template/link.typ"))
+            .unwrap();
+        let mut world = template_world(&dir);
+        assert!(stays_in_root(&world, &dir.join("template/link.typ")));
+
+        std::fs::remove_file(dir.join("template/link.typ")).unwrap();
+        std::os::unix::fs::symlink(dir.join("assets/x.png"), dir.join("template/link.typ"))
+            .unwrap();
+        world.reset();
+        assert!(!stays_in_root(&world, &dir.join("template/link.typ")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_resolved() {
+        let dir = test_dir("symlinks");
+        std::os::unix::fs::symlink(dir.join("assets"), dir.join("template/assets")).unwrap();
+        std::os::unix::fs::symlink(dir.join("template/main.typ"), dir.join("template/link.typ"))
+            .unwrap();
+        let world = template_world(&dir);
+        assert!(!stays_in_root(&world, &dir.join("template/assets/x.png")));
+        assert!(stays_in_root(&world, &dir.join("template/link.typ")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,3 @@
+#import "../lib.typ": *
+
+#make-title[Demo]
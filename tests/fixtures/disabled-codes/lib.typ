@@ -0,0 +1,2 @@
+#let make-title(fontSize: 12pt, body) = text(size: fontSize, body)
+#let makeTitle = make-title
@@ -0,0 +1,5 @@
+#import "@preview/template-asset:0.1.0": report
+
+#image("../assets/x.png")
+
+#show: report